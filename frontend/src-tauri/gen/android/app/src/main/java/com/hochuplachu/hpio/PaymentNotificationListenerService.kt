@@ -8,13 +8,13 @@ import android.content.IntentFilter
 import android.os.Build
 import android.service.notification.NotificationListenerService
 import android.service.notification.StatusBarNotification
-import android.util.Log
 import com.hochuplachu.hpio.BuildConfig
 import androidx.annotation.Keep
 import androidx.localbroadcastmanager.content.LocalBroadcastManager
 import org.json.JSONArray
 import org.json.JSONObject
 import java.io.File
+import java.io.IOException
 
 @Keep
 class PaymentNotificationListenerService : NotificationListenerService() {
@@ -22,20 +22,42 @@ class PaymentNotificationListenerService : NotificationListenerService() {
     companion object {
         private const val TAG = "PaymentNotificationListener"
         private const val NOTIFICATIONS_FILE = "pending_notifications.json"
-        private const val MAX_PENDING_NOTIFICATIONS = 50
-        private const val DEDUP_FILE = "notification_dedup.json"
-        private const val DEDUP_TIME_WINDOW_MS = 60_000L // 60 seconds
+        // Файл лишь буфер: Rust переносит его в SQLite при каждом запуске и чтении,
+        // поэтому лимит защищает только от многонедельного простоя приложения
+        private const val MAX_PENDING_NOTIFICATIONS = 2000
+        // Версия записи; должна совпадать с CURRENT_SCHEMA_VERSION в schema.rs
+        private const val SCHEMA_VERSION = 2
+        // Общий с Rust (spool.rs) lock-файл: файл меняется только под ним
+        private const val LOCK_FILE = "$NOTIFICATIONS_FILE.lock"
+        private const val RECOVERED_PREFIX = "$NOTIFICATIONS_FILE.recovered-"
+        private const val LOCK_STALE_MS = 5_000L
+        private const val LOCK_TIMEOUT_MS = 2_000L
+        private const val LOCK_RETRY_MS = 10L
         const val ACTION_NEW_NOTIFICATION = "com.hochuplachu.hpio.NEW_NOTIFICATION"
 
-        private val dedupLock = Any()
-
         // Предкомпилированные regex паттерны для лучшей производительности
         private val REFUND_PATTERN = Regex("\\b(пополнен|зачислен|получен|возврат|refunded|returned)\\b")
         private val TRANSFER_PATTERN = Regex("\\b(перевод|transfer|отправлен|получателю)\\b")
-        private val PAYMENT_PATTERN = Regex("\\b(покупка|оплата|заплатили|списание|платеж|transaction|purchase|payment)\\b")
-
-        // Поддерживаемые имена пакетов для парсинга уведомлений о платежах
-        private val SUPPORTED_PACKAGES = (setOf(
+        // Кроме русского и английского: казахский, грузинский, узбекский и транслит SMS
+        private val PAYMENT_PATTERN = Regex("\\b(покупка|оплата|заплатили|списание|платеж|transaction|purchase|payment|төлем|сатып алу|to['ʻ‘’]lov|тўлов|xarid|харид|oplata|pokupka)\\b|გადახდა|შესყიდვა")
+
+        // Список отслеживаемых пакетов ведёт Rust (monitored_packages.rs)
+        private const val MONITORED_PACKAGES_FILE = "monitored_packages.json"
+        // Пакеты, чьи уведомления видели, но не сохраняли; пишет только слушатель
+        private const val SEEN_PACKAGES_FILE = "seen_packages.json"
+        private const val MAX_SEEN_PACKAGES = 50
+        private const val SEEN_PACKAGES_FLUSH_MS = 60_000L
+        // История жизни слушателя для диагностики (diagnostics.rs): "<ms> <reason>" построчно
+        private const val HEARTBEAT_LOG_FILE = "listener_heartbeats.log"
+        private const val HEARTBEAT_LOG_MAX_BYTES = 32 * 1024L
+        private const val HEARTBEAT_LOG_KEEP_LINES = 300
+        // Обычные heartbeat пишутся не чаще раза в 5 минут, события жизненного цикла — всегда
+        private const val HEARTBEAT_LOG_THROTTLE_MS = 5 * 60_000L
+        private val ROUTINE_HEARTBEATS = setOf("notification_received", "ping_received")
+
+        // Пакеты по умолчанию, пока пользователь не менял список;
+        // должны совпадать с DEFAULT_PACKAGES в monitored_packages.rs
+        private val DEFAULT_PACKAGES = setOf(
             // Тестовые
             "com.android.shell",
             // Банковские приложения
@@ -80,7 +102,14 @@ class PaymentNotificationListenerService : NotificationListenerService() {
             "ru.mtsbank.business",
             "ru.bspb.business",
             "ru.tcsbank.business",
-        ) + BuildConfig.APPLICATION_ID)
+            // Банки Казахстана, Грузии, Беларуси и Узбекистана
+            "kz.kaspi.mobile",                    // Kaspi.kz
+            "kz.halyk.onlinebank",                // Halyk
+            "ge.bog.mobilebank",                  // Bank of Georgia
+            "ge.tbcbank.mobilebank",              // TBC
+            "by.priorbank.mobile",                // Приорбанк
+            "uz.uzum.bank",                       // Uzum Bank
+        )
 
         /**
          * Notification type enumeration
@@ -126,6 +155,16 @@ class PaymentNotificationListenerService : NotificationListenerService() {
         }
     }
 
+    @Volatile
+    private var monitoredPackages: Set<String> = DEFAULT_PACKAGES
+    @Volatile
+    private var monitoredPackagesModified = -1L
+
+    private val seenPackages = mutableMapOf<String, JSONObject>()
+    private var seenPackagesLoaded = false
+    private var seenPackagesFlushedAt = 0L
+    private var heartbeatLoggedAt = 0L
+
     private val heartbeatReceiver = object : BroadcastReceiver() {
         override fun onReceive(context: Context?, intent: Intent?) {
             if (intent?.action == NotificationPermissionHelper.ACTION_SERVICE_HEARTBEAT_PING) {
@@ -139,8 +178,9 @@ class PaymentNotificationListenerService : NotificationListenerService() {
 
         sbn ?: return
 
-        // Ранний выход для неподдерживаемых пакетов для минимизации обработки
-        if (!SUPPORTED_PACKAGES.contains(sbn.packageName)) {
+        // Ранний выход для неотслеживаемых пакетов для минимизации обработки
+        if (sbn.packageName != BuildConfig.APPLICATION_ID && !monitoredPackages().contains(sbn.packageName)) {
+            recordSeenPackage(sbn)
             return
         }
 
@@ -197,19 +237,11 @@ class PaymentNotificationListenerService : NotificationListenerService() {
             text = notification.tickerText?.toString()?.trim() ?: ""
         }
 
+        // В журнал попадают только имена полей: тексты могут содержать коды
+        // подтверждения и номера карт, их маскирует redaction.rs при импорте
         try {
-            val extraKeys = extras.keySet()
-            val snapshotBuilder = StringBuilder()
-            for (key in extraKeys) {
-                val value = extras.get(key)
-                snapshotBuilder.append(key)
-                snapshotBuilder.append("=")
-                snapshotBuilder.append(value?.toString() ?: "null")
-                snapshotBuilder.append("; ")
-            }
-            val snapshot = snapshotBuilder.toString()
-            Log.d(TAG, "Notification extras: $snapshot")
-            LoggerUtil.debug(this, TAG, "Notification extras: $snapshot")
+            val extraKeys = extras.keySet().joinToString(", ")
+            LoggerUtil.debug(this, TAG, "Notification extras: $extraKeys")
         } catch (e: Exception) {
             LoggerUtil.error(this, TAG, "Failed to log notification extras", e)
         }
@@ -220,7 +252,7 @@ class PaymentNotificationListenerService : NotificationListenerService() {
         }
 
         // Логируем все уведомления от поддерживаемых пакетов для отладки
-        LoggerUtil.debug(this, TAG, "Received notification from ${sbn.packageName}: title='$title', text='$text'")
+        LoggerUtil.debug(this, TAG, "Received notification from ${sbn.packageName}: title ${title.length} chars, text ${text.length} chars")
 
         // Определяем тип уведомления (платеж/возврат/перевод/другое)
         val notificationType = detectNotificationType("$title $text")
@@ -238,70 +270,13 @@ class PaymentNotificationListenerService : NotificationListenerService() {
             }
         }
 
-        if (notificationType != NotificationType.PAYMENT) {
-            LoggerUtil.info(this, TAG, "Non-payment notification ignored: package=${sbn.packageName}, type=$notificationType, title='$title'")
-            return
-        }
-
-        // Выполняем дедупликацию и проверку
-        // Проверяем дубликаты с постоянным хранилищем
-        val notificationId = "${sbn.packageName}|$title|$text"
-        if (isDuplicate(notificationId)) {
-            LoggerUtil.debug(this, TAG, "Duplicate notification detected and skipped: $notificationId")
-            return
-        }
-
-        // Только теперь выполняем тяжелую работу (файловый I/O)
+        // Тип уходит в Rust только подсказкой: возвраты, переводы и прочее
+        // тоже сохраняются, а расход это или нет, решает classifier.rs.
+        // Дедупликация выполняется в Rust (dedup.rs): повторы сохраняются
+        // в базе со статусом duplicate, чтобы их можно было проверить
         handlePaymentNotification(sbn, title, text, notificationType)
     }
 
-    private fun isDuplicate(notificationId: String): Boolean {
-        return synchronized(dedupLock) {
-            try {
-                val dedupFile = File(filesDir, DEDUP_FILE)
-                val currentTime = System.currentTimeMillis()
-
-                val dedupData = if (dedupFile.exists() && dedupFile.length() > 0) {
-                    try {
-                        JSONObject(dedupFile.readText())
-                    } catch (e: Exception) {
-                        JSONObject()
-                    }
-                } else {
-                    JSONObject()
-                }
-
-                // Очищаем старые записи
-                val keysToRemove = mutableListOf<String>()
-                dedupData.keys().forEach { key ->
-                    val timestamp = dedupData.optLong(key, 0L)
-                    if (currentTime - timestamp > DEDUP_TIME_WINDOW_MS) {
-                        keysToRemove.add(key)
-                    }
-                }
-                keysToRemove.forEach { dedupData.remove(it) }
-
-                // Проверяем, является ли это уведомление дубликатом
-                if (dedupData.has(notificationId)) {
-                    return@synchronized true
-                }
-
-                // Добавляем текущее уведомление в дедупликацию
-                dedupData.put(notificationId, currentTime)
-
-                // Сохраняем обновленные данные дедупликации атомарно
-                val tempFile = File(filesDir, "$DEDUP_FILE.tmp")
-                tempFile.writeText(dedupData.toString())
-                tempFile.renameTo(dedupFile)
-
-                return@synchronized false
-            } catch (e: Exception) {
-                LoggerUtil.error(this, TAG, "Error checking for duplicates", e)
-                return@synchronized false
-            }
-        }
-    }
-
     private fun handlePaymentNotification(
         sbn: StatusBarNotification,
         title: String,
@@ -313,13 +288,17 @@ class PaymentNotificationListenerService : NotificationListenerService() {
             val cardMatch = Regex("(?s).*(?:\\*|\\.\\.|\\s)(\\d{4})(?:\\D|$)").find(text)
             val extractedCard = cardMatch?.groupValues?.get(1)
 
+            // Формат записи описан в schema.rs (SpoolEntry)
             val notificationData = JSONObject().apply {
+                put("schemaVersion", SCHEMA_VERSION)
                 put("packageName", sbn.packageName)
                 put("title", title)
                 put("text", text)
                 put("timestamp", System.currentTimeMillis())
-                put("notificationType", notificationType.name)
+                put("notificationType", notificationType.name.lowercase())
                 put("cardLast4", extractedCard)
+                put("notificationKey", sbn.key)
+                putNotificationExtras(this, sbn)
             }
 
             saveNotification(notificationData)
@@ -329,51 +308,229 @@ class PaymentNotificationListenerService : NotificationListenerService() {
         }
     }
 
+    /**
+     * Дополнительные поля уведомления: часть банков кладёт сумму в bigText,
+     * subText или textLines, а продавца — в заголовок беседы. Разбор в parser.rs
+     */
+    private fun putNotificationExtras(json: JSONObject, sbn: StatusBarNotification) {
+        val notification = sbn.notification
+        val extras = notification.extras
+
+        fun putText(name: String, value: CharSequence?) {
+            val text = value?.toString()?.trim()
+            if (!text.isNullOrEmpty()) {
+                json.put(name, text)
+            }
+        }
+
+        json.put("postTime", sbn.postTime)
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            putText("channelId", notification.channelId)
+        }
+        putText("groupKey", sbn.groupKey)
+        putText("conversationTitle", extras.getCharSequence(Notification.EXTRA_CONVERSATION_TITLE))
+        putText("bigText", extras.getCharSequence(Notification.EXTRA_BIG_TEXT))
+        putText("subText", extras.getCharSequence(Notification.EXTRA_SUB_TEXT))
+        putText("category", notification.category)
+
+        val textLines = extras.getCharSequenceArray(Notification.EXTRA_TEXT_LINES)
+            ?.mapNotNull { it?.toString()?.trim()?.takeIf { line -> line.isNotEmpty() } }
+        if (!textLines.isNullOrEmpty()) {
+            json.put("textLines", JSONArray(textLines))
+        }
+    }
+
     private fun saveNotification(notificationData: JSONObject) {
         try {
-            val file = File(filesDir, NOTIFICATIONS_FILE)
-
-            // Читаем существующие уведомления только если файл существует
-            val notifications = if (file.exists() && file.length() > 0) {
-                try {
-                    JSONArray(file.readText())
-                } catch (e: Exception) {
-                    // Если файл поврежден, начинаем заново
-                    JSONArray()
+            val count = withNotificationsLock {
+                val file = File(filesDir, NOTIFICATIONS_FILE)
+                val notifications = readPendingNotifications(file)
+
+                // Добавляем новое уведомление
+                notifications.put(notificationData)
+
+                LoggerUtil.info(this, TAG, "Notification saved to pending_notifications.json from ${notificationData.optString("packageName")}")
+
+                // Ограничиваем очередь, чтобы не копить тысячи записей
+                val trimmedNotifications = if (notifications.length() > MAX_PENDING_NOTIFICATIONS) {
+                    val trimmed = JSONArray()
+                    val startIndex = notifications.length() - MAX_PENDING_NOTIFICATIONS
+                    for (i in startIndex until notifications.length()) {
+                        trimmed.put(notifications.get(i))
+                    }
+                    trimmed
+                } else {
+                    notifications
                 }
+
+                // Записываем атомарно используя временный файл для безопасности данных
+                val tempFile = File(filesDir, "$NOTIFICATIONS_FILE.tmp")
+                tempFile.writeText(trimmedNotifications.toString())
+                if (!tempFile.renameTo(file)) {
+                    throw IOException("Failed to replace $NOTIFICATIONS_FILE")
+                }
+
+                trimmedNotifications.length()
+            }
+
+            // Показываем локальное уведомление пользователю
+            showPaymentNotification(count)
+
+            // Отправляем событие для уведомления приложения
+            broadcastNewNotification()
+        } catch (e: Exception) {
+            LoggerUtil.error(this, TAG, "Error saving notification", e)
+        }
+    }
+
+    /**
+     * Reads the pending file. A damaged file is not discarded: it is moved aside
+     * as pending_notifications.json.recovered-<ms> and Rust salvages every
+     * complete entry from it.
+     */
+    private fun readPendingNotifications(file: File): JSONArray {
+        if (!file.exists() || file.length() == 0L) {
+            return JSONArray()
+        }
+
+        return try {
+            JSONArray(file.readText())
+        } catch (e: Exception) {
+            val recovered = File(filesDir, "$RECOVERED_PREFIX${System.currentTimeMillis()}")
+            if (file.renameTo(recovered)) {
+                LoggerUtil.warn(this, TAG, "Damaged $NOTIFICATIONS_FILE moved to ${recovered.name}")
             } else {
-                JSONArray()
+                LoggerUtil.error(this, TAG, "Failed to move damaged $NOTIFICATIONS_FILE aside", e)
             }
+            JSONArray()
+        }
+    }
 
-            // Добавляем новое уведомление
-            notifications.put(notificationData)
+    /**
+     * Runs [block] while holding the lock file shared with Rust (spool.rs).
+     * The lock is created atomically; a lock older than LOCK_STALE_MS is left
+     * over from a crash and gets removed.
+     */
+    private inline fun <T> withNotificationsLock(block: () -> T): T {
+        val lock = File(filesDir, LOCK_FILE)
+        val deadline = System.currentTimeMillis() + LOCK_TIMEOUT_MS
+
+        while (!lock.createNewFile()) {
+            if (System.currentTimeMillis() - lock.lastModified() > LOCK_STALE_MS) {
+                LoggerUtil.warn(this, TAG, "Removing stale $LOCK_FILE")
+                lock.delete()
+                continue
+            }
+            if (System.currentTimeMillis() >= deadline) {
+                throw IOException("Timed out waiting for $LOCK_FILE")
+            }
+            Thread.sleep(LOCK_RETRY_MS)
+        }
 
-            LoggerUtil.info(this, TAG, "Notification saved to pending_notifications.json: ${notificationData.toString()}")
+        try {
+            return block()
+        } finally {
+            lock.delete()
+        }
+    }
+
+    /**
+     * Список из monitored_packages.json; файл перечитывается, только если
+     * изменилось время его изменения. Без файла — DEFAULT_PACKAGES
+     */
+    private fun monitoredPackages(): Set<String> {
+        val file = File(filesDir, MONITORED_PACKAGES_FILE)
+        val modified = if (file.exists()) file.lastModified() else 0L
+        if (modified == monitoredPackagesModified) {
+            return monitoredPackages
+        }
 
-            // Ограничиваем очередь, чтобы не копить тысячи записей
-            val trimmedNotifications = if (notifications.length() > MAX_PENDING_NOTIFICATIONS) {
-                val trimmed = JSONArray()
-                val startIndex = notifications.length() - MAX_PENDING_NOTIFICATIONS
-                for (i in startIndex until notifications.length()) {
-                    trimmed.put(notifications.get(i))
+        monitoredPackages = if (modified == 0L) {
+            DEFAULT_PACKAGES
+        } else {
+            try {
+                val packages = JSONObject(file.readText()).getJSONArray("packages")
+                (0 until packages.length()).map { packages.getString(it) }.toSet()
+            } catch (e: Exception) {
+                LoggerUtil.error(this, TAG, "Failed to read $MONITORED_PACKAGES_FILE, keeping previous list", e)
+                monitoredPackages
+            }
+        }
+        monitoredPackagesModified = modified
+        LoggerUtil.info(this, TAG, "Monitoring ${monitoredPackages.size} packages")
+        return monitoredPackages
+    }
+
+    /**
+     * Запоминает приложение, чьё уведомление пропустили, чтобы пользователь
+     * мог выбрать банк из списка. Сохраняются только имя пакета, название,
+     * время и счётчик; файл пишется не чаще раза в минуту или при новом пакете
+     */
+    @Synchronized
+    private fun recordSeenPackage(sbn: StatusBarNotification) {
+        if (sbn.isOngoing) {
+            return
+        }
+        try {
+            val file = File(filesDir, SEEN_PACKAGES_FILE)
+            if (!seenPackagesLoaded) {
+                seenPackagesLoaded = true
+                if (file.exists()) {
+                    try {
+                        val saved = JSONObject(file.readText())
+                        for (name in saved.keys()) {
+                            saved.optJSONObject(name)?.let { seenPackages[name] = it }
+                        }
+                    } catch (e: Exception) {
+                        LoggerUtil.warn(this, TAG, "Damaged $SEEN_PACKAGES_FILE, starting over")
+                    }
+                }
+            }
+
+            val now = System.currentTimeMillis()
+            val entry = seenPackages[sbn.packageName]
+            val isNew = entry == null
+            if (entry == null) {
+                seenPackages[sbn.packageName] = JSONObject().apply {
+                    put("appName", appLabel(sbn.packageName))
+                    put("lastSeen", now)
+                    put("count", 1)
                 }
-                trimmed
             } else {
-                notifications
+                entry.put("lastSeen", now)
+                entry.put("count", entry.optLong("count") + 1)
             }
 
-            // Записываем атомарно используя временный файл для безопасности данных
-            val tempFile = File(filesDir, "$NOTIFICATIONS_FILE.tmp")
-            tempFile.writeText(trimmedNotifications.toString())
-            tempFile.renameTo(file)
+            if (!isNew && now - seenPackagesFlushedAt < SEEN_PACKAGES_FLUSH_MS) {
+                return
+            }
+            // Самые давние выпадают из списка
+            while (seenPackages.size > MAX_SEEN_PACKAGES) {
+                val oldest = seenPackages.minByOrNull { it.value.optLong("lastSeen") }?.key ?: break
+                seenPackages.remove(oldest)
+            }
 
-            // Показываем локальное уведомление пользователю
-            showPaymentNotification(trimmedNotifications.length())
+            val json = JSONObject()
+            for ((name, value) in seenPackages) {
+                json.put(name, value)
+            }
+            val tempFile = File(filesDir, "$SEEN_PACKAGES_FILE.tmp")
+            tempFile.writeText(json.toString())
+            if (!tempFile.renameTo(file)) {
+                throw IOException("Failed to replace $SEEN_PACKAGES_FILE")
+            }
+            seenPackagesFlushedAt = now
+        } catch (e: Exception) {
+            LoggerUtil.error(this, TAG, "Error recording seen package", e)
+        }
+    }
 
-            // Отправляем событие для уведомления приложения
-            broadcastNewNotification()
+    private fun appLabel(packageName: String): String? {
+        return try {
+            packageManager.getApplicationLabel(packageManager.getApplicationInfo(packageName, 0)).toString()
         } catch (e: Exception) {
-            LoggerUtil.error(this, TAG, "Error saving notification", e)
+            // Без QUERY_ALL_PACKAGES часть приложений не видна
+            null
         }
     }
 
@@ -384,6 +541,25 @@ class PaymentNotificationListenerService : NotificationListenerService() {
         } catch (e: Exception) {
             LoggerUtil.error(this, TAG, "Error updating heartbeat ($reason)", e)
         }
+        appendHeartbeatLog(reason)
+    }
+
+    @Synchronized
+    private fun appendHeartbeatLog(reason: String) {
+        val now = System.currentTimeMillis()
+        if (reason in ROUTINE_HEARTBEATS && now - heartbeatLoggedAt < HEARTBEAT_LOG_THROTTLE_MS) {
+            return
+        }
+        try {
+            val file = File(filesDir, HEARTBEAT_LOG_FILE)
+            if (file.length() > HEARTBEAT_LOG_MAX_BYTES) {
+                file.writeText(file.readLines().takeLast(HEARTBEAT_LOG_KEEP_LINES).joinToString("\n", postfix = "\n"))
+            }
+            file.appendText("$now $reason\n")
+            heartbeatLoggedAt = now
+        } catch (e: Exception) {
+            LoggerUtil.error(this, TAG, "Error writing heartbeat log ($reason)", e)
+        }
     }
 
     private fun broadcastNewNotification() {
@@ -433,6 +609,7 @@ class PaymentNotificationListenerService : NotificationListenerService() {
     override fun onListenerDisconnected() {
         super.onListenerDisconnected()
         LoggerUtil.warn(this, TAG, "Notification listener disconnected - will not receive notifications until reconnected")
+        appendHeartbeatLog("listener_disconnected")
     }
 
     override fun onCreate() {
@@ -455,6 +632,7 @@ class PaymentNotificationListenerService : NotificationListenerService() {
     override fun onDestroy() {
         super.onDestroy()
         LoggerUtil.info(this, TAG, "Notification listener service destroyed")
+        appendHeartbeatLog("service_destroyed")
         try {
             unregisterReceiver(heartbeatReceiver)
             LoggerUtil.debug(this, TAG, "Heartbeat receiver unregistered")
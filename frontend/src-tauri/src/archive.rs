@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+use crate::store::{
+    NotificationStore, StoredNotification, OUTCOME_DISMISSED, OUTCOME_DUPLICATE, OUTCOME_IMPORTED,
+};
+use crate::worker;
+
+pub const ARCHIVE_RETENTION_KEY: &str = "archive_retention";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Сколько хранить обработанные уведомления. Пустое поле — без ограничения
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    pub max_age_days: Option<u32>,
+    pub max_entries: Option<u32>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            max_age_days: Some(180),
+            max_entries: Some(5000),
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn load(store: &NotificationStore) -> Result<Self, String> {
+        Ok(store.get_setting(ARCHIVE_RETENTION_KEY)?.unwrap_or_default())
+    }
+}
+
+/// Чистит архив по настройкам хранения; возвращает число удалённых записей
+pub fn prune(store: &NotificationStore) -> Result<usize, String> {
+    let config = RetentionConfig::load(store)?;
+    let archived_before = config
+        .max_age_days
+        .map(|days| chrono::Utc::now().timestamp_millis() - i64::from(days) * DAY_MS);
+
+    let removed = store.prune_archive(archived_before, config.max_entries)?;
+    if removed > 0 {
+        log::info!("Removed {} notifications from the archive", removed);
+    }
+    Ok(removed)
+}
+
+fn validate_outcome(outcome: Option<&str>) -> Result<(), String> {
+    match outcome {
+        None | Some(OUTCOME_IMPORTED) | Some(OUTCOME_DISMISSED) | Some(OUTCOME_DUPLICATE) => Ok(()),
+        Some(other) => Err(format!("Unknown archive outcome: {}", other)),
+    }
+}
+
+#[tauri::command]
+pub async fn query_archive(
+    store: tauri::State<'_, NotificationStore>,
+    outcome: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<StoredNotification>, String> {
+    validate_outcome(outcome.as_deref())?;
+    let store = store.inner().clone();
+    worker::run(move || store.archived(outcome.as_deref(), limit, offset)).await
+}
+
+/// Возвращает отклонённое уведомление в очередь. false, если оно не отклонено
+#[tauri::command]
+pub async fn undo_dismissal(store: tauri::State<'_, NotificationStore>, id: String) -> Result<bool, String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let stored = store
+            .find_by_id(&id)?
+            .ok_or_else(|| format!("Notification not found: {}", id))?;
+        store.restore_dismissed(stored.row_id)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_archive_retention(store: tauri::State<'_, NotificationStore>) -> Result<RetentionConfig, String> {
+    let store = store.inner().clone();
+    worker::run(move || RetentionConfig::load(&store)).await
+}
+
+/// Сохраняет настройки и сразу чистит архив по ним
+#[tauri::command]
+pub async fn set_archive_retention(
+    store: tauri::State<'_, NotificationStore>,
+    config: RetentionConfig,
+) -> Result<usize, String> {
+    if config.max_age_days == Some(0) || config.max_entries == Some(0) {
+        return Err("Archive retention must keep at least one day and one entry".to_string());
+    }
+    let store = store.inner().clone();
+    worker::run(move || {
+        store.set_setting(ARCHIVE_RETENTION_KEY, &config)?;
+        prune(&store)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications;
+    use crate::store::STATUS_PENDING;
+    use std::path::PathBuf;
+
+    const NOTIFICATIONS: &str = r#"[
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽","timestamp":1700000000000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка Лента","text":"990 ₽","timestamp":1700000100000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка Лента","text":"990 ₽ — Баланс: 10 ₽","timestamp":1700000110000}
+    ]"#;
+
+    #[test]
+    fn records_outcomes_and_undoes_dismissal() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let inserted = store.import_json(NOTIFICATIONS).unwrap();
+        let ids: Vec<String> = inserted.iter().map(|n| n.id.clone()).collect();
+
+        store.link_payment(inserted[0].row_id, "payment-1").unwrap();
+        store.mark_duplicate(inserted[2].row_id, inserted[1].row_id, "same amount").unwrap();
+        notifications::clear_pending(&store, &ids[1..2]).unwrap();
+
+        let outcomes: Vec<(String, Option<String>)> = store
+            .archived(None, None, None)
+            .unwrap()
+            .into_iter()
+            .map(|n| (n.id, n.outcome))
+            .collect();
+        assert_eq!(outcomes.len(), 3);
+        assert!(outcomes.contains(&(ids[0].clone(), Some(OUTCOME_IMPORTED.to_string()))));
+        assert!(outcomes.contains(&(ids[1].clone(), Some(OUTCOME_DISMISSED.to_string()))));
+        assert!(outcomes.contains(&(ids[2].clone(), Some(OUTCOME_DUPLICATE.to_string()))));
+        assert_eq!(store.archived(Some(OUTCOME_IMPORTED), None, None).unwrap()[0].payment_id.as_deref(), Some("payment-1"));
+
+        // Отменить можно только отклонение
+        assert!(!store.restore_dismissed(inserted[0].row_id).unwrap());
+        assert!(store.restore_dismissed(inserted[1].row_id).unwrap());
+        let restored = store.get(inserted[1].row_id).unwrap().unwrap();
+        assert_eq!((restored.status.as_str(), restored.archived_at), (STATUS_PENDING, None));
+
+        store
+            .set_setting(ARCHIVE_RETENTION_KEY, &RetentionConfig { max_age_days: None, max_entries: Some(1) })
+            .unwrap();
+        assert_eq!(prune(&store).unwrap(), 1);
+        assert_eq!(store.archived(None, None, None).unwrap().len(), 1);
+        assert_eq!(store.pending().unwrap().len(), 1);
+    }
+}
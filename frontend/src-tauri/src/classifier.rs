@@ -0,0 +1,439 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::notifications::PendingNotification;
+use crate::parser::{self, ParsedPayment};
+use crate::worker;
+
+/// Начиная с этой уверенности расходы можно импортировать без ручной проверки
+pub const AUTO_IMPORT_CONFIDENCE: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Payment,
+    Refund,
+    Transfer,
+    Decline,
+    Hold,
+    CashWithdrawal,
+    TopUp,
+    Other,
+}
+
+impl TransactionKind {
+    const ALL: [TransactionKind; 7] = [
+        TransactionKind::Payment,
+        TransactionKind::Refund,
+        TransactionKind::Transfer,
+        TransactionKind::Decline,
+        TransactionKind::Hold,
+        TransactionKind::CashWithdrawal,
+        TransactionKind::TopUp,
+    ];
+
+    /// Списание, которое становится расходом
+    pub fn is_expense(self) -> bool {
+        matches!(self, TransactionKind::Payment | TransactionKind::CashWithdrawal)
+    }
+}
+
+/// Сработавшее правило или признак
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub rule: String,
+    pub kind: TransactionKind,
+    pub weight: f64,
+    pub matched: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Classification {
+    pub kind: TransactionKind,
+    pub confidence: f64,
+    pub auto_import: bool,
+    pub signals: Vec<Signal>,
+    pub explanation: String,
+}
+
+struct KeywordRule {
+    id: &'static str,
+    kind: TransactionKind,
+    weight: f64,
+    pattern: &'static str,
+}
+
+// Отказы и холды часто содержат слово "покупка", поэтому весят больше платежа
+const KEYWORD_RULES: &[KeywordRule] = &[
+    KeywordRule {
+        id: "decline_ru",
+        kind: TransactionKind::Decline,
+        weight: 5.0,
+        pattern: r"\b(?:отклонен\w*|отказ\w*|недостаточно средств|не выполнен\w*|операция не прошла)",
+    },
+    KeywordRule {
+        id: "decline_en",
+        kind: TransactionKind::Decline,
+        weight: 5.0,
+        pattern: r"\b(?:declined|rejected|insufficient funds|transaction failed)\b",
+    },
+    KeywordRule {
+        id: "decline_ka",
+        kind: TransactionKind::Decline,
+        weight: 5.0,
+        pattern: r"(?:უარყოფილია|არასაკმარისი)",
+    },
+    KeywordRule {
+        id: "decline_kk",
+        kind: TransactionKind::Decline,
+        weight: 5.0,
+        pattern: r"\b(?:қабылданбады|қаражат жеткіліксіз)",
+    },
+    KeywordRule {
+        id: "hold_ru",
+        kind: TransactionKind::Hold,
+        weight: 3.5,
+        pattern: r"\b(?:заблокирован\w*|холд\w*|предавторизац\w*|ожидает списания|в обработке)",
+    },
+    KeywordRule {
+        id: "hold_en",
+        kind: TransactionKind::Hold,
+        weight: 3.5,
+        pattern: r"\b(?:on hold|hold|pending|pre-?authori[sz]ation)\b",
+    },
+    KeywordRule {
+        id: "cash_withdrawal_ru",
+        kind: TransactionKind::CashWithdrawal,
+        weight: 3.0,
+        pattern: r"\b(?:(?:снятие|выдача) наличных|банкомат\w*)",
+    },
+    KeywordRule {
+        id: "cash_withdrawal_en",
+        kind: TransactionKind::CashWithdrawal,
+        weight: 3.0,
+        pattern: r"\b(?:atm|cash withdrawal)\b",
+    },
+    KeywordRule {
+        id: "cash_withdrawal_ka",
+        kind: TransactionKind::CashWithdrawal,
+        weight: 3.0,
+        pattern: r"(?:განაღდება|ბანკომატ)",
+    },
+    KeywordRule {
+        id: "cash_withdrawal_kk",
+        kind: TransactionKind::CashWithdrawal,
+        weight: 3.0,
+        pattern: r"\b(?:қолма-қол ақша)",
+    },
+    KeywordRule {
+        id: "refund_ru",
+        kind: TransactionKind::Refund,
+        weight: 2.5,
+        pattern: r"\b(?:возврат\w*|отмена покупки)",
+    },
+    KeywordRule {
+        id: "refund_en",
+        kind: TransactionKind::Refund,
+        weight: 2.5,
+        pattern: r"\b(?:refund\w*|returned|reversal)\b",
+    },
+    KeywordRule {
+        id: "refund_ka",
+        kind: TransactionKind::Refund,
+        weight: 2.5,
+        pattern: r"(?:დაბრუნება)",
+    },
+    KeywordRule {
+        id: "refund_kk",
+        kind: TransactionKind::Refund,
+        weight: 2.5,
+        pattern: r"\b(?:қайтару|қайтарылды)",
+    },
+    KeywordRule {
+        id: "top_up_ru",
+        kind: TransactionKind::TopUp,
+        weight: 2.0,
+        pattern: r"\b(?:пополнен\w*|зачислен\w*|поступлен\w*|получен\w*)",
+    },
+    KeywordRule {
+        id: "top_up_en",
+        kind: TransactionKind::TopUp,
+        weight: 2.0,
+        pattern: r"\b(?:top-?up|deposit\w*|credited)\b",
+    },
+    KeywordRule {
+        id: "top_up_ka",
+        kind: TransactionKind::TopUp,
+        weight: 2.0,
+        pattern: r"(?:ჩარიცხვა)",
+    },
+    KeywordRule {
+        id: "top_up_kk",
+        kind: TransactionKind::TopUp,
+        weight: 2.0,
+        pattern: r"\b(?:толықтыру|толықтырылды|түсті)",
+    },
+    KeywordRule {
+        id: "transfer_ru",
+        kind: TransactionKind::Transfer,
+        weight: 2.0,
+        pattern: r"\b(?:перевод\w*|отправлен\w*|получателю)",
+    },
+    KeywordRule {
+        id: "transfer_en",
+        kind: TransactionKind::Transfer,
+        weight: 2.0,
+        pattern: r"\btransfer\w*",
+    },
+    KeywordRule {
+        id: "transfer_ka",
+        kind: TransactionKind::Transfer,
+        weight: 2.0,
+        pattern: r"(?:გადარიცხვა)",
+    },
+    KeywordRule {
+        id: "transfer_kk",
+        kind: TransactionKind::Transfer,
+        weight: 2.0,
+        pattern: r"\bаударым\w*",
+    },
+    KeywordRule {
+        id: "payment_ru",
+        kind: TransactionKind::Payment,
+        weight: 1.5,
+        pattern: r"\b(?:покупк\w*|оплат\w*|заплатили|списани\w*|плат[её]ж\w*)",
+    },
+    KeywordRule {
+        id: "payment_en",
+        kind: TransactionKind::Payment,
+        weight: 1.5,
+        pattern: r"\b(?:transaction|purchase|payment)\b",
+    },
+    KeywordRule {
+        id: "payment_ka",
+        kind: TransactionKind::Payment,
+        weight: 1.5,
+        pattern: r"(?:გადახდა|შესყიდვა)",
+    },
+    KeywordRule {
+        id: "payment_kk",
+        kind: TransactionKind::Payment,
+        weight: 1.5,
+        pattern: r"\b(?:төлем\w*|сатып алу)",
+    },
+];
+
+const PARSER_RULE_WEIGHT: f64 = 1.5;
+const AMOUNT_SIGN_WEIGHT: f64 = 0.5;
+const LISTENER_TYPE_WEIGHT: f64 = 0.5;
+
+fn keyword_regexes() -> &'static [Regex] {
+    static CELL: OnceLock<Vec<Regex>> = OnceLock::new();
+    CELL.get_or_init(|| {
+        KEYWORD_RULES
+            .iter()
+            .map(|rule| {
+                RegexBuilder::new(rule.pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .expect("invalid classifier regex")
+            })
+            .collect()
+    })
+}
+
+fn debit_sign_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| Regex::new(r"(?:^|\s)[-−]\s?\d").expect("invalid classifier regex"))
+}
+
+fn credit_sign_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| Regex::new(r"(?:^|\s)\+\s?\d").expect("invalid classifier regex"))
+}
+
+fn collect_signals(notification: &PendingNotification, payment: Option<&ParsedPayment>) -> Vec<Signal> {
+    let body = notification.texts().join(" ");
+    let message = format!("{} {}", notification.titles().join(" "), body);
+    let mut signals: Vec<Signal> = KEYWORD_RULES
+        .iter()
+        .zip(keyword_regexes())
+        .filter_map(|(rule, regex)| {
+            regex.find(&message).map(|m| Signal {
+                rule: rule.id.to_string(),
+                kind: rule.kind,
+                weight: rule.weight,
+                matched: Some(m.as_str().to_string()),
+            })
+        })
+        .collect();
+
+    if let Some(payment) = payment {
+        signals.push(Signal {
+            rule: format!("parser_rule:{}", payment.bank),
+            kind: TransactionKind::Payment,
+            weight: PARSER_RULE_WEIGHT,
+            matched: None,
+        });
+    }
+
+    for (rule, regex, kind) in [
+        ("debit_sign", debit_sign_regex(), TransactionKind::Payment),
+        ("credit_sign", credit_sign_regex(), TransactionKind::TopUp),
+    ] {
+        if let Some(m) = regex.find(&body) {
+            signals.push(Signal {
+                rule: rule.to_string(),
+                kind,
+                weight: AMOUNT_SIGN_WEIGHT,
+                matched: Some(m.as_str().trim().to_string()),
+            });
+        }
+    }
+
+    // Тип, который определил Kotlin-слушатель, учитываем как слабый признак
+    let listener_kind = match notification.notification_type.as_deref() {
+        Some("PAYMENT") => Some(TransactionKind::Payment),
+        Some("REFUND") => Some(TransactionKind::Refund),
+        Some("TRANSFER") => Some(TransactionKind::Transfer),
+        _ => None,
+    };
+    if let Some(kind) = listener_kind {
+        signals.push(Signal {
+            rule: "listener_type".to_string(),
+            kind,
+            weight: LISTENER_TYPE_WEIGHT,
+            matched: notification.notification_type.clone(),
+        });
+    }
+
+    signals
+}
+
+fn explain(kind: TransactionKind, confidence: f64, signals: &[Signal]) -> String {
+    let kind_name = serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default();
+    if signals.is_empty() {
+        return format!("{} ({:.2}): no rules matched", kind_name, confidence);
+    }
+
+    let fired: Vec<String> = signals
+        .iter()
+        .map(|signal| match &signal.matched {
+            Some(matched) => format!("{} '{}' +{:.1}", signal.rule, matched, signal.weight),
+            None => format!("{} +{:.1}", signal.rule, signal.weight),
+        })
+        .collect();
+    format!("{} ({:.2}): {}", kind_name, confidence, fired.join(", "))
+}
+
+/// Классифицирует уведомление по сумме весов сработавших правил.
+/// Уверенность растёт с весом победителя и падает, если конкурент близко.
+pub fn classify(notification: &PendingNotification, payment: Option<&ParsedPayment>) -> Classification {
+    let signals = collect_signals(notification, payment);
+
+    let score = |kind: TransactionKind| -> f64 {
+        signals.iter().filter(|s| s.kind == kind).map(|s| s.weight).sum()
+    };
+    let mut scores: Vec<(TransactionKind, f64)> = TransactionKind::ALL
+        .iter()
+        .map(|kind| (*kind, score(*kind)))
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let (kind, best) = scores[0];
+    let second = scores[1].1;
+    let (kind, confidence) = if best > 0.0 {
+        let confidence = (1.0 - (-best).exp()) * best / (best + second);
+        (kind, (confidence * 100.0).round() / 100.0)
+    } else {
+        (TransactionKind::Other, 0.0)
+    };
+
+    Classification {
+        kind,
+        confidence,
+        auto_import: kind.is_expense() && payment.is_some() && confidence >= AUTO_IMPORT_CONFIDENCE,
+        explanation: explain(kind, confidence, &signals),
+        signals,
+    }
+}
+
+#[tauri::command]
+pub async fn classify_notification(notification: PendingNotification) -> Result<Classification, String> {
+    worker::run(move || {
+        let payment = parser::parse_notification(&notification);
+        Ok(classify(&notification, payment.as_ref()))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification(package_name: &str, title: &str, text: &str) -> PendingNotification {
+        PendingNotification {
+            id: String::new(),
+            package_name: package_name.to_string(),
+            title: title.to_string(),
+            text: text.to_string(),
+            timestamp: 0,
+            notification_type: None,
+            notification_key: None,
+            extras: Default::default(),
+        }
+    }
+
+    fn classify_text(package_name: &str, title: &str, text: &str) -> Classification {
+        let notification = notification(package_name, title, text);
+        let payment = parser::parse_notification(&notification);
+        classify(&notification, payment.as_ref())
+    }
+
+    #[test]
+    fn parsed_purchase_is_confident_payment() {
+        let result = classify_text("ru.sberbankmobile", "Покупка Купер", "150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165");
+        assert_eq!(result.kind, TransactionKind::Payment);
+        assert!(result.confidence >= AUTO_IMPORT_CONFIDENCE, "{}", result.explanation);
+        assert!(result.auto_import);
+        assert!(result.signals.iter().any(|s| s.rule == "parser_rule:sberbank"));
+    }
+
+    #[test]
+    fn recognizes_declines_holds_withdrawals_and_top_ups() {
+        let cases = [
+            ("Покупка отклонена", "Недостаточно средств. Магазин 500 ₽", TransactionKind::Decline),
+            ("Сбербанк", "Сумма 1 200 ₽ заблокирована до списания", TransactionKind::Hold),
+            ("Альфа-Банк", "Снятие наличных 5 000 ₽, банкомат *1234", TransactionKind::CashWithdrawal),
+            ("Т-Банк", "Пополнение +3 000 ₽. Баланс 10 000 ₽", TransactionKind::TopUp),
+            ("Ozon Банк", "Возврат 128 ₽ от Ozon", TransactionKind::Refund),
+            ("BOG", "Transfer 100.00 GEL to John", TransactionKind::Transfer),
+            ("TBC", "ჩარიცხვა 500.00 GEL", TransactionKind::TopUp),
+            ("TBC", "გადახდა უარყოფილია: არასაკმარისი თანხა", TransactionKind::Decline),
+            ("Halyk", "Сатып алу қабылданбады: қаражат жеткіліксіз", TransactionKind::Decline),
+            ("Kaspi.kz", "Аударым 5 000 ₸ Айгүл К.", TransactionKind::Transfer),
+        ];
+
+        for (title, text, kind) in cases {
+            let result = classify_text("com.example.bank", title, text);
+            assert_eq!(result.kind, kind, "{}", result.explanation);
+            assert!(!result.auto_import);
+        }
+    }
+
+    #[test]
+    fn weak_or_missing_evidence_needs_review() {
+        let keyword_only = classify_text("com.example.bank", "Банк", "Оплата услуг");
+        assert_eq!(keyword_only.kind, TransactionKind::Payment);
+        assert!(keyword_only.confidence < AUTO_IMPORT_CONFIDENCE);
+
+        let nothing = classify_text("com.example.bank", "Банк", "Ваш код 1234");
+        assert_eq!(nothing.kind, TransactionKind::Other);
+        assert_eq!(nothing.confidence, 0.0);
+        assert!(nothing.explanation.contains("no rules matched"));
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::merchant_rules::{self, CategorySuggestion};
+use crate::parser::ParsedPayment;
+use crate::parser_rules::{self, RuleSet};
+use crate::store::NotificationStore;
+use crate::worker;
+
+/// Больше в буфере обмена — это уже не SMS и не строка из письма
+const MAX_PASTED_LENGTH: usize = 64 * 1024;
+
+/// Черновик платежа из вставленного текста; фронтенд открывает по нему
+/// форму создания платежа
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentDraft {
+    #[serde(flatten)]
+    pub payment: ParsedPayment,
+    pub category: Option<CategorySuggestion>,
+    /// Строки, из которых разобран платёж
+    pub source_text: String,
+    /// Номер первой из них во вставленном тексте, с нуля
+    pub line: usize,
+}
+
+fn parse_with(rules: &RuleSet, package_name: Option<&str>, title: &str, text: &str) -> Option<ParsedPayment> {
+    match package_name {
+        Some(package_name) => rules.parse(package_name, title, text),
+        None => rules.parse_any(title, text),
+    }
+}
+
+/// Платежи из вставленного текста. Каждая строка разбирается сама по себе
+/// и вместе с предыдущей в роли заголовка уведомления ("Покупка Купер" /
+/// "150 ₽ — Баланс: ..."). Если ни одна строка не подошла, текст
+/// разбирается целиком: письма переносят строки посреди фразы
+pub fn parse_pasted(rules: &RuleSet, text: &str, package_name: Option<&str>) -> Vec<(ParsedPayment, String, usize)> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+
+    let mut parsed = Vec::new();
+    // Строка, уже ставшая частью платежа, не может быть заголовком следующего
+    let mut previous: Option<(usize, &str)> = None;
+    for &(index, line) in &lines {
+        let alone = parse_with(rules, package_name, "", line);
+        let with_title = previous.and_then(|(title_index, title)| {
+            parse_with(rules, package_name, title, line).map(|payment| (payment, title_index, title))
+        });
+        // Заголовок нужен, если без него строка не разбирается или в нём маска карты
+        match (alone, with_title) {
+            (Some(payment), with_title)
+                if payment.card_mask.is_some()
+                    || with_title.as_ref().and_then(|(titled, _, _)| titled.card_mask.as_ref()).is_none() =>
+            {
+                parsed.push((payment, line.to_string(), index));
+                previous = None;
+            }
+            (_, Some((payment, title_index, title))) => {
+                parsed.push((payment, format!("{}\n{}", title, line), title_index));
+                previous = None;
+            }
+            _ => previous = Some((index, line)),
+        }
+    }
+
+    if parsed.is_empty() && lines.len() > 1 {
+        let joined = lines.iter().map(|(_, line)| *line).collect::<Vec<_>>().join(" ");
+        if let Some(payment) = parse_with(rules, package_name, "", &joined) {
+            parsed.push((payment, joined, lines[0].0));
+        }
+    }
+    parsed
+}
+
+/// Черновики с категорией из локального кэша правил
+pub fn drafts(store: &NotificationStore, text: &str, package_name: Option<&str>) -> Result<Vec<PaymentDraft>, String> {
+    if text.len() > MAX_PASTED_LENGTH {
+        return Err(format!("Pasted text is too long: {} bytes", text.len()));
+    }
+
+    parse_pasted(&parser_rules::current(), text, package_name)
+        .into_iter()
+        .map(|(payment, source_text, line)| {
+            Ok(PaymentDraft {
+                category: merchant_rules::suggest_category(store, &payment.merchant_name)?,
+                payment,
+                source_text,
+                line,
+            })
+        })
+        .collect()
+}
+
+/// Разбирает вставленный текст. `package_name` ограничивает разбор правилами
+/// одного банка
+#[tauri::command]
+pub async fn parse_pasted_payments(
+    store: tauri::State<'_, NotificationStore>,
+    text: String,
+    package_name: Option<String>,
+) -> Result<Vec<PaymentDraft>, String> {
+    let store = store.inner().clone();
+    worker::run(move || drafts(&store, &text, package_name.as_deref())).await
+}
+
+/// То же для текста из буфера обмена
+#[tauri::command]
+pub async fn import_clipboard_payments<R: Runtime>(
+    app: AppHandle<R>,
+    store: tauri::State<'_, NotificationStore>,
+    package_name: Option<String>,
+) -> Result<Vec<PaymentDraft>, String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let text = app
+            .clipboard()
+            .read_text()
+            .map_err(|e| format!("Failed to read clipboard: {:?}", e))?;
+        drafts(&store, &text, package_name.as_deref())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASTE: &str = "
+        Заплатили картой *1234
+        − 689.68 ₽ в Пятерочка. Теперь на карте 34 574.90 ₽
+
+        Покупка Купер
+        150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165
+        Код подтверждения 1234
+        Purchase: 1,250.00 GEL at Carrefour.
+    ";
+
+    #[test]
+    fn parses_every_transaction_of_a_multiline_paste() {
+        let rules = RuleSet::bundled();
+        let parsed: Vec<(String, f64, String, usize)> = parse_pasted(&rules, PASTE, None)
+            .into_iter()
+            .map(|(payment, _, line)| (payment.merchant_name, payment.amount, payment.currency, line))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("Пятерочка".to_string(), 689.68, "RUB".to_string(), 1),
+                ("Купер".to_string(), 150.0, "RUB".to_string(), 4),
+                ("Carrefour".to_string(), 1250.0, "GEL".to_string(), 7),
+            ]
+        );
+
+        assert_eq!(parse_pasted(&rules, PASTE, Some("ru.sberbankmobile")).len(), 1);
+        assert!(parse_pasted(&rules, "Код подтверждения 1234", None).is_empty());
+
+        // Письмо с переносом строки посреди фразы
+        let email = "Purchase: 42.00\nGEL at Goodwill.";
+        let (payment, source_text, _) = parse_pasted(&rules, email, None).remove(0);
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Goodwill", 42.0));
+        assert_eq!(source_text, "Purchase: 42.00 GEL at Goodwill.");
+    }
+}
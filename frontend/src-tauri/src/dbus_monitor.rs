@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use crate::notifications::PendingNotification;
+#[cfg(target_os = "linux")]
+use crate::parser_rules;
+use crate::store::NotificationStore;
+use crate::worker;
+
+pub const DBUS_MONITOR_CONFIG_KEY: &str = "dbus_monitor";
+const LINUX_ONLY: &str = "D-Bus notification monitoring is only available on Linux";
+/// Как часто монитор без новых сообщений проверяет, не пора ли остановиться
+#[cfg(target_os = "linux")]
+const GENERATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Приложение, чьи уведомления забираем с шины. `package_name` — имя, под
+/// которым уведомление попадёт в парсер; без него пакет определяется по
+/// правилу, которое разобрало текст, а если такого нет — это `app_name`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DbusSource {
+    pub app_name: String,
+    #[serde(default)]
+    pub package_name: Option<String>,
+}
+
+/// Мониторинг org.freedesktop.Notifications выключен, пока пользователь
+/// явно его не включит
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DbusMonitorConfig {
+    pub enabled: bool,
+    pub sources: Vec<DbusSource>,
+}
+
+impl DbusMonitorConfig {
+    pub fn load(store: &NotificationStore) -> Result<Self, String> {
+        Ok(store.get_setting(DBUS_MONITOR_CONFIG_KEY)?.unwrap_or_default())
+    }
+
+    /// Включённый монитор без источников не запустится, такие настройки не сохраняем
+    fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if cfg!(not(target_os = "linux")) {
+            return Err(LINUX_ONLY.to_string());
+        }
+        if self.sources.is_empty() {
+            return Err("No D-Bus notification sources configured".to_string());
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn source_for(&self, app_name: &str) -> Option<&DbusSource> {
+        self.sources
+            .iter()
+            .find(|source| source.app_name.trim().eq_ignore_ascii_case(app_name.trim()))
+    }
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+// Каждый запуск получает новое поколение; старый поток завершается,
+// увидев, что поколение сменилось
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_os = "linux")]
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// Убирает разметку, которую спецификация разрешает в теле уведомления
+#[cfg(target_os = "linux")]
+fn strip_markup(body: &str) -> String {
+    let mut text = String::with_capacity(body.len());
+    let mut in_tag = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+/// Превращает вызов Notify в уведомление для хранилища, если приложение
+/// есть в списке источников
+#[cfg(target_os = "linux")]
+pub fn to_pending(
+    config: &DbusMonitorConfig,
+    app_name: &str,
+    summary: &str,
+    body: &str,
+    timestamp: i64,
+) -> Option<PendingNotification> {
+    let source = config.source_for(app_name)?;
+    let text = strip_markup(body);
+    if text.is_empty() {
+        return None;
+    }
+
+    let title = strip_markup(summary);
+    // Правила выбираются по пакету, под именем приложения ни одно не подойдёт
+    let package_name = source
+        .package_name
+        .clone()
+        .or_else(|| parser_rules::current().package_for(&title, &text).map(str::to_string))
+        .unwrap_or_else(|| source.app_name.clone());
+
+    let notification = PendingNotification {
+        id: String::new(),
+        package_name,
+        title,
+        text,
+        timestamp,
+        notification_type: None,
+        notification_key: None,
+        extras: Default::default(),
+    };
+    Some(notification.with_computed_id())
+}
+
+/// Запускает (или перезапускает с новыми настройками) монитор шины.
+/// Выключенный в настройках монитор останавливается
+pub fn start(store: NotificationStore) -> Result<(), String> {
+    let config = DbusMonitorConfig::load(&store)?;
+    let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    RUNNING.store(false, Ordering::SeqCst);
+
+    if !config.enabled {
+        return Ok(());
+    }
+    config.validate()?;
+
+    #[cfg(target_os = "linux")]
+    {
+        std::thread::Builder::new()
+            .name("dbus-notification-monitor".to_string())
+            .spawn(move || {
+                if let Err(e) = monitor(&store, &config, generation) {
+                    log::error!("D-Bus notification monitor stopped: {}", e);
+                }
+                if GENERATION.load(Ordering::SeqCst) == generation {
+                    RUNNING.store(false, Ordering::SeqCst);
+                }
+            })
+            .map_err(|e| format!("Failed to start D-Bus monitor: {:?}", e))?;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (store, generation);
+        Err(LINUX_ONLY.to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn monitor(store: &NotificationStore, config: &DbusMonitorConfig, generation: usize) -> Result<(), String> {
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use zbus::blocking::{fdo::MonitoringProxy, Connection};
+    use zbus::message::Type;
+    use zbus::zvariant::OwnedValue;
+    use zbus::{MatchRule, MessageStream};
+
+    // app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout
+    type NotifyArgs = (String, u32, String, String, String, Vec<String>, HashMap<String, OwnedValue>, i32);
+
+    let conn = Connection::session()
+        .map_err(|e| format!("Failed to connect to session bus: {:?}", e))?;
+    let rule = MatchRule::builder()
+        .msg_type(Type::MethodCall)
+        .interface("org.freedesktop.Notifications")
+        .and_then(|builder| builder.member("Notify"))
+        .map_err(|e| format!("Failed to build match rule: {:?}", e))?
+        .build();
+
+    let mut messages = MessageStream::from(conn.inner());
+    MonitoringProxy::new(&conn)
+        .map_err(|e| format!("Failed to create monitoring proxy: {:?}", e))?
+        .become_monitor(&[rule], 0)
+        .map_err(|e| format!("Failed to become D-Bus monitor: {:?}", e))?;
+    RUNNING.store(true, Ordering::SeqCst);
+    log::info!("Monitoring D-Bus notifications from {} sources", config.sources.len());
+
+    while GENERATION.load(Ordering::SeqCst) == generation {
+        // Без таймаута выключенный или перенастроенный монитор держал бы
+        // соединение до следующего уведомления на шине
+        let next = tauri::async_runtime::block_on(tokio::time::timeout(GENERATION_POLL_INTERVAL, messages.next()));
+        let message = match next {
+            Ok(Some(message)) => message.map_err(|e| format!("Failed to read D-Bus message: {:?}", e))?,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        let header = message.header();
+        if header.member().map(|member| member.as_str()) != Some("Notify") {
+            continue;
+        }
+
+        let (app_name, _, _, summary, body, ..): NotifyArgs = match message.body().deserialize() {
+            Ok(args) => args,
+            Err(e) => {
+                log::warn!("Unexpected Notify arguments: {:?}", e);
+                continue;
+            }
+        };
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        if let Some(notification) = to_pending(config, &app_name, &summary, &body, timestamp) {
+            // Дальше уведомление идёт тем же путём, что и с Android
+            if let Err(e) = store.insert_all(&[notification]) {
+                log::error!("Failed to store D-Bus notification: {}", e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_dbus_monitor_config(
+    store: tauri::State<'_, NotificationStore>,
+) -> Result<DbusMonitorConfig, String> {
+    let store = store.inner().clone();
+    worker::run(move || DbusMonitorConfig::load(&store)).await
+}
+
+#[tauri::command]
+pub async fn set_dbus_monitor_config(
+    store: tauri::State<'_, NotificationStore>,
+    config: DbusMonitorConfig,
+) -> Result<(), String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        config.validate()?;
+        store.set_setting(DBUS_MONITOR_CONFIG_KEY, &config)?;
+        start(store)
+    })
+    .await
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn config() -> DbusMonitorConfig {
+        DbusMonitorConfig {
+            enabled: true,
+            sources: vec![
+                DbusSource {
+                    app_name: "KDE Connect".to_string(),
+                    package_name: Some("ru.sberbankmobile".to_string()),
+                },
+                DbusSource {
+                    app_name: "Mirror".to_string(),
+                    package_name: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn maps_configured_apps_to_pending_notifications() {
+        let notification = to_pending(
+            &config(),
+            "kde connect",
+            "Покупка Купер",
+            "<b>150 ₽</b> — Баланс: 196,01 &amp; ₽",
+            1700000000000,
+        )
+        .unwrap();
+        assert_eq!(notification.package_name, "ru.sberbankmobile");
+        assert_eq!(notification.text, "150 ₽ — Баланс: 196,01 & ₽");
+        assert_eq!(notification.id, notification.compute_id());
+
+        let mirrored = to_pending(&config(), "Mirror", "Title", "Text", 0).unwrap();
+        assert_eq!(mirrored.package_name, "Mirror");
+
+        // Источник без package_name получает пакет правила, разобравшего текст
+        let mirrored = to_pending(&config(), "Mirror", "Покупка Купер", "150 ₽ — Баланс: 196,01 ₽", 0).unwrap();
+        assert_eq!(mirrored.package_name, "ru.sberbankmobile");
+        let payment = crate::parser::parse_notification(&mirrored).unwrap();
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Купер", 150.0));
+
+        // Для имени приложения правил нет, подходит любое
+        let mirrored = to_pending(&config(), "Mirror", "Покупка Купер", "150 ₽ — Баланс: 196,01 ₽", 0).unwrap();
+        let payment = crate::parser::parse_notification(&mirrored).unwrap();
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Купер", 150.0));
+
+        assert!(to_pending(&config(), "Thunderbird", "Mail", "Text", 0).is_none());
+        assert!(to_pending(&config(), "Mirror", "Title", "<i></i>", 0).is_none());
+
+        let without_sources = DbusMonitorConfig {
+            enabled: true,
+            sources: Vec::new(),
+        };
+        assert!(without_sources.validate().is_err());
+        assert!(DbusMonitorConfig::default().validate().is_ok());
+    }
+}
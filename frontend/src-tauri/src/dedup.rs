@@ -0,0 +1,287 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::merchant_rules::normalize_merchant_name;
+use crate::parser::ParsedPayment;
+use crate::store::{NotificationStore, StoredNotification};
+use crate::worker;
+
+pub const DEDUP_CONFIG_KEY: &str = "dedup";
+
+/// Банки переотправляют и обновляют уведомления с другим временем. Повтором
+/// считается уведомление с той же суммой в окне и тем же ключом Android или
+/// тем же балансом, а без баланса — с тем же продавцом и той же картой
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    pub window_secs: i64,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: 120,
+        }
+    }
+}
+
+impl DedupConfig {
+    pub fn load(store: &NotificationStore) -> Result<Self, String> {
+        Ok(store.get_setting(DEDUP_CONFIG_KEY)?.unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateMatch {
+    pub original_id: i64,
+    pub reason: String,
+}
+
+/// Почему уведомление признано повтором
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RepostBasis {
+    NotificationKey,
+    Balance,
+    MerchantAndCard,
+}
+
+fn balance_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| {
+        Regex::new(r"(?i)(?:баланс|доступно|остаток|теперь на карте|balance|available)\s*:?\s*(\d[\d \x{00A0}\x{202F}.,]*\d)")
+            .expect("invalid balance regex")
+    })
+}
+
+/// "34 574.90", "196,01", "1,250.00": последний разделитель с одной-двумя
+/// цифрами после него — дробная часть, остальные — разряды
+fn parse_balance(value: &str) -> Option<f64> {
+    let decimal = value
+        .rfind(['.', ','])
+        .filter(|index| (2..=3).contains(&(value.len() - index)));
+    let (integer, fraction) = match decimal {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    };
+    let integer: String = integer.chars().filter(char::is_ascii_digit).collect();
+    format!("{}.{}", integer, fraction).trim_end_matches('.').parse().ok()
+}
+
+/// Баланс после операции, если банк его пишет
+fn balance(stored: &StoredNotification) -> Option<f64> {
+    stored
+        .to_pending()
+        .texts()
+        .into_iter()
+        .find_map(|text| parse_balance(&balance_regex().captures(text)?[1]))
+}
+
+/// Продавцы совпадают, если совпали после нормализации ("Пятерочка" и
+/// "ПЯТЕРОЧКА 1234")
+fn merchants_match(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize_merchant_name(a), normalize_merchant_name(b));
+    !a.is_empty() && a == b
+}
+
+/// Карта должна быть известна в обоих уведомлениях
+fn cards_match(a: Option<&str>, b: Option<&str>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a == b)
+}
+
+fn repost_basis(
+    original: &StoredNotification,
+    original_payment: &ParsedPayment,
+    stored: &StoredNotification,
+    parsed: &ParsedPayment,
+) -> Option<RepostBasis> {
+    // Баланс уменьшился на сумму — это вторая настоящая покупка, даже с тем же ключом
+    match (balance(original), balance(stored)) {
+        (Some(before), Some(after)) if (before - parsed.amount - after).abs() < 0.005 => return None,
+        (Some(before), Some(after)) if (before - after).abs() < 0.005 => return Some(RepostBasis::Balance),
+        (Some(_), Some(_)) => return None,
+        _ => {}
+    }
+
+    if original.notification_key.is_some() && original.notification_key == stored.notification_key {
+        return Some(RepostBasis::NotificationKey);
+    }
+    if merchants_match(&original_payment.merchant_name, &parsed.merchant_name)
+        && cards_match(original_payment.card_mask.as_deref(), parsed.card_mask.as_deref())
+    {
+        return Some(RepostBasis::MerchantAndCard);
+    }
+    None
+}
+
+fn describe(
+    original: &StoredNotification,
+    stored: &StoredNotification,
+    parsed: &ParsedPayment,
+    basis: RepostBasis,
+) -> String {
+    let mut reason = format!("same amount {:.2} {}", parsed.amount, parsed.currency);
+    match basis {
+        RepostBasis::NotificationKey => reason.push_str(", same notification key"),
+        RepostBasis::Balance => reason.push_str(", same balance"),
+        RepostBasis::MerchantAndCard => reason.push_str(&format!(" and merchant '{}'", parsed.merchant_name)),
+    }
+    if let Some(card) = &parsed.card_mask {
+        reason.push_str(&format!(", card {}", card));
+    }
+    reason.push_str(&format!(
+        " as {} ({}s apart",
+        original.id,
+        (stored.timestamp - original.timestamp).abs() / 1000
+    ));
+    if original.package_name != stored.package_name {
+        reason.push_str(&format!(", from {}", original.package_name));
+    }
+    if original.text != stored.text {
+        reason.push_str(", text edited");
+    }
+    reason.push(')');
+    reason
+}
+
+/// Ищет более раннее уведомление о той же операции
+pub fn find_duplicate(
+    store: &NotificationStore,
+    config: &DedupConfig,
+    stored: &StoredNotification,
+    parsed: &ParsedPayment,
+) -> Result<Option<DuplicateMatch>, String> {
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let candidates = store.duplicate_candidates(
+        stored.row_id,
+        parsed,
+        stored.timestamp,
+        config.window_secs.saturating_mul(1000),
+    )?;
+
+    Ok(candidates.into_iter().find_map(|candidate| {
+        let basis = repost_basis(&candidate, candidate.parsed.as_ref()?, stored, parsed)?;
+        Some(DuplicateMatch {
+            original_id: candidate.row_id,
+            reason: describe(&candidate, stored, parsed, basis),
+        })
+    }))
+}
+
+/// Помечает уведомление дубликатом, если нашлось более раннее. Возвращает
+/// true, если уведомление ушло из очереди
+pub fn apply(
+    store: &NotificationStore,
+    stored: &StoredNotification,
+    parsed: &ParsedPayment,
+) -> Result<bool, String> {
+    let config = DedupConfig::load(store)?;
+    match find_duplicate(store, &config, stored, parsed)? {
+        Some(duplicate) => {
+            log::info!("Notification {} is a duplicate: {}", stored.id, duplicate.reason);
+            store.mark_duplicate(stored.row_id, duplicate.original_id, &duplicate.reason)
+        }
+        None => Ok(false),
+    }
+}
+
+#[tauri::command]
+pub async fn get_dedup_config(store: tauri::State<'_, NotificationStore>) -> Result<DedupConfig, String> {
+    let store = store.inner().clone();
+    worker::run(move || DedupConfig::load(&store)).await
+}
+
+#[tauri::command]
+pub async fn set_dedup_config(
+    store: tauri::State<'_, NotificationStore>,
+    config: DedupConfig,
+) -> Result<(), String> {
+    if config.window_secs < 0 {
+        return Err("Dedup window must not be negative".to_string());
+    }
+    let store = store.inner().clone();
+    worker::run(move || store.set_setting(DEDUP_CONFIG_KEY, &config)).await
+}
+
+#[tauri::command]
+pub async fn restore_duplicate_notification(
+    store: tauri::State<'_, NotificationStore>,
+    id: String,
+) -> Result<bool, String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let stored = store
+            .find_by_id(&id)?
+            .ok_or_else(|| format!("Notification not found: {}", id))?;
+        store.restore_duplicate(stored.row_id)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::store::{STATUS_DUPLICATE, STATUS_PENDING};
+    use std::path::PathBuf;
+
+    // Вторая строка — повтор первой с тем же балансом, третья — ещё одна
+    // покупка: баланс уменьшился ровно на сумму
+    const REPOSTS: &str = r#"[
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165","timestamp":1700000000000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка КУПЕР","text":"150,00 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165","timestamp":1700000030000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽ — Баланс: 46,01 ₽ MasterCard •• 7165","timestamp":1700000040000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽ — Баланс: 0,00 ₽ MasterCard •• 1111","timestamp":1700000050000},
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165","timestamp":1700003600000}
+    ]"#;
+
+    fn process_all(store: &NotificationStore) -> Vec<StoredNotification> {
+        for stored in store.pending().unwrap() {
+            parser::process_stored(store, &stored).unwrap();
+        }
+        store.query(&Default::default()).unwrap()
+    }
+
+    #[test]
+    fn marks_reposts_within_window_and_keeps_them() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        store.import_json(REPOSTS).unwrap();
+
+        let all = process_all(&store);
+        let statuses: Vec<&str> = all.iter().map(|n| n.status.as_str()).collect();
+        assert_eq!(statuses, [STATUS_PENDING, STATUS_DUPLICATE, STATUS_PENDING, STATUS_PENDING, STATUS_PENDING]);
+
+        let duplicate = &all[1];
+        assert_eq!(duplicate.duplicate_of.as_deref(), Some(all[0].id.as_str()));
+        let reason = duplicate.duplicate_reason.as_deref().unwrap();
+        assert!(reason.contains("150.00 RUB, same balance") && reason.contains("*7165"), "{}", reason);
+        assert!(reason.contains("30s apart") && reason.contains("text edited"), "{}", reason);
+
+        assert_eq!(parse_balance("34 574.90"), Some(34574.9));
+        assert_eq!(parse_balance("1,250.00"), Some(1250.0));
+        assert_eq!(parse_balance("12 345"), Some(12345.0));
+    }
+
+    #[test]
+    fn respects_window_and_restored_items() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        store
+            .set_setting(DEDUP_CONFIG_KEY, &DedupConfig { enabled: true, window_secs: 10 })
+            .unwrap();
+        store.import_json(REPOSTS).unwrap();
+        assert!(process_all(&store).iter().all(|n| n.status == STATUS_PENDING));
+
+        store.set_setting(DEDUP_CONFIG_KEY, &DedupConfig::default()).unwrap();
+        let duplicate = process_all(&store).remove(1);
+        assert_eq!(duplicate.status, STATUS_DUPLICATE);
+
+        assert!(store.restore_duplicate(duplicate.row_id).unwrap());
+        let restored = process_all(&store).remove(1);
+        assert_eq!(restored.status, STATUS_PENDING);
+        assert_eq!(restored.duplicate_reason, None);
+    }
+}
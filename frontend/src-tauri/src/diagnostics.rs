@@ -0,0 +1,402 @@
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use crate::drift::{self, ParserDrift};
+use crate::notifications::is_import_candidate;
+use crate::platform::{Platform, PlatformBridge};
+use crate::store::NotificationStore;
+use crate::worker;
+
+/// Журнал слушателя: строки "<ms> <reason>", пишет PaymentNotificationListenerService
+pub const HEARTBEAT_LOG_FILE: &str = "listener_heartbeats.log";
+
+// В отчёт попадают только последние события, счётчики — по всему журналу
+const REPORTED_EVENTS: usize = 50;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+/// Слушатель отмечается на каждом уведомлении банка и на ping из приложения,
+/// так что сутки тишины при выданном доступе — повод проверить его
+const STALE_HEARTBEAT_MS: i64 = DAY_MS;
+const RECENT_PARSE_FAILURE_MS: i64 = 7 * DAY_MS;
+
+/// Производители, прошивки которых останавливают фоновые сервисы без автозапуска
+const AGGRESSIVE_MANUFACTURERS: &[&str] = &[
+    "xiaomi", "redmi", "poco", "huawei", "honor", "oppo", "realme", "vivo", "oneplus", "meizu", "asus",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeartbeatEvent {
+    pub timestamp: i64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapKind {
+    /// Система отвязала слушателя (listener_disconnected → listener_connected)
+    Disconnected,
+    /// Сервис остановлен штатно (service_destroyed → service_created)
+    Stopped,
+    /// Процесс убит без onDestroy: последний признак жизни → service_created
+    Killed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeartbeatGap {
+    pub kind: GapKind,
+    pub from: i64,
+    pub to: i64,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct HeartbeatHistory {
+    pub events: Vec<HeartbeatEvent>,
+    pub restarts: usize,
+    pub unclean_restarts: usize,
+    pub gaps: Vec<HeartbeatGap>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParseFailure {
+    pub id: String,
+    pub package_name: String,
+    pub title: String,
+    pub text: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Recommendation {
+    pub id: String,
+    /// Чем больше, тем важнее; рекомендации отсортированы по убыванию
+    pub priority: u32,
+    pub message: String,
+}
+
+/// Всё, что нужно для разбора обращения «платежи не импортируются», одним вызовом.
+/// Проверка, которая не удалась, остаётся `None`, а её ошибка попадает в `errors`
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportDiagnostics {
+    pub generated_at: i64,
+    pub platform: String,
+    pub listener_permission: Option<bool>,
+    pub app_notification_permission: Option<bool>,
+    pub battery_optimization_disabled: Option<bool>,
+    pub autostart_enabled: Option<bool>,
+    pub manufacturer: Option<String>,
+    pub last_heartbeat: Option<i64>,
+    pub heartbeat_history: HeartbeatHistory,
+    pub pending_count: Option<usize>,
+    pub last_parse_failure: Option<ParseFailure>,
+    /// Банки, уведомления которых в последние дни перестали разбираться
+    pub parser_drift: Option<Vec<ParserDrift>>,
+    pub recommendations: Vec<Recommendation>,
+    pub errors: Vec<String>,
+}
+
+fn parse_heartbeat_log(content: &str) -> Vec<HeartbeatEvent> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (timestamp, reason) = line.trim().split_once(' ')?;
+            Some(HeartbeatEvent {
+                timestamp: timestamp.parse().ok()?,
+                reason: reason.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Разбирает журнал: перезапуски сервиса и периоды, когда слушатель не работал
+pub fn heartbeat_history(events: Vec<HeartbeatEvent>) -> HeartbeatHistory {
+    let mut history = HeartbeatHistory::default();
+    let mut disconnected_at: Option<i64> = None;
+    let mut previous: Option<&HeartbeatEvent> = None;
+
+    for event in &events {
+        match event.reason.as_str() {
+            "listener_disconnected" => disconnected_at = Some(event.timestamp),
+            "listener_connected" => {
+                if let Some(from) = disconnected_at.take() {
+                    history.gaps.push(gap(GapKind::Disconnected, from, event.timestamp));
+                }
+            }
+            "service_created" => {
+                history.restarts += 1;
+                if let Some(previous) = previous {
+                    let kind = if previous.reason == "service_destroyed" {
+                        GapKind::Stopped
+                    } else {
+                        history.unclean_restarts += 1;
+                        GapKind::Killed
+                    };
+                    history.gaps.push(gap(kind, previous.timestamp, event.timestamp));
+                }
+                disconnected_at = None;
+            }
+            _ => {}
+        }
+        previous = Some(event);
+    }
+
+    let skip = events.len().saturating_sub(REPORTED_EVENTS);
+    history.events = events.into_iter().skip(skip).collect();
+    history
+}
+
+fn gap(kind: GapKind, from: i64, to: i64) -> HeartbeatGap {
+    HeartbeatGap {
+        kind,
+        from,
+        to,
+        duration_ms: (to - from).max(0),
+    }
+}
+
+fn read_heartbeat_log(data_dir: &Path) -> Result<Vec<HeartbeatEvent>, String> {
+    match fs::read_to_string(data_dir.join(HEARTBEAT_LOG_FILE)) {
+        Ok(content) => Ok(parse_heartbeat_log(&content)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {:?}", HEARTBEAT_LOG_FILE, e)),
+    }
+}
+
+fn recommendation(id: &str, priority: u32, message: String) -> Recommendation {
+    Recommendation {
+        id: id.to_string(),
+        priority,
+        message,
+    }
+}
+
+fn recommend(report: &ImportDiagnostics, now: i64) -> Vec<Recommendation> {
+    let android = report.platform == "android";
+    let killed = report.heartbeat_history.unclean_restarts > 0;
+    let mut recommendations = Vec::new();
+
+    match (report.listener_permission, android) {
+        (Some(false), true) => recommendations.push(recommendation(
+            "enable_listener_access",
+            100,
+            "Разрешите приложению доступ к уведомлениям — без него платежи не импортируются".to_string(),
+        )),
+        (Some(false), false) => recommendations.push(recommendation(
+            "enable_dbus_monitor",
+            100,
+            "Включите приём уведомлений с D-Bus в настройках".to_string(),
+        )),
+        _ => {}
+    }
+
+    let heartbeat_stale = match report.last_heartbeat {
+        Some(heartbeat) => heartbeat <= 0 || now - heartbeat > STALE_HEARTBEAT_MS,
+        None => false,
+    };
+    if android && report.listener_permission == Some(true) && heartbeat_stale {
+        recommendations.push(recommendation(
+            "restart_listener",
+            90,
+            "Слушатель уведомлений давно не отвечал: выключите и снова включите доступ к уведомлениям".to_string(),
+        ));
+    }
+
+    if android && report.battery_optimization_disabled == Some(false) {
+        recommendations.push(recommendation(
+            "disable_battery_optimization",
+            if killed { 85 } else { 70 },
+            "Отключите оптимизацию батареи для приложения, чтобы система не останавливала слушатель".to_string(),
+        ));
+    }
+
+    let manufacturer = report.manufacturer.as_deref().unwrap_or_default().to_lowercase();
+    let aggressive = AGGRESSIVE_MANUFACTURERS.iter().any(|m| manufacturer.contains(m));
+    if android && aggressive && report.autostart_enabled == Some(false) {
+        recommendations.push(recommendation(
+            "enable_autostart",
+            if killed { 80 } else { 60 },
+            format!("Разрешите автозапуск приложения в настройках {}", report.manufacturer.as_deref().unwrap_or_default()),
+        ));
+    } else if killed {
+        recommendations.push(recommendation(
+            "check_background_limits",
+            50,
+            format!(
+                "Система {} раз останавливала слушатель без предупреждения: проверьте ограничения фоновой работы",
+                report.heartbeat_history.unclean_restarts
+            ),
+        ));
+    }
+
+    if let Some(failure) = &report.last_parse_failure {
+        if now - failure.timestamp <= RECENT_PARSE_FAILURE_MS {
+            recommendations.push(recommendation(
+                "report_unparsed_notification",
+                40,
+                format!("Уведомление от {} не удалось разобрать: отправьте его в поддержку", failure.package_name),
+            ));
+        }
+    }
+
+    for drift in report.parser_drift.iter().flatten() {
+        recommendations.push(recommendation(
+            "report_parser_drift",
+            45,
+            format!(
+                "Похоже, {} изменил формат уведомлений: разобрано {:.0}% против {:.0}% раньше. Отправьте пример в поддержку",
+                drift.bank,
+                drift.recent_rate * 100.0,
+                drift.baseline_rate * 100.0
+            ),
+        ));
+    }
+
+    if android && report.app_notification_permission == Some(false) {
+        recommendations.push(recommendation(
+            "enable_app_notifications",
+            30,
+            "Разрешите уведомления приложения, чтобы видеть новые платежи для импорта".to_string(),
+        ));
+    }
+
+    if let Some(pending) = report.pending_count.filter(|count| *count > 0) {
+        recommendations.push(recommendation(
+            "review_pending",
+            10,
+            format!("Необработанных уведомлений: {}", pending),
+        ));
+    }
+
+    recommendations.sort_by_key(|r| std::cmp::Reverse(r.priority));
+    recommendations
+}
+
+/// Записывает результат проверки или её ошибку
+fn check<T>(errors: &mut Vec<String>, name: &str, result: Result<T, String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            errors.push(format!("{}: {}", name, e));
+            None
+        }
+    }
+}
+
+pub fn collect(platform: &dyn PlatformBridge, store: &NotificationStore) -> ImportDiagnostics {
+    let mut errors = Vec::new();
+    let now = chrono::Utc::now().timestamp_millis();
+
+    let events = platform
+        .files_dir()
+        .and_then(|data_dir| read_heartbeat_log(&data_dir));
+    let pending_count = store.drain_spool().and_then(|_| store.pending()).map(|pending| {
+        pending
+            .iter()
+            .filter(|stored| is_import_candidate(&stored.to_pending()))
+            .count()
+    });
+    let last_parse_failure = store.last_parse_failure().map(|failure| {
+        failure.map(|stored| ParseFailure {
+            id: stored.id,
+            package_name: stored.package_name,
+            title: stored.title,
+            text: stored.text,
+            timestamp: stored.timestamp,
+        })
+    });
+
+    let mut report = ImportDiagnostics {
+        generated_at: now,
+        platform: platform.platform_name().to_string(),
+        listener_permission: check(&mut errors, "listener_permission", platform.notification_listener_enabled()),
+        app_notification_permission: check(
+            &mut errors,
+            "app_notification_permission",
+            platform.app_notification_permission(),
+        ),
+        battery_optimization_disabled: check(
+            &mut errors,
+            "battery_optimization",
+            platform.battery_optimization_disabled(),
+        ),
+        autostart_enabled: check(&mut errors, "autostart", platform.autostart_enabled()),
+        manufacturer: check(&mut errors, "manufacturer", platform.device_manufacturer()),
+        last_heartbeat: check(&mut errors, "heartbeat", platform.listener_heartbeat()),
+        heartbeat_history: heartbeat_history(check(&mut errors, "heartbeat_history", events).unwrap_or_default()),
+        pending_count: check(&mut errors, "pending_count", pending_count),
+        last_parse_failure: check(&mut errors, "last_parse_failure", last_parse_failure).flatten(),
+        parser_drift: check(&mut errors, "parser_drift", drift::detect(store, now)),
+        recommendations: Vec::new(),
+        errors: Vec::new(),
+    };
+    report.recommendations = recommend(&report, now);
+    report.errors = errors;
+    report
+}
+
+#[tauri::command]
+pub async fn get_import_diagnostics(
+    platform: tauri::State<'_, Platform>,
+    store: tauri::State<'_, NotificationStore>,
+) -> Result<ImportDiagnostics, String> {
+    let platform = platform.inner().clone();
+    let store = store.inner().clone();
+    worker::run(move || Ok(collect(platform.as_ref(), &store))).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::mock::{MockBridge, MockState};
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_restarts_and_gaps() {
+        let history = heartbeat_history(parse_heartbeat_log(
+            "1000 service_created\n\
+             1500 listener_connected\n\
+             garbage\n\
+             2000 listener_disconnected\n\
+             2600 listener_connected\n\
+             9000 notification_received\n\
+             20000 service_created\n\
+             21000 service_destroyed\n\
+             21400 service_created\n",
+        ));
+
+        assert_eq!((history.restarts, history.unclean_restarts), (3, 1));
+        let gaps: Vec<(GapKind, i64)> = history.gaps.iter().map(|g| (g.kind, g.duration_ms)).collect();
+        assert_eq!(gaps, [(GapKind::Disconnected, 600), (GapKind::Killed, 11000), (GapKind::Stopped, 400)]);
+        assert_eq!(history.events.len(), 8);
+    }
+
+    #[test]
+    fn ranks_recommendations() {
+        let dir = std::env::temp_dir().join(format!("hpio-diagnostics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(HEARTBEAT_LOG_FILE), "1000 service_created\n5000 notification_received\n9000 service_created\n")
+            .unwrap();
+        let platform = MockBridge::new(MockState {
+            platform_name: "android",
+            listener_enabled: true,
+            app_permission: false,
+            heartbeat: chrono::Utc::now().timestamp_millis(),
+            battery_optimization_disabled: false,
+            autostart_enabled: false,
+            manufacturer: "Xiaomi".to_string(),
+            files_dir: dir.clone(),
+            ..MockState::default()
+        });
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+
+        let report = collect(&platform, &store);
+        let ids: Vec<&str> = report.recommendations.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["disable_battery_optimization", "enable_autostart", "enable_app_notifications"]);
+        assert_eq!(report.heartbeat_history.unclean_restarts, 1);
+        assert_eq!(report.pending_count, Some(0));
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
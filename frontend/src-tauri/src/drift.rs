@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::redaction;
+use crate::store::{NotificationStore, ParseStats};
+use crate::worker;
+
+pub const PARSER_DRIFT_EVENT: &str = "parser-drift";
+/// Когда по приложению последний раз поднималась тревога: package_name -> мс
+pub const PARSER_DRIFT_ALERTS_KEY: &str = "parser_drift_alerts";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+/// Свежие уведомления сравниваются с предыдущими четырьмя неделями
+const RECENT_WINDOW_MS: i64 = 3 * DAY_MS;
+const BASELINE_WINDOW_MS: i64 = 28 * DAY_MS;
+// Меньше уведомлений — слишком шумная доля, чтобы о ней сообщать
+const MIN_RECENT: u32 = 5;
+const MIN_BASELINE: u32 = 10;
+/// На сколько должна упасть доля разобранных уведомлений
+const MIN_RATE_DROP: f64 = 0.3;
+/// Повторная тревога по тому же приложению — не раньше чем через сутки
+const ALERT_COOLDOWN_MS: i64 = DAY_MS;
+const SAMPLE_COUNT: u32 = 3;
+const SAMPLE_LENGTH: usize = 200;
+
+/// Банк стал присылать уведомления, которые правила больше не разбирают
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParserDrift {
+    pub package_name: String,
+    pub bank: String,
+    pub baseline_rate: f64,
+    pub recent_rate: f64,
+    pub baseline_total: u32,
+    pub recent_total: u32,
+    pub recent_failures: u32,
+    /// Неразобранные уведомления без кодов и номеров, свежие первыми
+    pub samples: Vec<String>,
+    pub detected_at: i64,
+}
+
+fn rate(stats: &ParseStats) -> f64 {
+    f64::from(stats.parsed) / f64::from(stats.total)
+}
+
+fn drifted(baseline: &ParseStats, recent: &ParseStats) -> bool {
+    baseline.total >= MIN_BASELINE
+        && recent.total >= MIN_RECENT
+        && rate(baseline) - rate(recent) >= MIN_RATE_DROP
+}
+
+/// Образец для тревоги. Текст из базы уже замаскирован, но образец уходит
+/// в журнал и фронтенду, поэтому проходит маскирование ещё раз
+fn sample(title: &str, text: &str) -> String {
+    redaction::redact_text(&format!("{} — {}", title.trim(), text.trim()))
+        .chars()
+        .take(SAMPLE_LENGTH)
+        .collect()
+}
+
+/// Приложения, у которых за последние дни резко упала доля разобранных уведомлений
+pub fn detect(store: &NotificationStore, now: i64) -> Result<Vec<ParserDrift>, String> {
+    let recent_from = now - RECENT_WINDOW_MS;
+    let baseline: HashMap<String, ParseStats> = store
+        .parse_stats(recent_from - BASELINE_WINDOW_MS, recent_from)?
+        .into_iter()
+        .map(|stats| (stats.package_name.clone(), stats))
+        .collect();
+
+    let mut drifts = Vec::new();
+    // Время уведомления задаёт телефон, поэтому верхней границы у окна нет
+    for recent in store.parse_stats(recent_from, i64::MAX)? {
+        let Some(baseline) = baseline.get(&recent.package_name) else {
+            continue;
+        };
+        if !drifted(baseline, &recent) {
+            continue;
+        }
+
+        let samples = store
+            .parse_failures(&recent.package_name, recent_from, SAMPLE_COUNT)?
+            .iter()
+            .map(|stored| sample(&stored.title, &stored.text))
+            .collect();
+        drifts.push(ParserDrift {
+            bank: baseline.bank.clone().unwrap_or_else(|| recent.package_name.clone()),
+            baseline_rate: rate(baseline),
+            recent_rate: rate(&recent),
+            baseline_total: baseline.total,
+            recent_total: recent.total,
+            recent_failures: recent.total - recent.parsed,
+            samples,
+            detected_at: now,
+            package_name: recent.package_name,
+        });
+    }
+    Ok(drifts)
+}
+
+/// То же, что `detect`, но о каждом приложении сообщает не чаще раза в сутки.
+/// Возвращает только новые тревоги
+pub fn check(store: &NotificationStore, now: i64) -> Result<Vec<ParserDrift>, String> {
+    let drifts = detect(store, now)?;
+    let mut alerted: HashMap<String, i64> = store.get_setting(PARSER_DRIFT_ALERTS_KEY)?.unwrap_or_default();
+
+    let fresh: Vec<ParserDrift> = drifts
+        .into_iter()
+        .filter(|drift| {
+            alerted
+                .get(&drift.package_name)
+                .filter(|alerted_at| now - **alerted_at < ALERT_COOLDOWN_MS)
+                .is_none()
+        })
+        .collect();
+    if fresh.is_empty() {
+        return Ok(fresh);
+    }
+
+    alerted.retain(|_, alerted_at| now - *alerted_at < ALERT_COOLDOWN_MS);
+    for drift in &fresh {
+        alerted.insert(drift.package_name.clone(), now);
+        log::warn!(
+            "Parse rate for {} ({}) dropped from {:.0}% to {:.0}%: {} of {} recent notifications are not parsed",
+            drift.bank,
+            drift.package_name,
+            drift.baseline_rate * 100.0,
+            drift.recent_rate * 100.0,
+            drift.recent_failures,
+            drift.recent_total
+        );
+    }
+    store.set_setting(PARSER_DRIFT_ALERTS_KEY, &alerted)?;
+    Ok(fresh)
+}
+
+#[tauri::command]
+pub async fn get_parser_drift(store: tauri::State<'_, NotificationStore>) -> Result<Vec<ParserDrift>, String> {
+    let store = store.inner().clone();
+    worker::run(move || detect(&store, chrono::Utc::now().timestamp_millis())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::{NotificationExtras, PendingNotification};
+    use crate::parser;
+    use std::path::PathBuf;
+
+    fn receive(store: &NotificationStore, title: &str, text: String, timestamp: i64) {
+        let notification = PendingNotification {
+            id: String::new(),
+            package_name: "ru.sberbankmobile".to_string(),
+            title: title.to_string(),
+            text,
+            timestamp,
+            notification_type: Some("PAYMENT".to_string()),
+            notification_key: None,
+            extras: NotificationExtras::default(),
+        }
+        .with_computed_id();
+        for stored in store.insert_all(&[notification]).unwrap() {
+            parser::process_stored(store, &stored).unwrap();
+        }
+    }
+
+    #[test]
+    fn alerts_once_when_a_bank_changes_its_wording() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let now = 1_700_000_000_000;
+        for i in 0..12 {
+            let text = format!("{} ₽ — Баланс: 12 345,67 ₽ MasterCard •• 7165", 100 + i);
+            receive(&store, "Покупка Пятерочка", text, now - 10 * DAY_MS + i64::from(i) * 1000);
+        }
+        assert!(check(&store, now).unwrap().is_empty());
+
+        for i in 0..6 {
+            let text = format!("Списание {} ₽ по карте 2202 2061 2345 7165", 200 + i);
+            receive(&store, "Покупка", text, now - DAY_MS + i64::from(i) * 1000);
+        }
+        let drifts = check(&store, now).unwrap();
+        assert_eq!(drifts.len(), 1);
+        let drift = &drifts[0];
+        assert_eq!((drift.bank.as_str(), drift.recent_failures, drift.recent_total), ("sberbank", 6, 6));
+        assert_eq!((drift.baseline_rate, drift.recent_rate), (1.0, 0.0));
+        assert_eq!(drift.samples.len(), SAMPLE_COUNT as usize);
+        assert_eq!(drift.samples[0], "Покупка — Списание 205 ₽ по карте •••• •••• •••• 7165");
+
+        assert!(check(&store, now + DAY_MS / 2).unwrap().is_empty());
+        assert_eq!(check(&store, now + DAY_MS).unwrap().len(), 1);
+        assert_eq!(detect(&store, now + DAY_MS).unwrap().len(), 1);
+    }
+}
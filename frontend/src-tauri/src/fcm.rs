@@ -1,208 +1,79 @@
-use serde::{Deserialize, Serialize};
-
-#[tauri::command]
-pub fn get_fcm_token() -> Result<Option<String>, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        // Get SharedPreferences
-        let prefs_name = env.new_string("fcm_prefs")
-            .map_err(|e| format!("Failed to create string: {:?}", e))?;
-
-        let prefs = env.call_method(
-            &context,
-            "getSharedPreferences",
-            "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-            &[JValue::Object(&prefs_name), JValue::Int(0)],
-        )
-        .map_err(|e| format!("Failed to get SharedPreferences: {:?}", e))?;
-
-        let prefs_obj = prefs.l()
-            .map_err(|e| format!("Failed to get prefs object: {:?}", e))?;
-
-        // Get token from SharedPreferences
-        let token_key = env.new_string("fcm_token")
-            .map_err(|e| format!("Failed to create token key: {:?}", e))?;
-
-        let default_value = env.new_string("")
-            .map_err(|e| format!("Failed to create default value: {:?}", e))?;
-
-        let token_result = env.call_method(
-            &prefs_obj,
-            "getString",
-            "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
-            &[JValue::Object(&token_key), JValue::Object(&default_value)],
-        )
-        .map_err(|e| format!("Failed to get token: {:?}", e))?;
-
-        let token_obj = token_result.l()
-            .map_err(|e| format!("Failed to get token object: {:?}", e))?;
+use crate::platform::{Platform, PlatformBridge};
+use crate::worker;
+
+const FCM_PREFS: &str = "fcm_prefs";
+const FCM_TOKEN_KEY: &str = "fcm_token";
+const APP_PREFS: &str = "app_prefs";
+const PENDING_NAVIGATION_KEY: &str = "pending_navigation";
+
+/// Пустая строка в SharedPreferences означает, что значения нет
+fn non_empty_pref(
+    platform: &dyn PlatformBridge,
+    prefs: &str,
+    key: &str,
+) -> Result<Option<String>, String> {
+    Ok(platform
+        .get_pref(prefs, key)?
+        .filter(|value| !value.is_empty()))
+}
 
-        let token_jstring = jni::objects::JString::from(token_obj);
-        let token_str: String = env.get_string(&token_jstring)
-            .map_err(|e| format!("Failed to get token string: {:?}", e))?
-            .into();
+pub(crate) fn fcm_token(platform: &dyn PlatformBridge) -> Result<Option<String>, String> {
+    non_empty_pref(platform, FCM_PREFS, FCM_TOKEN_KEY)
+}
 
-        if token_str.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(token_str))
-        }
-    }
+pub(crate) fn pending_navigation(platform: &dyn PlatformBridge) -> Result<Option<String>, String> {
+    non_empty_pref(platform, APP_PREFS, PENDING_NAVIGATION_KEY)
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(None)
-    }
+pub(crate) fn clear_navigation(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.remove_pref(APP_PREFS, PENDING_NAVIGATION_KEY)
 }
 
 #[tauri::command]
-pub fn get_pending_navigation() -> Result<Option<String>, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let prefs_name = env.new_string("app_prefs")
-            .map_err(|e| format!("Failed to create string: {:?}", e))?;
-
-        let prefs = env.call_method(
-            &context,
-            "getSharedPreferences",
-            "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-            &[JValue::Object(&prefs_name), JValue::Int(0)],
-        )
-        .map_err(|e| format!("Failed to get SharedPreferences: {:?}", e))?;
-
-        let prefs_obj = prefs.l()
-            .map_err(|e| format!("Failed to get prefs object: {:?}", e))?;
-
-        let nav_key = env.new_string("pending_navigation")
-            .map_err(|e| format!("Failed to create nav key: {:?}", e))?;
-
-        let default_value = env.new_string("")
-            .map_err(|e| format!("Failed to create default value: {:?}", e))?;
-
-        let nav_result = env.call_method(
-            &prefs_obj,
-            "getString",
-            "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
-            &[JValue::Object(&nav_key), JValue::Object(&default_value)],
-        )
-        .map_err(|e| format!("Failed to get navigation: {:?}", e))?;
-
-        let nav_obj = nav_result.l()
-            .map_err(|e| format!("Failed to get navigation object: {:?}", e))?;
-
-        let nav_jstring = jni::objects::JString::from(nav_obj);
-        let nav_str: String = env.get_string(&nav_jstring)
-            .map_err(|e| format!("Failed to get navigation string: {:?}", e))?
-            .into();
-
-        if nav_str.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(nav_str))
-        }
-    }
-
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(None)
-    }
+pub async fn get_fcm_token(platform: tauri::State<'_, Platform>) -> Result<Option<String>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || fcm_token(platform.as_ref())).await
 }
 
 #[tauri::command]
-pub fn clear_pending_navigation() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let prefs_name = env.new_string("app_prefs")
-            .map_err(|e| format!("Failed to create string: {:?}", e))?;
-
-        let prefs = env.call_method(
-            &context,
-            "getSharedPreferences",
-            "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
-            &[JValue::Object(&prefs_name), JValue::Int(0)],
-        )
-        .map_err(|e| format!("Failed to get SharedPreferences: {:?}", e))?;
-
-        let prefs_obj = prefs.l()
-            .map_err(|e| format!("Failed to get prefs object: {:?}", e))?;
-
-        let editor = env.call_method(
-            &prefs_obj,
-            "edit",
-            "()Landroid/content/SharedPreferences$Editor;",
-            &[],
-        )
-        .map_err(|e| format!("Failed to get editor: {:?}", e))?;
-
-        let editor_obj = editor.l()
-            .map_err(|e| format!("Failed to get editor object: {:?}", e))?;
-
-        let nav_key = env.new_string("pending_navigation")
-            .map_err(|e| format!("Failed to create nav key: {:?}", e))?;
+pub async fn get_pending_navigation(platform: tauri::State<'_, Platform>) -> Result<Option<String>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || pending_navigation(platform.as_ref())).await
+}
 
-        env.call_method(
-            &editor_obj,
-            "remove",
-            "(Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
-            &[JValue::Object(&nav_key)],
-        )
-        .map_err(|e| format!("Failed to remove key: {:?}", e))?;
+#[tauri::command]
+pub async fn clear_pending_navigation(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || clear_navigation(platform.as_ref())).await
+}
 
-        env.call_method(
-            &editor_obj,
-            "apply",
-            "()V",
-            &[],
-        )
-        .map_err(|e| format!("Failed to apply changes: {:?}", e))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::mock::{MockBridge, MockState};
 
-        Ok(())
+    fn pref(prefs: &str, key: &str, value: &str) -> ((String, String), String) {
+        ((prefs.to_string(), key.to_string()), value.to_string())
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(())
+    #[test]
+    fn treats_empty_preferences_as_missing() {
+        let platform = MockBridge::new(MockState {
+            prefs: [pref(FCM_PREFS, FCM_TOKEN_KEY, ""), pref(APP_PREFS, PENDING_NAVIGATION_KEY, "/payments/42")]
+                .into_iter()
+                .collect(),
+            ..MockState::default()
+        });
+
+        assert_eq!(fcm_token(&platform).unwrap(), None);
+        assert_eq!(pending_navigation(&platform).unwrap().as_deref(), Some("/payments/42"));
+
+        assert_eq!(clear_navigation(&platform), Ok(()));
+        assert_eq!(pending_navigation(&platform).unwrap(), None);
+
+        platform.state().failing = vec!["get_pref", "remove_pref"];
+        assert_eq!(fcm_token(&platform).unwrap_err(), "get_pref failed");
+        assert!(pending_navigation(&platform).is_err());
+        assert_eq!(clear_navigation(&platform), Err("remove_pref failed".to_string()));
     }
 }
@@ -1,56 +1,40 @@
 mod notifications;
 mod fcm;
+mod parser;
+mod parser_rules;
+mod store;
+mod watcher;
+mod classifier;
+mod dedup;
+mod merchant_rules;
+mod dbus_monitor;
+mod platform;
+mod worker;
+mod spool;
+mod schema;
+mod quarantine;
+mod monitored_packages;
+mod archive;
+mod diagnostics;
+mod simulate;
+mod clipboard_import;
+mod template_learning;
+mod drift;
+mod redaction;
 
 use std::panic;
+use tauri::Manager;
 
 #[cfg(target_os = "android")]
 fn append_to_android_logs(log_line: &str) -> bool {
-  use jni::objects::JObject;
-  use jni::JavaVM;
+  use platform::PlatformBridge;
   use std::fs::OpenOptions;
   use std::io::Write;
-  use std::os::raw::c_void;
-  use std::path::PathBuf;
 
-  let ctx = ndk_context::android_context();
-  let vm_ptr = ctx.vm() as *mut c_void;
-  let Ok(vm) = (unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }) else {
+  let Ok(files_dir) = platform::android::AndroidBridge.files_dir() else {
     return false;
   };
-
-  let Ok(mut env) = vm.attach_current_thread() else {
-    return false;
-  };
-
-  let context_global: JObject<'static> =
-    unsafe { JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-  let Ok(context) = env.new_local_ref(&context_global) else {
-    return false;
-  };
-
-  let Ok(files_dir_obj) = env.call_method(&context, "getFilesDir", "()Ljava/io/File;", &[]) else {
-    return false;
-  };
-  let Ok(files_dir_obj) = files_dir_obj.l() else {
-    return false;
-  };
-
-  let Ok(path_jstring) =
-    env.call_method(&files_dir_obj, "getAbsolutePath", "()Ljava/lang/String;", &[])
-  else {
-    return false;
-  };
-  let Ok(path_obj) = path_jstring.l() else {
-    return false;
-  };
-  let path_jstring = jni::objects::JString::from(path_obj);
-  let Ok(path_str) = env.get_string(&path_jstring) else {
-    return false;
-  };
-  let path_str: String = path_str.into();
-
-  let mut log_path = PathBuf::from(path_str);
-  log_path.push("logs.txt");
+  let log_path = files_dir.join("logs.txt");
 
   if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log_path) {
     let _ = file.write_all(log_line.as_bytes());
@@ -104,6 +88,34 @@ fn setup_panic_hook() {
   }));
 }
 
+/// Открывает хранилище уведомлений и запускает всё, что на нём работает
+fn start_notification_import(app: &tauri::App, platform: &platform::Platform) -> Result<(), String> {
+  let data_dir = platform.files_dir()?;
+  // Пользовательские правила парсера; при ошибке остаются встроенные
+  if let Err(e) = parser_rules::reload(&data_dir) {
+    log::error!("Failed to load parser rules: {}", e);
+  }
+
+  // Переносим накопленный pending_notifications.json в SQLite при старте
+  let store = store::NotificationStore::open(&data_dir)?;
+  if let Err(e) = watcher::start(app.handle().clone(), store.clone()) {
+    log::error!("Failed to start pending notification watcher: {}", e);
+  }
+  if let Err(e) = store.drain_spool() {
+    log::error!("Failed to import pending notifications: {}", e);
+  }
+  if let Err(e) = archive::prune(&store) {
+    log::error!("Failed to prune notification archive: {}", e);
+  }
+  // Опциональный приём уведомлений с D-Bus (Linux)
+  if let Err(e) = dbus_monitor::start(store.clone()) {
+    log::error!("Failed to start D-Bus notification monitor: {}", e);
+  }
+  app.manage(store);
+
+  Ok(())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   setup_panic_hook();
@@ -122,14 +134,45 @@ pub fn run() {
         notifications::request_app_notification_permission,
         notifications::open_app_notification_settings,
         notifications::simulate_app_payment_notification,
+        simulate::list_simulation_templates,
+        simulate::simulate_notification,
         notifications::get_pending_notifications,
         notifications::clear_pending_notifications,
+        notifications::acknowledge_pending_notifications,
+        notifications::link_notification_to_payment,
         notifications::get_notification_service_status,
         notifications::ping_notification_listener_service,
         notifications::check_battery_optimization_disabled,
         notifications::open_battery_optimization_settings,
         notifications::check_autostart_enabled,
         notifications::open_autostart_settings,
+        notifications::get_device_manufacturer,
+        diagnostics::get_import_diagnostics,
+        drift::get_parser_drift,
+        parser::parse_pending_notifications,
+        clipboard_import::parse_pasted_payments,
+        clipboard_import::import_clipboard_payments,
+        parser_rules::reload_parser_rules,
+        template_learning::learn_parser_template,
+        classifier::classify_notification,
+        dedup::get_dedup_config,
+        dedup::set_dedup_config,
+        dedup::restore_duplicate_notification,
+        merchant_rules::cache_merchant_rules,
+        merchant_rules::categorize_merchant,
+        dbus_monitor::get_dbus_monitor_config,
+        dbus_monitor::set_dbus_monitor_config,
+        store::query_notifications,
+        quarantine::list_quarantined_notifications,
+        quarantine::export_quarantined_notifications,
+        monitored_packages::get_monitored_packages,
+        monitored_packages::add_monitored_package,
+        monitored_packages::remove_monitored_package,
+        monitored_packages::get_seen_packages,
+        archive::query_archive,
+        archive::undo_dismissal,
+        archive::get_archive_retention,
+        archive::set_archive_retention,
         fcm::get_fcm_token,
         fcm::get_pending_navigation,
         fcm::clear_pending_navigation
@@ -142,6 +185,19 @@ pub fn run() {
               .build(),
           )?;
         }
+
+        // Без моста или хранилища приложение запускается без импорта
+        // уведомлений, а команды, которым они нужны, возвращают ошибку
+        match platform::default_bridge(app.handle()) {
+          Ok(platform) => {
+            if let Err(e) = start_notification_import(app, &platform) {
+              log::error!("Notification import disabled: {}", e);
+            }
+            app.manage(platform);
+          }
+          Err(e) => log::error!("Failed to initialize platform bridge: {}", e),
+        }
+
         Ok(())
       })
       .run(tauri::generate_context!())
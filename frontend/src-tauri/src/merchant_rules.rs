@@ -0,0 +1,258 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use crate::store::NotificationStore;
+use crate::worker;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleCategory {
+    pub id: String,
+    pub name: String,
+}
+
+/// Правило в формате `/merchant-rules` бэкенда
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MerchantCategoryRule {
+    pub id: String,
+    pub category_id: String,
+    pub merchant_keyword: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    #[serde(default)]
+    pub transaction_category: Option<RuleCategory>,
+    /// Теги для платежа. Бэкенд их не присылает, тогда тегом становится
+    /// название категории (см. `rule_tags`)
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchType {
+    /// Нормализованное имя совпало с ключом правила, как на бэкенде
+    Exact,
+    /// Ключ правила встречается в имени продавца целыми словами
+    Keyword,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySuggestion {
+    pub rule_id: String,
+    pub category_id: String,
+    pub category_name: Option<String>,
+    pub merchant_keyword: String,
+    pub tags: Vec<String>,
+    pub match_type: MatchType,
+}
+
+/// Порт `normalizeMerchantName` из merchantNormalizer.ts: нижний регистр,
+/// только латиница, кириллица и пробелы, пробелы схлопнуты
+pub fn normalize_merchant_name(merchant_name: &str) -> String {
+    merchant_name
+        .to_lowercase()
+        .chars()
+        .filter(|c| matches!(c, 'a'..='z' | 'а'..='я' | 'ё') || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Теги из правила, а без них — название его категории
+fn rule_tags(rule: &MerchantCategoryRule) -> Vec<String> {
+    if !rule.tags.is_empty() {
+        return rule.tags.clone();
+    }
+    rule.transaction_category
+        .iter()
+        .map(|category| category.name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Сохраняет правила в локальный кэш. `replace` заменяет весь кэш (полный
+/// список правил), иначе правила добавляются или обновляются по id.
+pub fn cache_rules(
+    store: &NotificationStore,
+    rules: &[MerchantCategoryRule],
+    replace: bool,
+) -> Result<usize, String> {
+    let mut conn = store.connection()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {:?}", e))?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    if replace {
+        tx.execute("DELETE FROM merchant_rules", [])
+            .map_err(|e| format!("Failed to clear merchant rules: {:?}", e))?;
+    }
+
+    for rule in rules {
+        let tags = serde_json::to_string(&rule_tags(rule))
+            .map_err(|e| format!("Failed to serialize rule tags: {:?}", e))?;
+        tx.execute(
+            "INSERT OR REPLACE INTO merchant_rules
+                (id, category_id, category_name, merchant_keyword, normalized_keyword, tags, updated_at, cached_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                rule.id,
+                rule.category_id,
+                rule.transaction_category.as_ref().map(|c| c.name.as_str()),
+                rule.merchant_keyword,
+                normalize_merchant_name(&rule.merchant_keyword),
+                tags,
+                rule.updated_at,
+                now,
+            ],
+        )
+        .map_err(|e| format!("Failed to cache merchant rule: {:?}", e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit merchant rules: {:?}", e))?;
+    Ok(rules.len())
+}
+
+fn cached_rules(store: &NotificationStore) -> Result<Vec<(String, CategorySuggestion)>, String> {
+    let conn = store.connection()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, category_id, category_name, merchant_keyword, normalized_keyword, tags
+             FROM merchant_rules",
+        )
+        .map_err(|e| format!("Failed to prepare merchant rules query: {:?}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let tags: String = row.get(5)?;
+            Ok((
+                row.get::<_, String>(4)?,
+                CategorySuggestion {
+                    rule_id: row.get(0)?,
+                    category_id: row.get(1)?,
+                    category_name: row.get(2)?,
+                    merchant_keyword: row.get(3)?,
+                    tags: serde_json::from_str(&tags).unwrap_or_default(),
+                    match_type: MatchType::Exact,
+                },
+            ))
+        })
+        .map_err(|e| format!("Failed to query merchant rules: {:?}", e))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read merchant rule: {:?}", e))
+}
+
+/// Подбирает категорию по кэшу правил: сначала точное совпадение, затем
+/// самое длинное правило, ключ которого входит в имя целыми словами
+pub fn suggest_category(
+    store: &NotificationStore,
+    merchant_name: &str,
+) -> Result<Option<CategorySuggestion>, String> {
+    let normalized = normalize_merchant_name(merchant_name);
+    if normalized.is_empty() {
+        return Ok(None);
+    }
+    let padded = format!(" {} ", normalized);
+
+    let mut best: Option<(usize, CategorySuggestion)> = None;
+    for (keyword, mut suggestion) in cached_rules(store)? {
+        if keyword.is_empty() {
+            continue;
+        }
+        if keyword == normalized {
+            return Ok(Some(suggestion));
+        }
+        let longer = match &best {
+            Some((len, _)) => keyword.len() > *len,
+            None => true,
+        };
+        if longer && padded.contains(&format!(" {} ", keyword)) {
+            suggestion.match_type = MatchType::Keyword;
+            best = Some((keyword.len(), suggestion));
+        }
+    }
+
+    Ok(best.map(|(_, suggestion)| suggestion))
+}
+
+#[tauri::command]
+pub async fn cache_merchant_rules(
+    store: tauri::State<'_, NotificationStore>,
+    rules: Vec<MerchantCategoryRule>,
+    replace: bool,
+) -> Result<usize, String> {
+    let store = store.inner().clone();
+    worker::run(move || cache_rules(&store, &rules, replace)).await
+}
+
+#[tauri::command]
+pub async fn categorize_merchant(
+    store: tauri::State<'_, NotificationStore>,
+    merchant_name: String,
+) -> Result<Option<CategorySuggestion>, String> {
+    let store = store.inner().clone();
+    worker::run(move || suggest_category(&store, &merchant_name)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn rule(id: &str, keyword: &str, category: &str) -> MerchantCategoryRule {
+        MerchantCategoryRule {
+            id: id.to_string(),
+            category_id: category.to_string(),
+            merchant_keyword: keyword.to_string(),
+            updated_at: None,
+            transaction_category: None,
+            tags: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normalizes_like_frontend() {
+        assert_eq!(normalize_merchant_name("  ПЯТЁРОЧКА-1234  "), "пятёрочка");
+        assert_eq!(normalize_merchant_name("Kofeynya  na\tOranzherey!"), "kofeynya na oranzherey");
+        assert_eq!(normalize_merchant_name("YANDEX*GO 5815"), "yandexgo");
+        assert_eq!(normalize_merchant_name("123"), "");
+    }
+
+    #[test]
+    fn suggests_exact_then_longest_keyword() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let mut coffee = rule("r1", "Кофейня", "food");
+        coffee.tags = vec!["coffee".to_string()];
+        let mut groceries = rule("r3", "Пятерочка", "groceries");
+        groceries.transaction_category = Some(RuleCategory {
+            id: "groceries".to_string(),
+            name: "Продукты".to_string(),
+        });
+        cache_rules(
+            &store,
+            &[coffee, rule("r2", "Кофейня на Оранжерейной", "favourite"), groceries],
+            true,
+        )
+        .unwrap();
+
+        let exact = suggest_category(&store, "ПЯТЕРОЧКА").unwrap().unwrap();
+        assert_eq!((exact.rule_id.as_str(), exact.match_type), ("r3", MatchType::Exact));
+        assert_eq!(exact.tags, ["Продукты"]);
+
+        let keyword = suggest_category(&store, "Кофейня на Оранжерейной, Москва").unwrap().unwrap();
+        assert_eq!((keyword.rule_id.as_str(), keyword.match_type), ("r2", MatchType::Keyword));
+
+        let tagged = suggest_category(&store, "Кофейня у дома").unwrap().unwrap();
+        assert_eq!(tagged.rule_id, "r1");
+        assert_eq!(tagged.tags, ["coffee"]);
+        assert!(keyword.tags.is_empty());
+
+        assert!(suggest_category(&store, "Кофейнятор").unwrap().is_none());
+
+        cache_rules(&store, &[rule("r4", "Магнит", "groceries")], true).unwrap();
+        assert!(suggest_category(&store, "Пятерочка").unwrap().is_none());
+    }
+}
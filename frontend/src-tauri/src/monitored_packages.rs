@@ -0,0 +1,262 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+use crate::platform::Platform;
+use crate::worker;
+
+/// Список, который читает слушатель. Пока файла нет, он работает по
+/// встроенному `DEFAULT_PACKAGES`
+pub const MONITORED_PACKAGES_FILE: &str = "monitored_packages.json";
+/// Приложения, чьи уведомления слушатель видел, но не сохранял. Пишет только Kotlin
+pub const SEEN_PACKAGES_FILE: &str = "seen_packages.json";
+
+/// Должен совпадать с `DEFAULT_PACKAGES` в PaymentNotificationListenerService.kt.
+/// Собственный пакет приложения слушатель добавляет сам
+pub const DEFAULT_PACKAGES: &[&str] = &[
+    "com.android.shell",
+    "ru.raiffeisennews",
+    "ru.sberbankmobile",
+    "com.idamob.tinkoff.android",
+    "ru.vtb24.mobilebanking",
+    "ru.alfabank.mobile.android",
+    "ru.sovcombank.halvacard",
+    "ru.pochtabank.pochtaapp",
+    "ru.rshb.mobilebank",
+    "ru.otpbank.online",
+    "ru.psb.mobile",
+    "ru.unicreditbank.mobile",
+    "ru.mtsbank.mobile",
+    "ru.bspb.mobile",
+    "ru.akbmetallbank.mobile",
+    "ru.yoo.money",
+    "com.yandex.bank",
+    "ru.nspk.sbp.pay",
+    "ru.ozon.fintech.finance",
+    "ru.ozon.app.android",
+    "com.wildberries.ru",
+    "ru.market.android",
+    "com.avito.android",
+    "ru.aliexpress.buyer",
+    "ru.lamoda",
+    "ru.sberbank.bankingbusiness",
+    "com.idamob.tinkoff.business",
+    "ru.vtb.mobile.business",
+    "ru.alfabank.mobile.android.biz",
+    "ru.sovcombank.business",
+    "ru.modulebank",
+    "ru.tochka.app",
+    "ru.openbusiness.app",
+    "ru.rosbank.business",
+    "ru.uralsib.business",
+    "ru.psb.business",
+    "ru.mtsbank.business",
+    "ru.bspb.business",
+    "ru.tcsbank.business",
+    "kz.kaspi.mobile",
+    "kz.halyk.onlinebank",
+    "ge.bog.mobilebank",
+    "ge.tbcbank.mobilebank",
+    "by.priorbank.mobile",
+    "uz.uzum.bank",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct MonitoredPackagesFile {
+    packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SeenPackageEntry {
+    #[serde(default)]
+    app_name: Option<String>,
+    last_seen: i64,
+    #[serde(default)]
+    count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeenPackage {
+    pub package_name: String,
+    pub app_name: Option<String>,
+    pub last_seen: i64,
+    pub count: u64,
+}
+
+fn package_name_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| {
+        Regex::new(r"^[A-Za-z][A-Za-z0-9_]*(?:\.[A-Za-z][A-Za-z0-9_]*)+$").expect("invalid package name regex")
+    })
+}
+
+// Чтение-изменение-запись из двух команд сразу не должно терять изменения
+fn write_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn validate(package_name: &str) -> Result<String, String> {
+    let package_name = package_name.trim();
+    if !package_name_regex().is_match(package_name) {
+        return Err(format!("Invalid package name: {}", package_name));
+    }
+    Ok(package_name.to_string())
+}
+
+/// Отслеживаемые пакеты по алфавиту
+pub fn list(data_dir: &Path) -> Result<Vec<String>, String> {
+    let path = data_dir.join(MONITORED_PACKAGES_FILE);
+    let mut packages = match fs::read_to_string(&path) {
+        Ok(content) => {
+            serde_json::from_str::<MonitoredPackagesFile>(&content)
+                .map_err(|e| format!("Failed to parse {}: {}", MONITORED_PACKAGES_FILE, e))?
+                .packages
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => DEFAULT_PACKAGES.iter().map(|p| p.to_string()).collect(),
+        Err(e) => return Err(format!("Failed to read {}: {:?}", MONITORED_PACKAGES_FILE, e)),
+    };
+    packages.sort();
+    packages.dedup();
+    Ok(packages)
+}
+
+/// Слушатель перечитывает файл по времени изменения, поэтому он заменяется
+/// целиком через временный файл
+fn save(data_dir: &Path, packages: &[String]) -> Result<(), String> {
+    let path = data_dir.join(MONITORED_PACKAGES_FILE);
+    let temp_path = data_dir.join(format!("{}.tmp", MONITORED_PACKAGES_FILE));
+    let content = serde_json::to_string_pretty(&MonitoredPackagesFile {
+        packages: packages.to_vec(),
+    })
+    .map_err(|e| format!("Failed to serialize monitored packages: {:?}", e))?;
+
+    fs::write(&temp_path, content).map_err(|e| format!("Failed to write monitored packages: {:?}", e))?;
+    fs::rename(&temp_path, &path).map_err(|e| format!("Failed to replace monitored packages: {:?}", e))
+}
+
+fn update(data_dir: &Path, change: impl FnOnce(&mut Vec<String>)) -> Result<Vec<String>, String> {
+    let _guard = write_lock().lock().unwrap_or_else(|e| e.into_inner());
+    let mut packages = list(data_dir)?;
+    change(&mut packages);
+    packages.sort();
+    packages.dedup();
+    save(data_dir, &packages)?;
+    Ok(packages)
+}
+
+pub fn add(data_dir: &Path, package_name: &str) -> Result<Vec<String>, String> {
+    let package_name = validate(package_name)?;
+    update(data_dir, |packages| packages.push(package_name))
+}
+
+pub fn remove(data_dir: &Path, package_name: &str) -> Result<Vec<String>, String> {
+    let package_name = package_name.trim().to_string();
+    update(data_dir, |packages| packages.retain(|p| *p != package_name))
+}
+
+/// Замеченные слушателем приложения, которых нет в списке, свежие первыми.
+/// Повреждённый файл не мешает работе: слушатель перепишет его
+pub fn seen(data_dir: &Path) -> Result<Vec<SeenPackage>, String> {
+    let monitored = list(data_dir)?;
+    let content = match fs::read_to_string(data_dir.join(SEEN_PACKAGES_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {:?}", SEEN_PACKAGES_FILE, e)),
+    };
+    let entries: HashMap<String, SeenPackageEntry> = match serde_json::from_str(&content) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Ignoring damaged {}: {}", SEEN_PACKAGES_FILE, e);
+            return Ok(Vec::new());
+        }
+    };
+
+    let mut seen: Vec<SeenPackage> = entries
+        .into_iter()
+        .filter(|(package_name, _)| !monitored.contains(package_name))
+        .map(|(package_name, entry)| SeenPackage {
+            package_name,
+            app_name: entry.app_name,
+            last_seen: entry.last_seen,
+            count: entry.count,
+        })
+        .collect();
+    seen.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.package_name.cmp(&b.package_name)));
+    Ok(seen)
+}
+
+#[tauri::command]
+pub async fn get_monitored_packages(platform: tauri::State<'_, Platform>) -> Result<Vec<String>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || list(&platform.files_dir()?)).await
+}
+
+#[tauri::command]
+pub async fn add_monitored_package(
+    platform: tauri::State<'_, Platform>,
+    package_name: String,
+) -> Result<Vec<String>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || add(&platform.files_dir()?, &package_name)).await
+}
+
+#[tauri::command]
+pub async fn remove_monitored_package(
+    platform: tauri::State<'_, Platform>,
+    package_name: String,
+) -> Result<Vec<String>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || remove(&platform.files_dir()?, &package_name)).await
+}
+
+#[tauri::command]
+pub async fn get_seen_packages(platform: tauri::State<'_, Platform>) -> Result<Vec<SeenPackage>, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || seen(&platform.files_dir()?)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hpio-packages-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn edits_allowlist_and_hides_monitored_from_seen() {
+        let dir = temp_dir("allowlist");
+        assert_eq!(list(&dir).unwrap().len(), DEFAULT_PACKAGES.len());
+
+        let packages = add(&dir, " kz.kaspi.mobile ").unwrap();
+        assert!(packages.contains(&"kz.kaspi.mobile".to_string()));
+        let packages = remove(&dir, "com.android.shell").unwrap();
+        assert!(!packages.contains(&"com.android.shell".to_string()));
+        assert_eq!(list(&dir).unwrap(), packages);
+        assert_eq!(add(&dir, "not a package").unwrap_err(), "Invalid package name: not a package");
+
+        fs::write(
+            dir.join(SEEN_PACKAGES_FILE),
+            r#"{
+                "kz.kaspi.mobile": {"appName": "Kaspi.kz", "lastSeen": 300, "count": 4},
+                "ge.tbc.mobile": {"appName": "TBC", "lastSeen": 200, "count": 1},
+                "uz.payme": {"lastSeen": 500}
+            }"#,
+        )
+        .unwrap();
+        let seen: Vec<String> = seen(&dir).unwrap().into_iter().map(|p| p.package_name).collect();
+        assert_eq!(seen, ["uz.payme", "ge.tbc.mobile"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
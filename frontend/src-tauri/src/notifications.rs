@@ -1,772 +1,538 @@
 use serde::{Deserialize, Serialize};
 
+use crate::parser::{self, ParsedNotification};
+use crate::platform::{Platform, PlatformBridge};
+use crate::redaction;
+use crate::store::{NotificationStore, StoredNotification};
+use crate::worker;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PermissionStatus {
     pub granted: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingNotification {
+    #[serde(default)]
+    pub id: String,
     pub package_name: String,
     pub title: String,
     pub text: String,
     pub timestamp: i64,
     pub notification_type: Option<String>,
+    #[serde(default)]
+    pub notification_key: Option<String>,
+    #[serde(flatten)]
+    pub extras: NotificationExtras,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NotificationServiceStatus {
-    pub last_heartbeat: i64,
+/// Поля `StatusBarNotification` и extras Android помимо заголовка и текста.
+/// Многие банки кладут сумму в развёрнутый текст или строки InboxStyle,
+/// а продавца — в заголовок беседы
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationExtras {
+    /// `StatusBarNotification.postTime`; `timestamp` — время, когда запись сделал слушатель
+    pub post_time: Option<i64>,
+    pub channel_id: Option<String>,
+    pub group_key: Option<String>,
+    /// `EXTRA_CONVERSATION_TITLE` (MessagingStyle)
+    pub conversation_title: Option<String>,
+    /// `EXTRA_BIG_TEXT` (BigTextStyle)
+    pub big_text: Option<String>,
+    /// `EXTRA_SUB_TEXT`
+    pub sub_text: Option<String>,
+    /// `EXTRA_TEXT_LINES` (InboxStyle)
+    pub text_lines: Vec<String>,
+    /// `Notification.category`: "msg", "status", "promo"...
+    pub category: Option<String>,
 }
 
-#[tauri::command]
-pub fn check_notification_permission() -> Result<PermissionStatus, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        // Получаем JavaVM через ndk_context
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        // Вызываем NotificationPermissionHelper.isNotificationListenerEnabled
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let is_enabled = env.call_static_method(
-            helper_class,
-            "isNotificationListenerEnabled",
-            "(Landroid/content/Context;)Z",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let granted = is_enabled.z()
-            .map_err(|e| format!("Failed to get boolean result: {:?}", e))?;
-
-        Ok(PermissionStatus { granted })
-    }
+impl PendingNotification {
+    /// Стабильный id: хеш нормализованного содержимого и ключа уведомления
+    /// Android. Пробелы и форма записи Unicode на него не влияют
+    pub fn compute_id(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"v1");
+        for field in [
+            self.package_name.as_str(),
+            self.title.as_str(),
+            self.text.as_str(),
+            &self.timestamp.to_string(),
+            self.notification_key.as_deref().unwrap_or(""),
+        ] {
+            hasher.update([0u8]);
+            hasher.update(normalize_for_id(field).as_bytes());
+        }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        // На других платформах всегда возвращаем false
-        Ok(PermissionStatus { granted: false })
+        hasher.finalize()[..16]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
     }
-}
-
-#[tauri::command]
-pub fn open_notification_settings() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        // Получаем JavaVM через ndk_context
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        // Вызываем NotificationPermissionHelper.openNotificationListenerSettings
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        env.call_static_method(
-            helper_class,
-            "openNotificationListenerSettings",
-            "(Landroid/content/Context;)V",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
 
-        Ok(())
+    /// Ключ старого формата `timestamp_packageName_text`, который строит фронтенд
+    pub fn legacy_key(&self) -> String {
+        format!("{}_{}_{}", self.timestamp, self.package_name, self.text)
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Err("This feature is only available on Android".to_string())
+    /// Подходит стабильный id, а на переходный период и ключ старого формата
+    pub fn matches_key(&self, key: &str) -> bool {
+        key == self.id || key == self.legacy_key()
     }
-}
 
-#[tauri::command]
-pub fn check_app_notification_permission() -> Result<PermissionStatus, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let is_granted = env.call_static_method(
-            helper_class,
-            "checkAppNotificationPermission",
-            "(Landroid/content/Context;)Z",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let granted = is_granted.z()
-            .map_err(|e| format!("Failed to get boolean result: {:?}", e))?;
-
-        Ok(PermissionStatus { granted })
+    /// Текст и все дополнительные тексты без пустых и повторяющихся
+    pub fn texts(&self) -> Vec<&str> {
+        let extras = &self.extras;
+        let mut texts: Vec<&str> = Vec::new();
+        let candidates = std::iter::once(self.text.as_str())
+            .chain(extras.big_text.as_deref())
+            .chain(extras.text_lines.iter().map(String::as_str))
+            .chain(extras.sub_text.as_deref());
+        for text in candidates.map(str::trim) {
+            if !text.is_empty() && !texts.contains(&text) {
+                texts.push(text);
+            }
+        }
+        texts
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(PermissionStatus { granted: false })
+    /// Заголовок уведомления и заголовок беседы, если он отличается
+    pub fn titles(&self) -> Vec<&str> {
+        let mut titles = vec![self.title.as_str()];
+        if let Some(conversation) = self.extras.conversation_title.as_deref().map(str::trim) {
+            if !conversation.is_empty() && conversation != self.title.trim() {
+                titles.push(conversation);
+            }
+        }
+        titles
     }
-}
 
-#[tauri::command]
-pub fn request_app_notification_permission() -> Result<PermissionStatus, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let already_granted = env.call_static_method(
-            helper_class,
-            "requestAppNotificationPermission",
-            "(Landroid/app/Activity;)Z",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let granted = already_granted.z()
-            .map_err(|e| format!("Failed to get boolean result: {:?}", e))?;
-
-        Ok(PermissionStatus { granted })
+    /// Id операции из сгруппированного уведомления
+    pub fn transaction_id(&self, index: usize) -> String {
+        format!("{}:{}", self.id, index)
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(PermissionStatus { granted: false })
+    pub fn with_computed_id(mut self) -> Self {
+        if self.id.is_empty() {
+            self.id = self.compute_id();
+        }
+        self
     }
 }
 
-#[tauri::command]
-pub fn open_app_notification_settings() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        env.call_static_method(
-            helper_class,
-            "openAppNotificationSettings",
-            "(Landroid/content/Context;)V",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
+fn normalize_for_id(value: &str) -> String {
+    use unicode_normalization::UnicodeNormalization;
 
-        Ok(())
-    }
+    value
+        .nfkc()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Err("This feature is only available on Android".to_string())
-    }
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotificationServiceStatus {
+    pub last_heartbeat: i64,
 }
 
-#[tauri::command]
-pub fn simulate_app_payment_notification(title: String, body: String) -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::{JObject, JString, JValue};
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let title_jstring: JString = env.new_string(title)
-            .map_err(|e| format!("Failed to create title string: {:?}", e))?;
-        let body_jstring: JString = env.new_string(body)
-            .map_err(|e| format!("Failed to create body string: {:?}", e))?;
-        let title_object = JObject::from(title_jstring);
-        let body_object = JObject::from(body_jstring);
-
-        env.call_static_method(
-            helper_class,
-            "simulatePaymentNotification",
-            "(Landroid/content/Context;Ljava/lang/String;Ljava/lang/String;)V",
-            &[
-                JValue::Object(&context),
-                JValue::Object(&title_object),
-                JValue::Object(&body_object),
-            ],
-        )
-        .map_err(|e| format!("Failed to call simulatePaymentNotification: {:?}", e))?;
+pub(crate) fn listener_permission(platform: &dyn PlatformBridge) -> Result<PermissionStatus, String> {
+    Ok(PermissionStatus {
+        granted: platform.notification_listener_enabled()?,
+    })
+}
 
-        Ok(())
-    }
+#[tauri::command]
+pub async fn check_notification_permission(
+    platform: tauri::State<'_, Platform>,
+) -> Result<PermissionStatus, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || listener_permission(platform.as_ref())).await
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Err("Payment notification simulation is only available on Android".to_string())
-    }
+pub(crate) fn open_listener_settings(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.open_notification_listener_settings()
 }
 
 #[tauri::command]
-pub fn get_pending_notifications() -> Result<Vec<PendingNotification>, String> {
-    #[cfg(target_os = "android")]
-    {
-        use std::fs;
-        use std::path::PathBuf;
-
-        let ctx = ndk_context::android_context();
-        let files_dir = unsafe {
-            let context = jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject);
-            let vm_ptr = ctx.vm() as *mut std::os::raw::c_void;
-            let vm = jni::JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM)
-                .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-            let mut env = vm.attach_current_thread()
-                .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-            let files_dir_obj = env.call_method(
-                &context,
-                "getFilesDir",
-                "()Ljava/io/File;",
-                &[],
-            )
-            .map_err(|e| format!("Failed to get files dir: {:?}", e))?;
-
-            let file_obj = files_dir_obj.l()
-                .map_err(|e| format!("Failed to get file object: {:?}", e))?;
-
-            let path_jstring = env.call_method(
-                &file_obj,
-                "getAbsolutePath",
-                "()Ljava/lang/String;",
-                &[],
-            )
-            .map_err(|e| format!("Failed to get absolute path: {:?}", e))?;
-
-            let path_obj = path_jstring.l()
-                .map_err(|e| format!("Failed to get path object: {:?}", e))?;
-            let path_jstring = jni::objects::JString::from(path_obj);
-
-            let path_str: String = env.get_string(&path_jstring)
-                .map_err(|e| format!("Failed to get string: {:?}", e))?
-                .into();
-
-            path_str
-        };
-
-        let mut file_path = PathBuf::from(files_dir);
-        file_path.push("pending_notifications.json");
-
-        if !file_path.exists() {
-            return Ok(Vec::new());
-        }
+pub async fn open_notification_settings(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || open_listener_settings(platform.as_ref())).await
+}
 
-        let content = fs::read_to_string(&file_path)
-            .map_err(|e| format!("Failed to read notifications file: {:?}", e))?;
+pub(crate) fn app_permission(platform: &dyn PlatformBridge) -> Result<PermissionStatus, String> {
+    Ok(PermissionStatus {
+        granted: platform.app_notification_permission()?,
+    })
+}
 
-        if content.is_empty() {
-            return Ok(Vec::new());
-        }
+#[tauri::command]
+pub async fn check_app_notification_permission(
+    platform: tauri::State<'_, Platform>,
+) -> Result<PermissionStatus, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || app_permission(platform.as_ref())).await
+}
 
-        let json_array: serde_json::Value = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse JSON: {:?}", e))?;
-
-        let mut notifications = Vec::new();
-        if let Some(array) = json_array.as_array() {
-            for item in array {
-                let notification_type = item
-                    .get("notificationType")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string());
-
-                if let Some(ref notif_type) = notification_type {
-                    if notif_type != "PAYMENT" {
-                        continue;
-                    }
-                }
-
-                if let (Some(package_name), Some(title), Some(text), Some(timestamp)) = (
-                    item.get("packageName").and_then(|v| v.as_str()),
-                    item.get("title").and_then(|v| v.as_str()),
-                    item.get("text").and_then(|v| v.as_str()),
-                    item.get("timestamp").and_then(|v| v.as_i64()),
-                ) {
-                    notifications.push(PendingNotification {
-                        package_name: package_name.to_string(),
-                        title: title.to_string(),
-                        text: text.to_string(),
-                        timestamp,
-                        notification_type,
-                    });
-                }
-            }
-        }
+pub(crate) fn request_app_permission(platform: &dyn PlatformBridge) -> Result<PermissionStatus, String> {
+    Ok(PermissionStatus {
+        granted: platform.request_app_notification_permission()?,
+    })
+}
 
-        Ok(notifications)
-    }
+#[tauri::command]
+pub async fn request_app_notification_permission(
+    platform: tauri::State<'_, Platform>,
+) -> Result<PermissionStatus, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || request_app_permission(platform.as_ref())).await
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(Vec::new())
-    }
+pub(crate) fn open_app_settings(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.open_app_notification_settings()
 }
 
 #[tauri::command]
-pub fn clear_pending_notifications(processed_keys: Vec<String>) -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use std::fs;
-        use std::path::PathBuf;
-
-        let ctx = ndk_context::android_context();
-        let files_dir = unsafe {
-            let context = jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject);
-            let vm_ptr = ctx.vm() as *mut std::os::raw::c_void;
-            let vm = jni::JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM)
-                .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-            let mut env = vm.attach_current_thread()
-                .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-            let files_dir_obj = env.call_method(
-                &context,
-                "getFilesDir",
-                "()Ljava/io/File;",
-                &[],
-            )
-            .map_err(|e| format!("Failed to get files dir: {:?}", e))?;
-
-            let file_obj = files_dir_obj.l()
-                .map_err(|e| format!("Failed to get file object: {:?}", e))?;
-
-            let path_jstring = env.call_method(
-                &file_obj,
-                "getAbsolutePath",
-                "()Ljava/lang/String;",
-                &[],
-            )
-            .map_err(|e| format!("Failed to get absolute path: {:?}", e))?;
-
-            let path_obj = path_jstring.l()
-                .map_err(|e| format!("Failed to get path object: {:?}", e))?;
-            let path_jstring = jni::objects::JString::from(path_obj);
-
-            let path_str: String = env.get_string(&path_jstring)
-                .map_err(|e| format!("Failed to get string: {:?}", e))?
-                .into();
-
-            path_str
-        };
-
-        let mut file_path = PathBuf::from(files_dir);
-        file_path.push("pending_notifications.json");
-
-        if !file_path.exists() {
-            return Ok(());
-        }
-
-        // If no keys provided, do nothing (safety check)
-        if processed_keys.is_empty() {
-            return Ok(());
-        }
+pub async fn open_app_notification_settings(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || open_app_settings(platform.as_ref())).await
+}
 
-        // Read current file content
-        let content = fs::read_to_string(&file_path)
-            .map_err(|e| format!("Failed to read notifications file: {:?}", e))?;
+pub(crate) fn simulate_notification(platform: &dyn PlatformBridge, title: &str, body: &str) -> Result<(), String> {
+    platform.simulate_payment_notification(title, body)
+}
 
-        if content.is_empty() {
-            return Ok(());
-        }
+#[tauri::command]
+pub async fn simulate_app_payment_notification(
+    platform: tauri::State<'_, Platform>,
+    title: String,
+    body: String,
+) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || simulate_notification(platform.as_ref(), &title, &body)).await
+}
 
-        let json_array: serde_json::Value = serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse JSON: {:?}", e))?;
+/// В очередь попадает всё, кроме кодов подтверждения: расход это или нет,
+/// решает `classifier::classify`, тип от слушателя — только подсказка
+pub(crate) fn is_import_candidate(notification: &PendingNotification) -> bool {
+    notification.notification_type.as_deref() != Some(redaction::SECURITY_CODE_TYPE)
+}
 
-        let mut remaining_notifications = Vec::new();
-        if let Some(array) = json_array.as_array() {
-            for item in array {
-                // Construct the key for this notification to match frontend logic
-                // Key format: timestamp_packageName_text
-                let package_name = item.get("packageName").and_then(|v| v.as_str()).unwrap_or("");
-                let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
-                let timestamp = item.get("timestamp").and_then(|v| v.as_i64()).unwrap_or(0);
+pub(crate) fn read_pending_notifications(
+    store: &NotificationStore,
+) -> Result<Vec<StoredNotification>, String> {
+    store.drain_spool()?;
+
+    let pending = store
+        .pending()?
+        .into_iter()
+        .filter(|stored| is_import_candidate(&stored.to_pending()))
+        .collect();
+    // Операции группы очищаются по своим id, не задевая соседние
+    parser::expand_grouped(store, pending)
+}
 
-                let key = format!("{}_{}_{}", timestamp, package_name, text);
+/// Ожидающие уведомления вместе с результатом разбора: что импортировать,
+/// решает парсер на Rust, а не webview
+#[tauri::command]
+pub async fn get_pending_notifications(
+    store: tauri::State<'_, NotificationStore>,
+) -> Result<Vec<ParsedNotification>, String> {
+    let store = store.inner().clone();
+    worker::run(move || parser::parse_pending(&store)).await
+}
 
-                // If this key is NOT in the processed list, keep it
-                if !processed_keys.contains(&key) {
-                    remaining_notifications.push(item.clone());
-                }
-            }
-        }
+fn resolve_pending(store: &NotificationStore, keys: &[String]) -> Result<Vec<i64>, String> {
+    store.drain_spool()?;
+
+    Ok(store
+        .pending()?
+        .into_iter()
+        .filter(|stored| {
+            let notification = stored.to_pending();
+            keys.iter().any(|key| notification.matches_key(key))
+        })
+        .map(|stored| stored.row_id)
+        .collect())
+}
 
-        // Write back remaining notifications
-        let new_content = serde_json::to_string(&remaining_notifications)
-            .map_err(|e| format!("Failed to serialize JSON: {:?}", e))?;
+/// Принимает стабильные id, а на переходный период и ключи старого формата
+#[tauri::command]
+pub async fn clear_pending_notifications(
+    store: tauri::State<'_, NotificationStore>,
+    processed_keys: Vec<String>,
+) -> Result<(), String> {
+    // If no keys provided, do nothing (safety check)
+    if processed_keys.is_empty() {
+        return Ok(());
+    }
 
-        fs::write(&file_path, new_content)
-            .map_err(|e| format!("Failed to update notifications file: {:?}", e))?;
+    let store = store.inner().clone();
+    worker::run(move || clear_pending(&store, &processed_keys)).await
+}
 
-        Ok(())
-    }
+pub(crate) fn clear_pending(store: &NotificationStore, keys: &[String]) -> Result<(), String> {
+    let processed_ids = resolve_pending(store, keys)?;
+    store.archive(&processed_ids)?;
+    Ok(())
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
+#[tauri::command]
+pub async fn acknowledge_pending_notifications(
+    store: tauri::State<'_, NotificationStore>,
+    ids: Vec<String>,
+) -> Result<(), String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let acknowledged_ids = resolve_pending(&store, &ids)?;
+        store.acknowledge(&acknowledged_ids)?;
         Ok(())
-    }
+    })
+    .await
 }
 
 #[tauri::command]
-pub fn get_notification_service_status() -> Result<NotificationServiceStatus, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let value = env.call_static_method(
-            helper_class,
-            "getNotificationListenerHeartbeat",
-            "(Landroid/content/Context;)J",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let last_heartbeat = value.j()
-            .map_err(|e| format!("Failed to get long result: {:?}", e))?;
-
-        Ok(NotificationServiceStatus { last_heartbeat })
-    }
+pub async fn link_notification_to_payment(
+    store: tauri::State<'_, NotificationStore>,
+    id: String,
+    payment_id: String,
+) -> Result<(), String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let row_id = store
+            .find_by_id(&id)?
+            .ok_or_else(|| format!("Notification not found: {}", id))?
+            .row_id;
+
+        store.link_payment(row_id, &payment_id)
+    })
+    .await
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(NotificationServiceStatus { last_heartbeat: 0 })
-    }
+pub(crate) fn service_status(platform: &dyn PlatformBridge) -> Result<NotificationServiceStatus, String> {
+    Ok(NotificationServiceStatus {
+        last_heartbeat: platform.listener_heartbeat()?,
+    })
 }
 
 #[tauri::command]
-pub fn ping_notification_listener_service() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        env.call_static_method(
-            helper_class,
-            "pingNotificationListenerService",
-            "(Landroid/content/Context;)V",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
+pub async fn get_notification_service_status(
+    platform: tauri::State<'_, Platform>,
+) -> Result<NotificationServiceStatus, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || service_status(platform.as_ref())).await
+}
 
-        Ok(())
-    }
+pub(crate) fn ping_service(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.ping_listener()
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(())
-    }
+#[tauri::command]
+pub async fn ping_notification_listener_service(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || ping_service(platform.as_ref())).await
+}
+
+pub(crate) fn battery_optimization_status(platform: &dyn PlatformBridge) -> Result<bool, String> {
+    platform.battery_optimization_disabled()
 }
 
 #[tauri::command]
-pub fn check_battery_optimization_disabled() -> Result<bool, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let is_disabled = env.call_static_method(
-            helper_class,
-            "isBatteryOptimizationDisabled",
-            "(Landroid/content/Context;)Z",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let result = is_disabled.z()
-            .map_err(|e| format!("Failed to get boolean result: {:?}", e))?;
-
-        Ok(result)
-    }
+pub async fn check_battery_optimization_disabled(platform: tauri::State<'_, Platform>) -> Result<bool, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || battery_optimization_status(platform.as_ref())).await
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(false)
-    }
+pub(crate) fn open_battery_settings(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.open_battery_optimization_settings()
 }
 
 #[tauri::command]
-pub fn open_battery_optimization_settings() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm.attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context = unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env.find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        env.call_static_method(
-            helper_class,
-            "openBatteryOptimizationSettings",
-            "(Landroid/content/Context;)V",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
+pub async fn open_battery_optimization_settings(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || open_battery_settings(platform.as_ref())).await
+}
 
-        Ok(())
-    }
+pub(crate) fn autostart_status(platform: &dyn PlatformBridge) -> Result<bool, String> {
+    platform.autostart_enabled()
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Err("This feature is only available on Android".to_string())
-    }
+#[tauri::command]
+pub async fn check_autostart_enabled(platform: tauri::State<'_, Platform>) -> Result<bool, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || autostart_status(platform.as_ref())).await
+}
+
+pub(crate) fn open_autostart(platform: &dyn PlatformBridge) -> Result<(), String> {
+    platform.open_autostart_settings()
 }
 
 #[tauri::command]
-pub fn check_autostart_enabled() -> Result<bool, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm
-            .attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context =
-            unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env
-            .find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let is_enabled = env
-            .call_static_method(
-                helper_class,
-                "isAutostartEnabled",
-                "(Landroid/content/Context;)Z",
-                &[JValue::Object(&context)],
-            )
-            .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let result = is_enabled
-            .z()
-            .map_err(|e| format!("Failed to get boolean result: {:?}", e))?;
-
-        Ok(result)
-    }
+pub async fn open_autostart_settings(platform: tauri::State<'_, Platform>) -> Result<(), String> {
+    let platform = platform.inner().clone();
+    worker::run(move || open_autostart(platform.as_ref())).await
+}
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(false)
-    }
+pub(crate) fn manufacturer(platform: &dyn PlatformBridge) -> Result<String, String> {
+    platform.device_manufacturer()
 }
 
 #[tauri::command]
-pub fn open_autostart_settings() -> Result<(), String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JValue;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm
-            .attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let context =
-            unsafe { jni::objects::JObject::from_raw(ctx.context() as *mut jni::sys::_jobject) };
-
-        let helper_class = env
-            .find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        env.call_static_method(
-            helper_class,
-            "openAutostartSettings",
-            "(Landroid/content/Context;)V",
-            &[JValue::Object(&context)],
-        )
-        .map_err(|e| format!("Failed to call method: {:?}", e))?;
+pub async fn get_device_manufacturer(platform: tauri::State<'_, Platform>) -> Result<String, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || manufacturer(platform.as_ref())).await
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::mock::{MockBridge, MockState};
+
+    #[test]
+    fn reports_listener_heartbeat_from_platform() {
+        let platform = MockBridge::new(MockState {
+            heartbeat: 1700000000000,
+            ..MockState::default()
+        });
+
+        assert_eq!(service_status(&platform).unwrap().last_heartbeat, 1700000000000);
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Err("This feature is only available on Android".to_string())
+    #[test]
+    fn checks_and_requests_notification_permissions() {
+        let platform = MockBridge::new(MockState {
+            listener_enabled: true,
+            app_permission: false,
+            ..MockState::default()
+        });
+
+        assert!(listener_permission(&platform).unwrap().granted);
+        assert!(!app_permission(&platform).unwrap().granted);
+        assert!(!request_app_permission(&platform).unwrap().granted);
+        assert_eq!(open_listener_settings(&platform), Ok(()));
+        assert_eq!(open_app_settings(&platform), Ok(()));
+        assert_eq!(simulate_notification(&platform, "Сбер", "Покупка 150 ₽"), Ok(()));
+        assert_eq!(
+            platform.state().calls,
+            [
+                "request_app_notification_permission",
+                "open_notification_listener_settings",
+                "open_app_notification_settings",
+                "simulate_payment_notification:Сбер:Покупка 150 ₽"
+            ]
+        );
+
+        // Сбой JNI доходит до UI ошибкой, а не «разрешения нет»
+        platform.state().failing = vec![
+            "notification_listener_enabled",
+            "request_app_notification_permission",
+            "open_app_notification_settings",
+        ];
+        assert_eq!(
+            listener_permission(&platform).unwrap_err(),
+            "notification_listener_enabled failed"
+        );
+        assert!(request_app_permission(&platform).is_err());
+        assert_eq!(
+            open_app_settings(&platform),
+            Err("open_app_notification_settings failed".to_string())
+        );
+        assert!(app_permission(&platform).is_ok());
+        assert_eq!(platform.state().calls.len(), 4);
     }
-}
 
-#[tauri::command]
-pub fn get_device_manufacturer() -> Result<String, String> {
-    #[cfg(target_os = "android")]
-    {
-        use jni::objects::JString;
-        use jni::{AttachGuard, JavaVM};
-        use std::os::raw::c_void;
-
-        let ctx = ndk_context::android_context();
-        let vm_ptr = ctx.vm() as *mut c_void;
-        let vm = unsafe { JavaVM::from_raw(vm_ptr as *mut jni::sys::JavaVM) }
-            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
-
-        let mut env: AttachGuard = vm
-            .attach_current_thread()
-            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
-
-        let helper_class = env
-            .find_class("com/hochuplachu/hpio/NotificationPermissionHelper")
-            .map_err(|e| format!("Failed to find helper class: {:?}", e))?;
-
-        let manufacturer_value = env
-            .call_static_method(
-                helper_class,
-                "getDeviceManufacturer",
-                "()Ljava/lang/String;",
-                &[],
-            )
-            .map_err(|e| format!("Failed to call method: {:?}", e))?;
-
-        let manufacturer_obj = manufacturer_value
-            .l()
-            .map_err(|e| format!("Failed to get object result: {:?}", e))?;
-
-        let manufacturer_jstring = JString::from(manufacturer_obj);
-        let manufacturer: String = env
-            .get_string(&manufacturer_jstring)
-            .map_err(|e| format!("Failed to read string: {:?}", e))?
-            .into();
-
-        Ok(manufacturer)
+    #[test]
+    fn reports_battery_and_autostart_settings() {
+        let platform = MockBridge::new(MockState {
+            battery_optimization_disabled: true,
+            manufacturer: "Xiaomi".to_string(),
+            failing: vec!["autostart_enabled", "open_autostart_settings"],
+            ..MockState::default()
+        });
+
+        assert_eq!(battery_optimization_status(&platform), Ok(true));
+        assert_eq!(manufacturer(&platform).unwrap(), "Xiaomi");
+        assert_eq!(
+            autostart_status(&platform),
+            Err("autostart_enabled failed".to_string())
+        );
+        assert_eq!(open_battery_settings(&platform), Ok(()));
+        assert_eq!(
+            open_autostart(&platform),
+            Err("open_autostart_settings failed".to_string())
+        );
+        assert_eq!(ping_service(&platform), Ok(()));
+        assert_eq!(platform.state().calls, ["open_battery_optimization_settings", "ping_listener"]);
+    }
+
+    #[test]
+    fn clears_grouped_transactions_one_by_one() {
+        let store = NotificationStore::open_in_memory(std::path::PathBuf::new()).unwrap();
+        let grouped = serde_json::json!([{
+            "schemaVersion": 2,
+            "packageName": "ru.ozon.app.android",
+            "title": "Ozon Банк",
+            "text": "3 операции",
+            "timestamp": 1700000000000i64,
+            "textLines": [
+                "Покупка в Пятерочка. 350.00 RUR",
+                "Потрачено за день 820.50 ₽",
+                "Покупка в Магнит. 120.50 RUR",
+                "Покупка в Пятерочка. 350.00 RUR",
+            ],
+        }]);
+        let parent = store.import_json(&grouped.to_string()).unwrap().remove(0);
+
+        let items = read_pending_notifications(&store).unwrap();
+        let ids: Vec<&str> = items.iter().map(|item| item.id.as_str()).collect();
+        assert_eq!(ids, [format!("{}:0", parent.id), format!("{}:2", parent.id), format!("{}:3", parent.id)]);
+        assert!(items.iter().all(|item| item.parent_id.as_deref() == Some(parent.id.as_str())));
+        assert!(items.iter().all(|item| item.timestamp == parent.timestamp));
+        assert_eq!(store.get(parent.row_id).unwrap().unwrap().status, crate::store::STATUS_GROUPED);
+
+        let payment = parser::parse_notification(&items[1].to_pending()).unwrap();
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Магнит", 120.5));
+
+        // Очистка одной операции и самого уведомления не задевает остальные
+        clear_pending(&store, &[items[0].id.clone(), parent.id.clone()]).unwrap();
+        let left: Vec<String> = read_pending_notifications(&store)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(left, [items[1].id.clone(), items[2].id.clone()]);
     }
 
-    #[cfg(not(target_os = "android"))]
-    {
-        Ok(String::new())
+    #[test]
+    fn queues_non_payment_types_but_not_security_codes() {
+        let store = NotificationStore::open_in_memory(std::path::PathBuf::new()).unwrap();
+        let entries = serde_json::json!([
+            {
+                "schemaVersion": 2,
+                "packageName": "ru.sberbankmobile",
+                "title": "СберБанк",
+                "text": "Возврат покупки 350 ₽ Пятерочка",
+                "timestamp": 1700000000000i64,
+                "notificationType": "refund",
+            },
+            {
+                "schemaVersion": 2,
+                "packageName": "ru.sberbankmobile",
+                "title": "СберБанк",
+                "text": "Код подтверждения: 482139. Никому не сообщайте",
+                "timestamp": 1700000001000i64,
+                "notificationType": "other",
+            },
+        ]);
+        store.import_json(&entries.to_string()).unwrap();
+
+        let parsed = parser::parse_pending(&store).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].notification.notification_type.as_deref(), Some("REFUND"));
+        assert_eq!(parsed[0].classification.kind, crate::classifier::TransactionKind::Refund);
+        assert!(!parsed[0].classification.auto_import);
     }
 }
@@ -0,0 +1,343 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::classifier::{self, Classification};
+use crate::notifications::{self, PendingNotification};
+use crate::parser_rules::{self, RuleSet};
+use crate::dedup;
+use crate::merchant_rules::{self, CategorySuggestion};
+use crate::store::{NotificationStore, StoredNotification};
+use crate::worker;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedPayment {
+    pub merchant_name: String,
+    pub amount: f64,
+    pub currency: String,
+    pub bank: String,
+    pub card_mask: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedNotification {
+    pub notification: PendingNotification,
+    pub payment: Option<ParsedPayment>,
+    pub classification: Classification,
+    #[serde(default)]
+    pub category: Option<CategorySuggestion>,
+}
+
+impl ParsedNotification {
+    pub fn new(notification: PendingNotification) -> Self {
+        let payment = parse_notification(&notification);
+        let classification = classifier::classify(&notification, payment.as_ref());
+        Self {
+            notification,
+            payment,
+            classification,
+            category: None,
+        }
+    }
+}
+
+fn card_mask_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| Regex::new(r"(?:\*|••|\.\.)\s?(\d{4})\b").expect("invalid card mask regex"))
+}
+
+/// Приводит символ валюты к коду ISO
+pub(crate) fn normalize_currency(currency: &str) -> String {
+    match currency.trim().to_uppercase().as_str() {
+        "₾" => "GEL".to_string(),
+        "$" => "USD".to_string(),
+        "€" => "EUR".to_string(),
+        "₽" | "RUR" | "РУБ" => "RUB".to_string(),
+        "₸" | "ТГ" => "KZT".to_string(),
+        // to_uppercase переводит грузинское "ლარი" в мтаврули
+        "ᲚᲐᲠᲘ" => "GEL".to_string(),
+        "BR" => "BYN".to_string(),
+        "СЎМ" | "СУМ" | "SO'M" | "SOʻM" | "SO‘M" | "SO’M" => "UZS".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Ищет маску карты ("*1234", "•• 1234") сначала в заголовке, затем в тексте
+pub fn extract_card_mask(title: &str, text: &str) -> Option<String> {
+    [title, text].iter().find_map(|source| {
+        card_mask_regex()
+            .captures(source)
+            .map(|caps| format!("*{}", &caps[1]))
+    })
+}
+
+/// Разбирает текст банка для пакета по действующим правилам (см.
+/// `parser_rules`). Встроенные правила повторяют `parseNotification` из
+/// фронтенда, чтобы обе стороны разбирали одинаково
+#[cfg(test)]
+pub fn parse_notification_text(package_name: &str, title: &str, text: &str) -> Option<ParsedPayment> {
+    parser_rules::current().parse(package_name, title, text)
+}
+
+/// Пробует основной текст, затем развёрнутый текст, строки InboxStyle и
+/// подтекст, каждый с заголовком уведомления и с заголовком беседы
+pub fn parse_notification(notification: &PendingNotification) -> Option<ParsedPayment> {
+    parse_notification_with(&parser_rules::current(), notification)
+}
+
+/// То же с заданным набором правил, например с проверяемым шаблоном
+pub fn parse_notification_with(rules: &RuleSet, notification: &PendingNotification) -> Option<ParsedPayment> {
+    let titles = notification.titles();
+    notification.texts().into_iter().find_map(|text| {
+        titles
+            .iter()
+            .find_map(|title| rules.parse(&notification.package_name, title, text))
+    })
+}
+
+/// Строки сгруппированного уведомления: InboxStyle или многострочный bigText
+fn grouped_lines(notification: &PendingNotification) -> Vec<&str> {
+    let extras = &notification.extras;
+    if extras.text_lines.len() >= 2 {
+        return extras.text_lines.iter().map(|line| line.trim()).collect();
+    }
+    extras
+        .big_text
+        .as_deref()
+        .map(|big_text| big_text.lines().map(str::trim).filter(|line| !line.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// Делит сгруппированное уведомление ("3 операции") на операции: каждая
+/// строка с платежом становится уведомлением с id `{id}:{номер строки}`.
+/// Пустой список, если платёж нашёлся меньше чем в двух строках
+pub fn split_grouped(notification: &PendingNotification) -> Vec<PendingNotification> {
+    let lines = grouped_lines(notification);
+    if lines.len() < 2 {
+        return Vec::new();
+    }
+
+    let rules = parser_rules::current();
+    let titles = notification.titles();
+    let items: Vec<PendingNotification> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            titles
+                .iter()
+                .any(|title| rules.parse(&notification.package_name, title, line).is_some())
+        })
+        .map(|(index, line)| {
+            let mut item = notification.clone();
+            item.id = notification.transaction_id(index);
+            item.text = line.to_string();
+            item.extras.big_text = None;
+            item.extras.text_lines = Vec::new();
+            item
+        })
+        .collect();
+
+    if items.len() < 2 {
+        Vec::new()
+    } else {
+        items
+    }
+}
+
+/// Заменяет сгруппированные уведомления их операциями (см. `NotificationStore::insert_group`)
+pub(crate) fn expand_grouped(
+    store: &NotificationStore,
+    pending: Vec<StoredNotification>,
+) -> Result<Vec<StoredNotification>, String> {
+    let mut expanded = Vec::with_capacity(pending.len());
+    for stored in pending {
+        let items = split_grouped(&stored.to_pending());
+        if items.is_empty() {
+            expanded.push(stored);
+        } else {
+            log::info!("Notification {} holds {} transactions", stored.id, items.len());
+            expanded.extend(store.insert_group(stored.row_id, &items)?);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Разбирает сохранённое уведомление, записывает результат и прогоняет
+/// дедупликацию и подбор категории. Сгруппированное уведомление сначала
+/// делится на операции. Повторы в результат не попадают
+pub(crate) fn process_stored(
+    store: &NotificationStore,
+    stored: &StoredNotification,
+) -> Result<Vec<ParsedNotification>, String> {
+    let mut processed = Vec::new();
+    for stored in expand_grouped(store, vec![stored.clone()])? {
+        let mut parsed = ParsedNotification::new(stored.to_pending());
+        store.record_parse_result(stored.row_id, parsed.payment.as_ref())?;
+
+        if let Some(payment) = &parsed.payment {
+            if dedup::apply(store, &stored, payment)? {
+                continue;
+            }
+            // Категория из локального кэша правил, работает без сети
+            parsed.category = merchant_rules::suggest_category(store, &payment.merchant_name)?;
+        }
+        processed.push(parsed);
+    }
+
+    Ok(processed)
+}
+
+/// Очередь на импорт: разбор, классификация и категория для каждого
+/// ожидающего уведомления. Повторы из неё выпадают
+pub(crate) fn parse_pending(store: &NotificationStore) -> Result<Vec<ParsedNotification>, String> {
+    let pending = notifications::read_pending_notifications(store)?;
+
+    let mut parsed = Vec::with_capacity(pending.len());
+    for stored in &pending {
+        parsed.extend(process_stored(store, stored)?);
+    }
+    Ok(parsed)
+}
+
+#[tauri::command]
+pub async fn parse_pending_notifications(
+    store: tauri::State<'_, NotificationStore>,
+) -> Result<Vec<ParsedNotification>, String> {
+    let store = store.inner().clone();
+    worker::run(move || parse_pending(&store)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(package_name: &str, title: &str, text: &str) -> Option<(String, f64, String)> {
+        parse_notification_text(package_name, title, text)
+            .map(|p| (p.merchant_name, p.amount, p.currency))
+    }
+
+    fn expected(merchant_name: &str, amount: f64, currency: &str) -> Option<(String, f64, String)> {
+        Some((merchant_name.to_string(), amount, currency.to_string()))
+    }
+
+    #[test]
+    fn validates_raiffeisen_titles() {
+        let text = "− 100.00 ₽ в Кафе.";
+        assert!(parse("ru.raiffeisennews", "Заплатили картой *1234", text).is_some());
+        assert!(parse("ru.raiffeisennews", "Заплатили со счета  *1974", text).is_some());
+        assert!(parse("ru.raiffeisennews", "ЗАПЛАТИЛИ КАРТОЙ *1234", text).is_some());
+        assert!(parse("ru.raiffeisennews", "Заплатили картой *12345", text).is_none());
+        assert!(parse("ru.raiffeisennews", "Оплатили картой *1234", text).is_none());
+    }
+
+    #[test]
+    fn parses_raiffeisen() {
+        assert_eq!(
+            parse("ru.raiffeisennews", "Заплатили картой *1234", "− 689.68 ₽ в Пятерочка. Теперь на карте 34 574.90 ₽"),
+            expected("Пятерочка", 689.68, "RUB")
+        );
+        assert_eq!(
+            parse("ru.raiffeisennews", "Заплатили со счета *1234", "- 340.00 ₽ в t2"),
+            expected("t2", 340.0, "RUB")
+        );
+        assert_eq!(
+            parse("ru.raiffeisennews", "", "− 1\u{00A0}000.50 ₽ в Магазин."),
+            expected("Магазин", 1000.5, "RUB")
+        );
+        assert_eq!(parse("ru.raiffeisennews", "Some title", "− 100.00 ₽ в Магазин."), None);
+        assert_eq!(parse("ru.raiffeisennews", "", "− 0.00 ₽ в Магазин."), None);
+        assert_eq!(parse("ru.raiffeisennews", "", "− 100 ₽ в Магазин."), None);
+    }
+
+    #[test]
+    fn parses_sberbank() {
+        assert_eq!(
+            parse("ru.sberbankmobile", "Покупка Купер", "150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165"),
+            expected("Купер", 150.0, "RUB")
+        );
+        assert_eq!(
+            parse("ru.sberbankmobile", "Покупка Магазин", "1 234,56 ₽ — Баланс: 5 000,00 ₽ MasterCard •• 1234"),
+            expected("Магазин", 1234.56, "RUB")
+        );
+        assert_eq!(parse("ru.sberbankmobile", "Перевод средств", "150 ₽ — Баланс: 196,01 ₽"), None);
+        assert_eq!(parse("ru.sberbankmobile", "Покупка Купер", "Недостаточно средств"), None);
+    }
+
+    #[test]
+    fn parses_yandex_bank() {
+        assert_eq!(
+            parse("com.yandex.bank", "Пятёрочка", "Покупка на 1 500.50 RUB, карта *1234. Доступно 10 000.00 RUB"),
+            expected("Пятёрочка", 1500.5, "RUB")
+        );
+        assert_eq!(parse("com.yandex.bank", "", "Покупка на 35.00 RUB, карта *7222."), None);
+        assert_eq!(parse("com.yandex.bank", "Ситикард", "Перевод средств 100 RUB"), None);
+    }
+
+    #[test]
+    fn parses_ozon() {
+        assert_eq!(
+            parse("ru.ozon.app.android", "Ozon Банк", "Покупка на 128 ₽. Ozon. Доступно 1 499 ₽"),
+            expected("Ozon", 128.0, "RUB")
+        );
+        assert_eq!(
+            parse("ru.ozon.app.android", "OzOn БаНк", "Покупка в Магазин продуктов. 2500 RUR. Баланс 1500 ₽"),
+            expected("Магазин продуктов", 2500.0, "RUB")
+        );
+        assert_eq!(parse("ru.ozon.app.android", "Ozon", "Покупка на 128 ₽. Ozon."), None);
+        assert_eq!(parse("ru.ozon.app.android", "Ozon Банк", "Покупка в магазине без суммы"), None);
+    }
+
+    #[test]
+    fn parses_tbank() {
+        assert_eq!(
+            parse("com.idamob.tinkoff.android", "Kofeynya na Oranzherey", "Покупка на 741 ₽, кэшбэк ₽ Р,карта *0725\nДоступно 1 446,98 ₽"),
+            expected("Kofeynya na Oranzherey", 741.0, "RUB")
+        );
+        assert_eq!(
+            parse("com.idamob.tinkoff.android", "  Магазин  ", "Покупка на 1 234,56 ₽, кэшбэк 12,34 ₽, карта *5678"),
+            expected("Магазин", 1234.56, "RUB")
+        );
+        assert_eq!(parse("com.idamob.tinkoff.android", "   ", "Покупка на 100 ₽, карта *1234"), None);
+        assert_eq!(parse("com.idamob.tinkoff.android", "Магазин", "Покупка на 0 ₽, карта *1234"), None);
+    }
+
+    #[test]
+    fn parses_bank_of_georgia() {
+        assert_eq!(
+            parse("ge.bog.mobilebank", "BOG", "Purchase: 50.00 GEL at Shop Name"),
+            expected("Shop Name", 50.0, "GEL")
+        );
+        assert_eq!(
+            parse("ge.bog.mobilebank", "BOG", "25.50 € - Store"),
+            expected("Store", 25.5, "EUR")
+        );
+        assert_eq!(
+            parse("ge.bog.mobilebank", "Coffee", "Charged 12.00 USD"),
+            expected("Coffee", 12.0, "USD")
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_packages() {
+        assert_eq!(parse("", "Some title", "− 100.00 ₽ в Магазин."), None);
+        assert_eq!(parse("com.example.unknown", "Покупка Купер", "150 ₽"), None);
+    }
+
+    #[test]
+    fn extracts_card_mask() {
+        let payment = parse_notification_text(
+            "ru.raiffeisennews",
+            "Заплатили картой *1974",
+            "− 100.00 ₽ в Кафе.",
+        )
+        .unwrap();
+        assert_eq!(payment.bank, "raiffeisen");
+        assert_eq!(payment.card_mask.as_deref(), Some("*1974"));
+
+        assert_eq!(
+            extract_card_mask("Покупка Купер", "150 ₽ — Баланс: 196,01 ₽ MasterCard •• 7165").as_deref(),
+            Some("*7165")
+        );
+        assert_eq!(extract_card_mask("Ситикард", "Покупка на 35.00 RUB"), None);
+    }
+}
@@ -0,0 +1,555 @@
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+
+use crate::parser::{self, ParsedPayment};
+use crate::platform::Platform;
+use crate::redaction;
+use crate::worker;
+
+pub const USER_RULES_TOML: &str = "parser_rules.toml";
+pub const USER_RULES_JSON: &str = "parser_rules.json";
+
+// Правила по умолчанию вшиты в бинарник: российские банки и региональные наборы
+const BUNDLED_PACKS: &[(&str, &str)] = &[
+    ("default", include_str!("../rules/default.toml")),
+    ("kz", include_str!("../rules/kz.toml")),
+    ("ge", include_str!("../rules/ge.toml")),
+    ("by", include_str!("../rules/by.toml")),
+    ("uz", include_str!("../rules/uz.toml")),
+];
+
+// Раскрывается в целую часть суммы с разделителями разрядов из number_format
+const AMOUNT_PLACEHOLDER: &str = "{amount}";
+
+/// Разделители разрядов и дробной части. Задаётся именем ("ru", "en", "space_comma")
+/// или явно: `number_format = { group = " ,", decimal = "." }`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NumberFormat {
+    Named(String),
+    Custom { group: String, decimal: String },
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat::Named("ru".to_string())
+    }
+}
+
+impl NumberFormat {
+    fn separators(&self) -> Result<(String, String), String> {
+        let (group, decimal) = match self {
+            NumberFormat::Named(name) => match name.as_str() {
+                // 1 234,56 / 1 234.56
+                "ru" => (" \u{00A0}\u{202F}", ",."),
+                // 1,234.56 / 1 234.56
+                "en" => (" \u{00A0}\u{202F},", "."),
+                // 1 234,56: точка в такой сумме не бывает дробной частью
+                "space_comma" => (" \u{00A0}\u{202F}", ","),
+                other => return Err(format!("Unknown number format: {}", other)),
+            },
+            NumberFormat::Custom { group, decimal } => (group.as_str(), decimal.as_str()),
+        };
+
+        if let Some(c) = group.chars().find(|c| decimal.contains(*c)) {
+            return Err(format!("Separator {:?} is both a group and a decimal separator", c));
+        }
+
+        Ok((group.to_string(), decimal.to_string()))
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_currency() -> String {
+    "RUB".to_string()
+}
+
+/// Декларативное описание разбора уведомлений одного банка
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParserRule {
+    pub id: String,
+    pub bank: String,
+    pub packages: Vec<String>,
+    #[serde(default)]
+    pub title_pattern: Option<String>,
+    /// Пустой заголовок допустим, непустой обязан совпасть с `title_pattern`
+    #[serde(default)]
+    pub allow_empty_title: bool,
+    pub text_pattern: String,
+    /// Фиксированное имя продавца, если в шаблонах нет группы `merchant`
+    #[serde(default)]
+    pub merchant: Option<String>,
+    /// Брать продавца из заголовка, если он не найден в шаблонах
+    #[serde(default)]
+    pub merchant_from_title: bool,
+    /// Валюта, если в шаблонах нет группы `currency`
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    #[serde(default)]
+    pub number_format: NumberFormat,
+    #[serde(default = "default_true")]
+    pub case_insensitive: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RuleFile {
+    #[serde(default)]
+    parser: Vec<ParserRule>,
+}
+
+struct CompiledRule {
+    rule: ParserRule,
+    title: Option<Regex>,
+    text: Regex,
+    group_separators: String,
+    decimal_separators: String,
+}
+
+fn amount_group(group_separators: &str) -> String {
+    let separators: String = group_separators
+        .chars()
+        .map(|c| regex::escape(&c.to_string()))
+        .collect();
+    format!(r"\d{{1,3}}(?:[{}]?\d{{3}})*", separators)
+}
+
+fn compile_pattern(pattern: &str, rule: &ParserRule, group_separators: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(&pattern.replace(AMOUNT_PLACEHOLDER, &amount_group(group_separators)))
+        .case_insensitive(rule.case_insensitive)
+        .build()
+}
+
+fn positive_amount(value: &str) -> Option<f64> {
+    let amount: f64 = value.parse().ok()?;
+    if amount.is_finite() && amount > 0.0 {
+        Some(amount)
+    } else {
+        None
+    }
+}
+
+impl CompiledRule {
+    fn compile(rule: ParserRule) -> Result<Self, String> {
+        let (group_separators, decimal_separators) = rule
+            .number_format
+            .separators()
+            .map_err(|e| format!("Invalid number_format in rule '{}': {}", rule.id, e))?;
+        let title = rule
+            .title_pattern
+            .as_deref()
+            .map(|pattern| compile_pattern(pattern, &rule, &group_separators))
+            .transpose()
+            .map_err(|e| format!("Invalid title_pattern in rule '{}': {}", rule.id, e))?;
+        let text = compile_pattern(&rule.text_pattern, &rule, &group_separators)
+            .map_err(|e| format!("Invalid text_pattern in rule '{}': {}", rule.id, e))?;
+
+        Ok(Self {
+            rule,
+            title,
+            text,
+            group_separators,
+            decimal_separators,
+        })
+    }
+
+    fn parse_amount(&self, value: &str) -> Option<f64> {
+        let normalized: String = value
+            .chars()
+            .filter(|c| !self.group_separators.contains(*c))
+            .map(|c| if self.decimal_separators.contains(c) { '.' } else { c })
+            .collect();
+        positive_amount(&normalized)
+    }
+
+    fn apply(&self, title: &str, text: &str) -> Option<ParsedPayment> {
+        let title_caps = match &self.title {
+            Some(_) if self.rule.allow_empty_title && title.is_empty() => None,
+            Some(pattern) => match pattern.captures(title) {
+                Some(caps) => Some(caps),
+                None => {
+                    log::debug!(
+                        "Rule '{}' rejected notification title: {}",
+                        self.rule.id,
+                        redaction::redact_text(title)
+                    );
+                    return None;
+                }
+            },
+            None => None,
+        };
+        let text_caps = self.text.captures(text)?;
+        let group = |name: &str| {
+            capture(&text_caps, name)
+                .or_else(|| title_caps.as_ref().and_then(|caps| capture(caps, name)))
+        };
+
+        let amount = self.parse_amount(group("amount")?)?;
+        let merchant_name = group("merchant")
+            .or(self.rule.merchant.as_deref())
+            .or_else(|| Some(title.trim()).filter(|t| self.rule.merchant_from_title && !t.is_empty()))?;
+        let currency = group("currency")
+            .map(parser::normalize_currency)
+            .unwrap_or_else(|| self.rule.currency.clone());
+        let card_mask = group("card")
+            .map(|digits| format!("*{}", digits))
+            .or_else(|| parser::extract_card_mask(title, text));
+
+        Some(ParsedPayment {
+            merchant_name: merchant_name.to_string(),
+            amount,
+            currency,
+            bank: self.rule.bank.clone(),
+            card_mask,
+        })
+    }
+}
+
+fn capture<'t>(caps: &Captures<'t>, name: &str) -> Option<&'t str> {
+    caps.name(name)
+        .map(|m| m.as_str().trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// Скомпилированный набор правил: встроенные плюс пользовательские
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    user_rules: usize,
+    source: Option<PathBuf>,
+}
+
+impl RuleSet {
+    pub fn compile(rules: Vec<ParserRule>) -> Result<Self, String> {
+        let rules = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .map(CompiledRule::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            rules,
+            user_rules: 0,
+            source: None,
+        })
+    }
+
+    pub fn bundled() -> Self {
+        Self::compile(bundled_rules()).expect("invalid bundled parser rules")
+    }
+
+    /// Встроенные правила с наложенными поверх пользовательскими из `data_dir`
+    pub fn load(data_dir: &Path) -> Result<Self, String> {
+        let Some((path, user)) = read_user_rules(data_dir)? else {
+            return Ok(Self::bundled());
+        };
+
+        let user_rules = user.len();
+        let mut set = Self::compile(merge_rules(bundled_rules(), user))?;
+        set.user_rules = user_rules;
+        set.source = Some(path);
+        Ok(set)
+    }
+
+    #[cfg(test)]
+    pub fn rules(&self) -> impl Iterator<Item = &ParserRule> {
+        self.rules.iter().map(|compiled| &compiled.rule)
+    }
+
+    /// Первое подходящее правило для пакета определяет результат
+    pub fn parse(&self, package_name: &str, title: &str, text: &str) -> Option<ParsedPayment> {
+        self.rules
+            .iter()
+            .filter(|compiled| compiled.rule.packages.iter().any(|p| p == package_name))
+            .find_map(|compiled| compiled.apply(title, text))
+    }
+
+    /// Без привязки к пакету: для текста, который пользователь вставил сам
+    pub fn parse_any(&self, title: &str, text: &str) -> Option<ParsedPayment> {
+        self.rules.iter().find_map(|compiled| compiled.apply(title, text))
+    }
+
+    /// Пакет первого правила, которое разбирает текст, как в `parse_any`
+    #[cfg(target_os = "linux")]
+    pub fn package_for(&self, title: &str, text: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|compiled| compiled.apply(title, text).is_some())
+            .and_then(|compiled| compiled.rule.packages.first())
+            .map(String::as_str)
+    }
+}
+
+fn bundled_rules() -> Vec<ParserRule> {
+    BUNDLED_PACKS
+        .iter()
+        .flat_map(|(pack, content)| {
+            parse_rule_file(content, false).unwrap_or_else(|e| panic!("invalid bundled parser rules '{}': {}", pack, e))
+        })
+        .collect()
+}
+
+fn parse_rule_file(content: &str, json: bool) -> Result<Vec<ParserRule>, String> {
+    let file: RuleFile = if json {
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse rules JSON: {}", e))?
+    } else {
+        toml::from_str(content).map_err(|e| format!("Failed to parse rules TOML: {}", e))?
+    };
+    Ok(file.parser)
+}
+
+fn read_user_rules(data_dir: &Path) -> Result<Option<(PathBuf, Vec<ParserRule>)>, String> {
+    for (name, json) in [(USER_RULES_TOML, false), (USER_RULES_JSON, true)] {
+        let path = data_dir.join(name);
+        if !path.exists() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {:?}", path.display(), e))?;
+        let rules = parse_rule_file(&content, json).map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Ok(Some((path, rules)));
+    }
+
+    Ok(None)
+}
+
+/// Пользовательское правило с тем же id заменяет встроенное на его месте,
+/// новые правила проверяются раньше встроенных
+fn merge_rules(bundled: Vec<ParserRule>, user: Vec<ParserRule>) -> Vec<ParserRule> {
+    let (overrides, added): (Vec<_>, Vec<_>) = user
+        .into_iter()
+        .partition(|rule| bundled.iter().any(|b| b.id == rule.id));
+
+    let bundled = bundled.into_iter().map(|rule| {
+        overrides
+            .iter()
+            .find(|o| o.id == rule.id)
+            .cloned()
+            .unwrap_or(rule)
+    });
+
+    added.into_iter().chain(bundled).collect()
+}
+
+fn active() -> &'static RwLock<Arc<RuleSet>> {
+    static ACTIVE: OnceLock<RwLock<Arc<RuleSet>>> = OnceLock::new();
+    ACTIVE.get_or_init(|| RwLock::new(Arc::new(RuleSet::bundled())))
+}
+
+/// Текущий набор правил, которым пользуется парсер
+pub fn current() -> Arc<RuleSet> {
+    active().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParserRulesSummary {
+    pub total: usize,
+    pub user_rules: usize,
+    pub source: Option<String>,
+}
+
+/// Перечитывает пользовательские правила. При ошибке остаётся прежний набор
+pub fn reload(data_dir: &Path) -> Result<ParserRulesSummary, String> {
+    let set = RuleSet::load(data_dir)?;
+    let summary = ParserRulesSummary {
+        total: set.rules.len(),
+        user_rules: set.user_rules,
+        source: set.source.as_ref().map(|p| p.display().to_string()),
+    };
+
+    *active().write().unwrap_or_else(|e| e.into_inner()) = Arc::new(set);
+    log::info!("Loaded {} parser rules ({} from user config)", summary.total, summary.user_rules);
+    Ok(summary)
+}
+
+/// Id всех правил: встроенных и пользовательских, в том числе отключённых
+pub fn rule_ids(data_dir: &Path) -> Result<Vec<String>, String> {
+    let user = read_user_rules(data_dir)?.map(|(_, rules)| rules).unwrap_or_default();
+    Ok(bundled_rules().into_iter().chain(user).map(|rule| rule.id).collect())
+}
+
+fn write_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+/// Дописывает правило в пользовательский файл, не трогая текущий набор. Пишет
+/// в parser_rules.json, только если он уже есть, а parser_rules.toml нет:
+/// иначе JSON перестал бы читаться
+pub fn save_user_rule(data_dir: &Path, rule: ParserRule) -> Result<(), String> {
+    let _guard = write_lock().lock().unwrap_or_else(|e| e.into_inner());
+    RuleSet::compile(vec![rule.clone()])?;
+    let existing = read_user_rules(data_dir)?;
+
+    match existing {
+        Some((path, mut rules)) if path.ends_with(USER_RULES_JSON) => {
+            rules.push(rule);
+            let content = serde_json::to_string_pretty(&RuleFile { parser: rules })
+                .map_err(|e| format!("Failed to serialize parser rules: {:?}", e))?;
+            fs::write(&path, content).map_err(|e| format!("Failed to write {}: {:?}", path.display(), e))?;
+        }
+        _ => {
+            let path = data_dir.join(USER_RULES_TOML);
+            let rule = toml::to_string(&RuleFile { parser: vec![rule] })
+                .map_err(|e| format!("Failed to serialize parser rule: {:?}", e))?;
+            // Дописываем, чтобы не потерять комментарии пользователя
+            let mut content = fs::read_to_string(&path).unwrap_or_default();
+            if !content.is_empty() && !content.ends_with("\n\n") {
+                content.push_str(if content.ends_with('\n') { "\n" } else { "\n\n" });
+            }
+            content.push_str(&rule);
+            fs::write(&path, content).map_err(|e| format!("Failed to write {}: {:?}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Дописывает правило в пользовательский файл и перечитывает набор
+pub fn add_user_rule(data_dir: &Path, rule: ParserRule) -> Result<ParserRulesSummary, String> {
+    save_user_rule(data_dir, rule)?;
+    reload(data_dir)
+}
+
+#[tauri::command]
+pub async fn reload_parser_rules(
+    platform: tauri::State<'_, Platform>,
+) -> Result<ParserRulesSummary, String> {
+    let platform = platform.inner().clone();
+    worker::run(move || reload(&platform.files_dir()?)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hpio-rules-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn bundled_rules_compile() {
+        let set = RuleSet::bundled();
+        assert!(set.rules().any(|rule| rule.id == "sberbank"));
+        assert_eq!(set.user_rules, 0);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct FixtureFile {
+        case: Vec<FixtureCase>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct FixtureCase {
+        package: String,
+        title: String,
+        text: String,
+        expect: Option<FixtureExpectation>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct FixtureExpectation {
+        bank: String,
+        merchant: String,
+        amount: f64,
+        currency: String,
+        card: Option<String>,
+    }
+
+    const FIXTURES: &[(&str, &str)] = &[
+        ("kz", include_str!("../rules/fixtures/kz.toml")),
+        ("ge", include_str!("../rules/fixtures/ge.toml")),
+        ("by", include_str!("../rules/fixtures/by.toml")),
+        ("uz", include_str!("../rules/fixtures/uz.toml")),
+    ];
+
+    #[test]
+    fn regional_packs_parse_fixture_corpora() {
+        let set = RuleSet::bundled();
+        for (pack, content) in FIXTURES {
+            let fixtures: FixtureFile = toml::from_str(content).unwrap();
+            for case in fixtures.case {
+                let actual = set.parse(&case.package, &case.title, &case.text);
+                let expected = case.expect.map(|e| ParsedPayment {
+                    merchant_name: e.merchant,
+                    amount: e.amount,
+                    currency: e.currency,
+                    bank: e.bank,
+                    card_mask: e.card,
+                });
+                assert_eq!(actual, expected, "{}: {}", pack, case.text);
+            }
+        }
+    }
+
+    #[test]
+    fn named_number_formats() {
+        let separators = |name: &str| NumberFormat::Named(name.to_string()).separators();
+        assert_eq!(separators("space_comma").unwrap().1, ",");
+        assert_eq!(separators("de").unwrap_err(), "Unknown number format: de");
+    }
+
+    #[test]
+    fn user_rules_override_and_extend_bundled() {
+        let dir = temp_dir("override");
+        fs::write(
+            dir.join(USER_RULES_TOML),
+            r#"
+[[parser]]
+id = "sberbank"
+bank = "sberbank"
+packages = ["ru.sberbankmobile"]
+enabled = false
+text_pattern = "unused"
+
+[[parser]]
+id = "example_bank"
+bank = "example_bank"
+packages = ["com.example.bank"]
+text_pattern = 'Spent (?P<amount>{amount}(?:\.\d{2})?) (?P<currency>\w+) at (?P<merchant>.+)'
+number_format = { group = " ,", decimal = "." }
+"#,
+        )
+        .unwrap();
+
+        let set = RuleSet::load(&dir).unwrap();
+        assert_eq!(set.user_rules, 2);
+        assert!(!set.rules().any(|rule| rule.id == "sberbank"));
+        assert_eq!(set.parse("ru.sberbankmobile", "Покупка Купер", "150 ₽"), None);
+
+        let payment = set
+            .parse("com.example.bank", "", "Spent 1,234.50 usd at Corner Shop *4321")
+            .unwrap();
+        assert_eq!(payment.amount, 1234.5);
+        assert_eq!(payment.currency, "USD");
+        assert_eq!(payment.merchant_name, "Corner Shop *4321");
+        assert_eq!(payment.card_mask.as_deref(), Some("*4321"));
+    }
+
+    #[test]
+    fn reads_json_rules_and_reports_errors() {
+        let dir = temp_dir("json");
+        fs::write(
+            dir.join(USER_RULES_JSON),
+            r#"{"parser": [{"id": "json_bank", "bank": "json_bank", "packages": ["com.example.json"],
+                "text_pattern": "(?P<amount>{amount}) ₸", "merchant_from_title": true, "currency": "KZT"}]}"#,
+        )
+        .unwrap();
+
+        let payment = RuleSet::load(&dir).unwrap().parse("com.example.json", "Magnum", "5 000 ₸").unwrap();
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Magnum", 5000.0));
+        assert_eq!(payment.currency, "KZT");
+
+        fs::write(dir.join(USER_RULES_TOML), "[[parser]]\nid = \"broken\"").unwrap();
+        assert!(RuleSet::load(&dir).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+use jni::objects::{JObject, JValue, JValueOwned};
+use jni::JNIEnv;
+use std::path::PathBuf;
+
+use super::jni_context::{java_error, read_string, JniContext};
+use super::PlatformBridge;
+
+/// Android: вызовы NotificationPermissionHelper и Context через JNI
+pub struct AndroidBridge;
+
+/// Статический метод хелпера вида `name(Context)`
+fn call_helper<T>(
+    name: &'static str,
+    signature: &'static str,
+    convert: impl FnOnce(JValueOwned<'_>) -> jni::errors::Result<T>,
+) -> Result<T, String> {
+    let jni = JniContext::get()?;
+    jni.with_env(|env, context| {
+        let value = jni.call_helper(env, name, signature, &[JValue::Object(context)])?;
+        convert(value).map_err(|e| format!("Failed to read {} result: {:?}", name, e))
+    })
+}
+
+fn shared_preferences<'local>(
+    env: &mut JNIEnv<'local>,
+    context: &JObject<'_>,
+    prefs: &str,
+) -> Result<JObject<'local>, String> {
+    let prefs_name = env
+        .new_string(prefs)
+        .map_err(|e| format!("Failed to create string: {:?}", e))?;
+
+    env.call_method(
+        context,
+        "getSharedPreferences",
+        "(Ljava/lang/String;I)Landroid/content/SharedPreferences;",
+        &[JValue::Object(&prefs_name), JValue::Int(0)],
+    )
+    .and_then(|prefs| prefs.l())
+    .map_err(|e| java_error(env, "getSharedPreferences", e))
+}
+
+impl PlatformBridge for AndroidBridge {
+    fn platform_name(&self) -> &'static str {
+        "android"
+    }
+
+    fn notification_listener_enabled(&self) -> Result<bool, String> {
+        call_helper("isNotificationListenerEnabled", "(Landroid/content/Context;)Z", |value| value.z())
+    }
+
+    fn open_notification_listener_settings(&self) -> Result<(), String> {
+        call_helper("openNotificationListenerSettings", "(Landroid/content/Context;)V", |value| value.v())
+    }
+
+    fn app_notification_permission(&self) -> Result<bool, String> {
+        call_helper("checkAppNotificationPermission", "(Landroid/content/Context;)Z", |value| value.z())
+    }
+
+    fn request_app_notification_permission(&self) -> Result<bool, String> {
+        // Context в Tauri — это MainActivity
+        call_helper("requestAppNotificationPermission", "(Landroid/app/Activity;)Z", |value| value.z())
+    }
+
+    fn open_app_notification_settings(&self) -> Result<(), String> {
+        call_helper("openAppNotificationSettings", "(Landroid/content/Context;)V", |value| value.v())
+    }
+
+    fn simulate_payment_notification(&self, title: &str, body: &str) -> Result<(), String> {
+        let jni = JniContext::get()?;
+        jni.with_env(|env, context| {
+            let title = env
+                .new_string(title)
+                .map_err(|e| format!("Failed to create title string: {:?}", e))?;
+            let body = env
+                .new_string(body)
+                .map_err(|e| format!("Failed to create body string: {:?}", e))?;
+
+            jni.call_helper(
+                env,
+                "simulatePaymentNotification",
+                "(Landroid/content/Context;Ljava/lang/String;Ljava/lang/String;)V",
+                &[
+                    JValue::Object(context),
+                    JValue::Object(&title),
+                    JValue::Object(&body),
+                ],
+            )?;
+
+            Ok(())
+        })
+    }
+
+    fn listener_heartbeat(&self) -> Result<i64, String> {
+        call_helper("getNotificationListenerHeartbeat", "(Landroid/content/Context;)J", |value| value.j())
+    }
+
+    fn ping_listener(&self) -> Result<(), String> {
+        call_helper("pingNotificationListenerService", "(Landroid/content/Context;)V", |value| value.v())
+    }
+
+    fn battery_optimization_disabled(&self) -> Result<bool, String> {
+        call_helper("isBatteryOptimizationDisabled", "(Landroid/content/Context;)Z", |value| value.z())
+    }
+
+    fn open_battery_optimization_settings(&self) -> Result<(), String> {
+        call_helper("openBatteryOptimizationSettings", "(Landroid/content/Context;)V", |value| value.v())
+    }
+
+    fn autostart_enabled(&self) -> Result<bool, String> {
+        call_helper("isAutostartEnabled", "(Landroid/content/Context;)Z", |value| value.z())
+    }
+
+    fn open_autostart_settings(&self) -> Result<(), String> {
+        call_helper("openAutostartSettings", "(Landroid/content/Context;)V", |value| value.v())
+    }
+
+    fn device_manufacturer(&self) -> Result<String, String> {
+        let jni = JniContext::get()?;
+        jni.with_env(|env, _| {
+            let manufacturer = jni
+                .call_helper(env, "getDeviceManufacturer", "()Ljava/lang/String;", &[])?
+                .l()
+                .map_err(|e| format!("Failed to read getDeviceManufacturer result: {:?}", e))?;
+
+            read_string(env, manufacturer)
+        })
+    }
+
+    fn get_pref(&self, prefs: &str, key: &str) -> Result<Option<String>, String> {
+        JniContext::get()?.with_env(|env, context| {
+            let prefs = shared_preferences(env, context, prefs)?;
+            let key = env
+                .new_string(key)
+                .map_err(|e| format!("Failed to create key: {:?}", e))?;
+
+            let value = env
+                .call_method(
+                    &prefs,
+                    "getString",
+                    "(Ljava/lang/String;Ljava/lang/String;)Ljava/lang/String;",
+                    &[JValue::Object(&key), JValue::Object(&JObject::null())],
+                )
+                .and_then(|value| value.l())
+                .map_err(|e| java_error(env, "getString", e))?;
+
+            if value.is_null() {
+                return Ok(None);
+            }
+            read_string(env, value).map(Some)
+        })
+    }
+
+    fn remove_pref(&self, prefs: &str, key: &str) -> Result<(), String> {
+        JniContext::get()?.with_env(|env, context| {
+            let prefs = shared_preferences(env, context, prefs)?;
+            let editor = env
+                .call_method(&prefs, "edit", "()Landroid/content/SharedPreferences$Editor;", &[])
+                .and_then(|editor| editor.l())
+                .map_err(|e| java_error(env, "edit", e))?;
+
+            let key = env
+                .new_string(key)
+                .map_err(|e| format!("Failed to create key: {:?}", e))?;
+            env.call_method(
+                &editor,
+                "remove",
+                "(Ljava/lang/String;)Landroid/content/SharedPreferences$Editor;",
+                &[JValue::Object(&key)],
+            )
+            .map_err(|e| java_error(env, "remove", e))?;
+
+            env.call_method(&editor, "apply", "()V", &[])
+                .map_err(|e| java_error(env, "apply", e))?;
+
+            Ok(())
+        })
+    }
+
+    fn files_dir(&self) -> Result<PathBuf, String> {
+        JniContext::get()?.files_dir()
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use super::PlatformBridge;
+
+const ANDROID_ONLY: &str = "This feature is only available on Android";
+
+/// Десктоп: системных разрешений и SharedPreferences нет, уведомления
+/// приходят только через D-Bus-монитор на Linux
+pub struct DesktopBridge {
+    data_dir: PathBuf,
+}
+
+impl DesktopBridge {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self { data_dir }
+    }
+}
+
+impl PlatformBridge for DesktopBridge {
+    fn platform_name(&self) -> &'static str {
+        "desktop"
+    }
+
+    fn notification_listener_enabled(&self) -> Result<bool, String> {
+        #[cfg(target_os = "linux")]
+        {
+            Ok(crate::dbus_monitor::is_running())
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Ok(false)
+        }
+    }
+
+    fn open_notification_listener_settings(&self) -> Result<(), String> {
+        Err(ANDROID_ONLY.to_string())
+    }
+
+    fn app_notification_permission(&self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn request_app_notification_permission(&self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn open_app_notification_settings(&self) -> Result<(), String> {
+        Err(ANDROID_ONLY.to_string())
+    }
+
+    fn simulate_payment_notification(&self, _title: &str, _body: &str) -> Result<(), String> {
+        Err("Payment notification simulation is only available on Android".to_string())
+    }
+
+    fn listener_heartbeat(&self) -> Result<i64, String> {
+        Ok(0)
+    }
+
+    fn ping_listener(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn battery_optimization_disabled(&self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn open_battery_optimization_settings(&self) -> Result<(), String> {
+        Err(ANDROID_ONLY.to_string())
+    }
+
+    fn autostart_enabled(&self) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn open_autostart_settings(&self) -> Result<(), String> {
+        Err(ANDROID_ONLY.to_string())
+    }
+
+    fn device_manufacturer(&self) -> Result<String, String> {
+        Ok(String::new())
+    }
+
+    fn get_pref(&self, _prefs: &str, _key: &str) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+
+    fn remove_pref(&self, _prefs: &str, _key: &str) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn files_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.clone())
+    }
+}
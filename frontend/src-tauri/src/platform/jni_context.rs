@@ -0,0 +1,203 @@
+use jni::objects::{GlobalRef, JClass, JObject, JStaticMethodID, JString, JValue, JValueOwned};
+use jni::signature::TypeSignature;
+use jni::{JNIEnv, JavaVM};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+const HELPER_CLASS: &str = "com.hochuplachu.hpio.NotificationPermissionHelper";
+// Локальные ссылки одного вызова живут в отдельном фрейме: потоки
+// прикреплены навсегда, и без фрейма ссылки копились бы до выхода потока
+const LOCAL_FRAME_CAPACITY: i32 = 16;
+
+/// JavaVM, Context и класс хелпера, найденные один раз на весь процесс.
+/// Настройки и статусы дёргаются пачками, и каждый вызов раньше заново
+/// прикреплял поток, искал класс и метод
+pub struct JniContext {
+    vm: JavaVM,
+    context: GlobalRef,
+    helper_class: GlobalRef,
+    helper_methods: Mutex<HashMap<(&'static str, &'static str), JStaticMethodID>>,
+    files_dir: OnceLock<PathBuf>,
+}
+
+static JNI_CONTEXT: OnceLock<JniContext> = OnceLock::new();
+
+impl JniContext {
+    pub fn get() -> Result<&'static JniContext, String> {
+        if let Some(context) = JNI_CONTEXT.get() {
+            return Ok(context);
+        }
+
+        let context = Self::init()?;
+        Ok(JNI_CONTEXT.get_or_init(|| context))
+    }
+
+    fn init() -> Result<Self, String> {
+        // Получаем JavaVM через ndk_context
+        let ctx = ndk_context::android_context();
+        let vm = unsafe { JavaVM::from_raw(ctx.vm() as *mut jni::sys::JavaVM) }
+            .map_err(|e| format!("Failed to get JavaVM: {:?}", e))?;
+
+        let mut env = vm
+            .attach_current_thread_permanently()
+            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
+        let context = unsafe { JObject::from_raw(ctx.context() as jni::sys::jobject) };
+        let context = env
+            .new_global_ref(context)
+            .map_err(|e| format!("Failed to reference Context: {:?}", e))?;
+
+        // find_class из потока, созданного не Java, видит только системный
+        // загрузчик классов, поэтому класс хелпера грузим через загрузчик Context
+        let helper_class = load_class(&mut env, context.as_obj(), HELPER_CLASS)?;
+        let helper_class = env
+            .new_global_ref(helper_class)
+            .map_err(|e| format!("Failed to reference {}: {:?}", HELPER_CLASS, e))?;
+
+        Ok(Self {
+            vm,
+            context,
+            helper_class,
+            helper_methods: Mutex::new(HashMap::new()),
+            files_dir: OnceLock::new(),
+        })
+    }
+
+    /// Прикрепляет текущий поток (один раз, до его завершения) и выполняет
+    /// `f` в своём фрейме локальных ссылок
+    pub fn with_env<T>(
+        &self,
+        f: impl FnOnce(&mut JNIEnv<'_>, &JObject<'_>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut env = self
+            .vm
+            .attach_current_thread_permanently()
+            .map_err(|e| format!("Failed to attach thread: {:?}", e))?;
+
+        env.with_local_frame(LOCAL_FRAME_CAPACITY, |env| {
+            Ok::<_, jni::errors::Error>(f(env, self.context.as_obj()))
+        })
+        .map_err(|e| format!("Failed to create JNI frame: {:?}", e))?
+    }
+
+    /// Вызывает статический метод NotificationPermissionHelper по
+    /// закэшированному id
+    pub fn call_helper<'local>(
+        &self,
+        env: &mut JNIEnv<'local>,
+        name: &'static str,
+        signature: &'static str,
+        args: &[JValue<'_, '_>],
+    ) -> Result<JValueOwned<'local>, String> {
+        let method = self.helper_method(env, name, signature)?;
+        let ret = TypeSignature::from_str(signature)
+            .map_err(|e| format!("Invalid signature {}: {:?}", signature, e))?
+            .ret;
+        let args: Vec<_> = args.iter().map(JValue::as_jni).collect();
+        let class: &JClass = self.helper_class.as_obj().into();
+
+        // Сигнатура та же, по которой получен id, так что типы аргументов
+        // и результата совпадают с методом
+        unsafe { env.call_static_method_unchecked(class, method, ret, &args) }
+            .map_err(|e| java_error(env, name, e))
+    }
+
+    fn helper_method(
+        &self,
+        env: &mut JNIEnv<'_>,
+        name: &'static str,
+        signature: &'static str,
+    ) -> Result<JStaticMethodID, String> {
+        let mut methods = self.helper_methods.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(method) = methods.get(&(name, signature)) {
+            return Ok(*method);
+        }
+
+        let class: &JClass = self.helper_class.as_obj().into();
+        let method = env
+            .get_static_method_id(class, name, signature)
+            .map_err(|e| java_error(env, name, e))?;
+        methods.insert((name, signature), method);
+        Ok(method)
+    }
+
+    /// filesDir не меняется за время жизни процесса
+    pub fn files_dir(&self) -> Result<PathBuf, String> {
+        if let Some(files_dir) = self.files_dir.get() {
+            return Ok(files_dir.clone());
+        }
+
+        let files_dir = self.with_env(|env, context| {
+            let files_dir = env
+                .call_method(context, "getFilesDir", "()Ljava/io/File;", &[])
+                .and_then(|files_dir| files_dir.l())
+                .map_err(|e| java_error(env, "getFilesDir", e))?;
+
+            let path = env
+                .call_method(&files_dir, "getAbsolutePath", "()Ljava/lang/String;", &[])
+                .and_then(|path| path.l())
+                .map_err(|e| java_error(env, "getAbsolutePath", e))?;
+
+            read_string(env, path).map(PathBuf::from)
+        })?;
+
+        Ok(self.files_dir.get_or_init(|| files_dir).clone())
+    }
+}
+
+fn load_class<'local>(
+    env: &mut JNIEnv<'local>,
+    context: &JObject<'_>,
+    name: &str,
+) -> Result<JObject<'local>, String> {
+    let loader = env
+        .call_method(context, "getClassLoader", "()Ljava/lang/ClassLoader;", &[])
+        .and_then(|loader| loader.l())
+        .map_err(|e| java_error(env, "getClassLoader", e))?;
+
+    let name = env
+        .new_string(name)
+        .map_err(|e| format!("Failed to create string: {:?}", e))?;
+    env.call_method(
+        &loader,
+        "loadClass",
+        "(Ljava/lang/String;)Ljava/lang/Class;",
+        &[JValue::Object(&name)],
+    )
+    .and_then(|class| class.l())
+    .map_err(|e| java_error(env, "loadClass", e))
+}
+
+pub fn read_string(env: &mut JNIEnv<'_>, object: JObject<'_>) -> Result<String, String> {
+    let string = JString::from(object);
+    let value: String = env
+        .get_string(&string)
+        .map_err(|e| format!("Failed to read string: {:?}", e))?
+        .into();
+    Ok(value)
+}
+
+/// Превращает ошибку JNI в читаемый текст. Если Java бросила исключение,
+/// снимает его (иначе следующий JNI-вызов упадёт) и берёт `toString()`
+pub fn java_error(env: &mut JNIEnv<'_>, call: &str, error: jni::errors::Error) -> String {
+    if !env.exception_check().unwrap_or(false) {
+        return format!("Failed to call {}: {:?}", call, error);
+    }
+
+    let throwable = env.exception_occurred();
+    let _ = env.exception_clear();
+    let description = throwable.ok().and_then(|throwable| {
+        let text = env
+            .call_method(&throwable, "toString", "()Ljava/lang/String;", &[])
+            .and_then(|text| text.l())
+            .ok()?;
+        read_string(env, text).ok()
+    });
+    // toString() тоже мог бросить исключение
+    let _ = env.exception_clear();
+
+    match description {
+        Some(description) => format!("{} threw {}", call, description),
+        None => format!("{} threw a Java exception", call),
+    }
+}
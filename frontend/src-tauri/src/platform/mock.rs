@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use super::PlatformBridge;
+
+/// Состояние платформы, которое тест задаёт заранее и проверяет после вызова
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub platform_name: &'static str,
+    pub listener_enabled: bool,
+    pub app_permission: bool,
+    pub heartbeat: i64,
+    pub battery_optimization_disabled: bool,
+    pub autostart_enabled: bool,
+    pub manufacturer: String,
+    pub prefs: HashMap<(String, String), String>,
+    pub files_dir: PathBuf,
+    /// Вызовы с побочными эффектами: открытые настройки, пинги, симуляции
+    pub calls: Vec<String>,
+    /// Методы, которые вернут ошибку, как при сбое JNI
+    pub failing: Vec<&'static str>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockBridge {
+    state: Mutex<MockState>,
+}
+
+impl MockBridge {
+    pub fn new(state: MockState) -> Self {
+        Self {
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    fn check(&self, method: &str) -> Result<(), String> {
+        if self.state().failing.contains(&method) {
+            return Err(format!("{} failed", method));
+        }
+        Ok(())
+    }
+
+    fn record(&self, call: &str) -> Result<(), String> {
+        self.check(call)?;
+        self.state().calls.push(call.to_string());
+        Ok(())
+    }
+}
+
+impl PlatformBridge for MockBridge {
+    fn platform_name(&self) -> &'static str {
+        self.state().platform_name
+    }
+
+    fn notification_listener_enabled(&self) -> Result<bool, String> {
+        self.check("notification_listener_enabled")?;
+        Ok(self.state().listener_enabled)
+    }
+
+    fn open_notification_listener_settings(&self) -> Result<(), String> {
+        self.record("open_notification_listener_settings")
+    }
+
+    fn app_notification_permission(&self) -> Result<bool, String> {
+        self.check("app_notification_permission")?;
+        Ok(self.state().app_permission)
+    }
+
+    fn request_app_notification_permission(&self) -> Result<bool, String> {
+        self.record("request_app_notification_permission")?;
+        Ok(self.state().app_permission)
+    }
+
+    fn open_app_notification_settings(&self) -> Result<(), String> {
+        self.record("open_app_notification_settings")
+    }
+
+    fn simulate_payment_notification(&self, title: &str, body: &str) -> Result<(), String> {
+        self.check("simulate_payment_notification")?;
+        self.state()
+            .calls
+            .push(format!("simulate_payment_notification:{}:{}", title, body));
+        Ok(())
+    }
+
+    fn listener_heartbeat(&self) -> Result<i64, String> {
+        self.check("listener_heartbeat")?;
+        Ok(self.state().heartbeat)
+    }
+
+    fn ping_listener(&self) -> Result<(), String> {
+        self.record("ping_listener")
+    }
+
+    fn battery_optimization_disabled(&self) -> Result<bool, String> {
+        self.check("battery_optimization_disabled")?;
+        Ok(self.state().battery_optimization_disabled)
+    }
+
+    fn open_battery_optimization_settings(&self) -> Result<(), String> {
+        self.record("open_battery_optimization_settings")
+    }
+
+    fn autostart_enabled(&self) -> Result<bool, String> {
+        self.check("autostart_enabled")?;
+        Ok(self.state().autostart_enabled)
+    }
+
+    fn open_autostart_settings(&self) -> Result<(), String> {
+        self.record("open_autostart_settings")
+    }
+
+    fn device_manufacturer(&self) -> Result<String, String> {
+        self.check("device_manufacturer")?;
+        Ok(self.state().manufacturer.clone())
+    }
+
+    fn get_pref(&self, prefs: &str, key: &str) -> Result<Option<String>, String> {
+        self.check("get_pref")?;
+        Ok(self
+            .state()
+            .prefs
+            .get(&(prefs.to_string(), key.to_string()))
+            .cloned())
+    }
+
+    fn remove_pref(&self, prefs: &str, key: &str) -> Result<(), String> {
+        self.check("remove_pref")?;
+        self.state().prefs.remove(&(prefs.to_string(), key.to_string()));
+        Ok(())
+    }
+
+    fn files_dir(&self) -> Result<PathBuf, String> {
+        self.check("files_dir")?;
+        Ok(self.state().files_dir.clone())
+    }
+}
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+
+#[cfg(target_os = "android")]
+pub mod android;
+#[cfg(target_os = "android")]
+mod jni_context;
+#[cfg(not(target_os = "android"))]
+mod desktop;
+#[cfg(test)]
+pub mod mock;
+
+/// Всё, что команды берут у платформы: разрешения, настройки системы,
+/// состояние слушателя уведомлений, SharedPreferences и каталог данных.
+/// На Android это JNI-вызовы NotificationPermissionHelper, на десктопе —
+/// заглушки, в тестах — `mock::MockBridge`.
+pub trait PlatformBridge: Send + Sync {
+    /// "android", "desktop" или "mock": от этого зависит, какие проверки имеют смысл
+    fn platform_name(&self) -> &'static str;
+
+    fn notification_listener_enabled(&self) -> Result<bool, String>;
+    fn open_notification_listener_settings(&self) -> Result<(), String>;
+
+    fn app_notification_permission(&self) -> Result<bool, String>;
+    /// Запрашивает разрешение; `true`, если оно уже было выдано
+    fn request_app_notification_permission(&self) -> Result<bool, String>;
+    fn open_app_notification_settings(&self) -> Result<(), String>;
+    fn simulate_payment_notification(&self, title: &str, body: &str) -> Result<(), String>;
+
+    fn listener_heartbeat(&self) -> Result<i64, String>;
+    fn ping_listener(&self) -> Result<(), String>;
+
+    fn battery_optimization_disabled(&self) -> Result<bool, String>;
+    fn open_battery_optimization_settings(&self) -> Result<(), String>;
+    fn autostart_enabled(&self) -> Result<bool, String>;
+    fn open_autostart_settings(&self) -> Result<(), String>;
+    fn device_manufacturer(&self) -> Result<String, String>;
+
+    /// Строка из SharedPreferences `prefs`; `None`, если ключа нет
+    fn get_pref(&self, prefs: &str, key: &str) -> Result<Option<String>, String>;
+    fn remove_pref(&self, prefs: &str, key: &str) -> Result<(), String>;
+
+    /// Каталог данных приложения: filesDir на Android (там же пишет
+    /// слушатель), app data dir на остальных платформах
+    fn files_dir(&self) -> Result<PathBuf, String>;
+}
+
+pub type Platform = Arc<dyn PlatformBridge>;
+
+pub fn default_bridge<R: Runtime>(app: &AppHandle<R>) -> Result<Platform, String> {
+    #[cfg(target_os = "android")]
+    {
+        let _ = app;
+        Ok(Arc::new(android::AndroidBridge))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        use tauri::Manager;
+
+        let data_dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to resolve app data dir: {:?}", e))?;
+        Ok(Arc::new(desktop::DesktopBridge::new(data_dir)))
+    }
+}
@@ -0,0 +1,111 @@
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::redaction;
+use crate::schema::{self, CURRENT_SCHEMA_VERSION};
+use crate::store::NotificationStore;
+use crate::worker;
+
+/// Запись, которую слушатель сохранил, а импорт отбросил
+#[derive(Debug, Clone, Serialize)]
+pub struct QuarantinedNotification {
+    pub id: i64,
+    /// Файл или канал, откуда пришла запись
+    pub source: String,
+    pub payload: Value,
+    pub error: String,
+    pub schema_version: Option<u32>,
+    pub quarantined_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuarantineExport {
+    exported_at: String,
+    current_schema_version: u32,
+    entries: Vec<QuarantinedNotification>,
+}
+
+/// Сохраняет отброшенные записи, замаскировав коды и номера (см. `redaction`).
+/// Одинаковый payload хранится один раз
+pub fn add(store: &NotificationStore, source: &str, rejected: &[(Value, String)]) -> Result<(), String> {
+    let mut conn = store.connection()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {:?}", e))?;
+    let now = chrono::Utc::now().timestamp_millis();
+
+    for (payload, error) in rejected {
+        log::warn!("Quarantined notification from {}: {}", source, error);
+        tx.execute(
+            "INSERT OR IGNORE INTO quarantine (source, payload, error, schema_version, quarantined_at, redacted)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+            params![
+                source,
+                redaction::redact_json(payload).to_string(),
+                error,
+                schema::schema_version(payload).ok(),
+                now,
+            ],
+        )
+        .map_err(|e| format!("Failed to quarantine notification: {:?}", e))?;
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit quarantine: {:?}", e))
+}
+
+pub fn list(store: &NotificationStore, limit: Option<u32>) -> Result<Vec<QuarantinedNotification>, String> {
+    let conn = store.connection()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, source, payload, error, schema_version, quarantined_at
+             FROM quarantine ORDER BY id DESC LIMIT ?1",
+        )
+        .map_err(|e| format!("Failed to prepare quarantine query: {:?}", e))?;
+    let rows = stmt
+        .query_map(params![limit.map(i64::from).unwrap_or(-1)], |row| {
+            let payload: String = row.get(2)?;
+            Ok(QuarantinedNotification {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                // Повреждённый файл хранится как есть, строкой
+                payload: serde_json::from_str(&payload).unwrap_or(Value::String(payload)),
+                error: row.get(3)?,
+                schema_version: row.get(4)?,
+                quarantined_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query quarantine: {:?}", e))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read quarantined notification: {:?}", e))
+}
+
+#[tauri::command]
+pub async fn list_quarantined_notifications(
+    store: tauri::State<'_, NotificationStore>,
+    limit: Option<u32>,
+) -> Result<Vec<QuarantinedNotification>, String> {
+    let store = store.inner().clone();
+    worker::run(move || list(&store, limit)).await
+}
+
+/// JSON-документ со всеми отброшенными записями, чтобы приложить к отчёту
+#[tauri::command]
+pub async fn export_quarantined_notifications(
+    store: tauri::State<'_, NotificationStore>,
+) -> Result<String, String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        let export = QuarantineExport {
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            current_schema_version: CURRENT_SCHEMA_VERSION,
+            entries: list(&store, None)?,
+        };
+        serde_json::to_string_pretty(&export)
+            .map_err(|e| format!("Failed to serialize quarantine: {:?}", e))
+    })
+    .await
+}
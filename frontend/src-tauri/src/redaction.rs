@@ -0,0 +1,388 @@
+use regex::{Captures, Regex};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::notifications::PendingNotification;
+
+/// `notification_type` уведомления с кодом подтверждения: в очередь импорта
+/// оно не попадает (см. `is_import_candidate`)
+pub const SECURITY_CODE_TYPE: &str = "SECURITY_CODE";
+/// Текст, который хранится вместо уведомления с кодом подтверждения
+pub const SECURITY_CODE_TEXT: &str = "Код подтверждения скрыт";
+
+const MASK: char = '•';
+/// Сколько символов после ключевого слова ищется код
+const CODE_WINDOW: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveKind {
+    OneTimePassword,
+    /// Код 3-D Secure для подтверждения покупки
+    SecureCode,
+    CardNumber,
+    PhoneNumber,
+    Passport,
+}
+
+impl SensitiveKind {
+    pub fn is_security_code(self) -> bool {
+        matches!(self, SensitiveKind::OneTimePassword | SensitiveKind::SecureCode)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redacted {
+    pub text: String,
+    pub kinds: Vec<SensitiveKind>,
+}
+
+fn regex(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("invalid redaction regex"))
+}
+
+// Номер карты: 4-4-4-4 с хвостом до 19 цифр, Amex 4-6-5 или 13-19 цифр подряд
+fn card_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"\b(?:\d{4}[ -]){3}\d{4}(?:[ -]?\d{1,3})?\b|\b\d{4}[ -]\d{6}[ -]\d{5}\b|\b\d{13,19}\b",
+    )
+}
+
+// Международный номер или российский через 8. Сумма вроде "+1 234 567 890,00"
+// отличается дробной частью и остаётся как есть
+fn phone_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?P<phone>\+\d{1,3}[ -]?\(?\d{2,3}\)?(?:[ -]?\d{2,3}){2,3}|\b8[ -]?\(?[3-9]\d{2}\)?[ -]?\d{3}[ -]?\d{2}[ -]?\d{2})\b(?P<fraction>[.,]\d)?",
+    )
+}
+
+fn passport_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)(?P<prefix>\b(?:паспорт\w*|passport\w*)[^\d\n]{0,25})(?P<number>\d[\d ]{4,10}\d)\b",
+    )
+}
+
+// Ключевое слово, после которого в той же строке идёт код: "Код для входа: 1234".
+// Просто «код» бывает и в чеке ("Код операции 48213917"), поэтому он в группе weak
+fn code_keyword_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)\b(?:(?P<weak>код\w{0,3}|code)|парол\w*|password|passcode|otp|pin|cvc2?|cvv2?|kod|კოდი)\b",
+    )
+}
+
+// Формулировки, при которых «код» в строке — код подтверждения
+fn confirmation_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)подтвержд|для входа|одноразов|3-?d[ -]?secure|\b3ds\b|для (?:оплаты|покупки)|никому|\bваш\w*\s+код|verification|one-time|your code",
+    )
+}
+
+// Сумма рядом с кодом ("Пароль для оплаты 1500 ₽: 123456") кодом не считается
+fn code_candidate_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)\b\d{4,8}\b(?P<amount>[.,]\d|\s?(?:₽|руб|р\.|rub|usd|eur|\$|€|₸|₾|gel|kzt|byn|uzs|сум|тг))?",
+    )
+}
+
+// Код перед пояснением: "483920 — ваш код", "123456 is your verification code"
+fn leading_code_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)\b(?P<code>\d{4,8})\s*(?:[—–:-]|is|это)?\s*(?:ваш|your)\s+(?:\w+\s+){0,2}?(?:код|code|парол\w*|password|otp)\b",
+    )
+}
+
+// Код авторизации в чеке — не секрет, его номер не маскируется
+fn authorization_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(&CELL, r"(?i)авториз|author|approval")
+}
+
+fn secure_code_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    regex(
+        &CELL,
+        r"(?i)3-?d[ -]?secure|\b3ds\b|mir ?accept|secure ?code|подтвержд\w* (?:покупк|оплат|операци|платеж)|для (?:оплаты|покупки)|\bpurchase\b",
+    )
+}
+
+fn mask_digits(value: &str, keep_last: usize) -> String {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    let mut seen = 0;
+    value
+        .chars()
+        .map(|c| {
+            if !c.is_ascii_digit() {
+                return c;
+            }
+            seen += 1;
+            if seen > digits.saturating_sub(keep_last) {
+                c
+            } else {
+                MASK
+            }
+        })
+        .collect()
+}
+
+/// Байтовые диапазоны кодов подтверждения в тексте
+fn code_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    for caps in code_keyword_regex().captures_iter(text) {
+        let keyword = caps.get(0).expect("whole match");
+        if caps.name("weak").is_some() {
+            let line_start = text[..keyword.start()].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = text[keyword.end()..]
+                .find('\n')
+                .map_or(text.len(), |i| keyword.end() + i);
+            if !confirmation_regex().is_match(&text[line_start..line_end]) {
+                continue;
+            }
+        }
+        let window: String = text[keyword.end()..]
+            .chars()
+            .take_while(|c| *c != '\n')
+            .take(CODE_WINDOW)
+            .collect();
+        let code = code_candidate_regex()
+            .captures_iter(&window)
+            .filter(|caps| caps.name("amount").is_none())
+            .filter_map(|caps| caps.get(0))
+            .next();
+        let Some(code) = code else {
+            continue;
+        };
+        if authorization_regex().is_match(&window[..code.start()]) {
+            continue;
+        }
+        ranges.push((keyword.end() + code.start(), keyword.end() + code.end()));
+    }
+    ranges.extend(leading_code_regex().captures_iter(text).filter_map(|caps| {
+        let code = caps.name("code")?;
+        Some((code.start(), code.end()))
+    }));
+    ranges
+}
+
+fn mask_ranges(text: &str, ranges: &[(usize, usize)]) -> String {
+    text.char_indices()
+        .map(|(index, c)| {
+            let masked = ranges.iter().any(|(start, end)| (*start..*end).contains(&index));
+            if masked && c.is_ascii_digit() {
+                MASK
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Маскирует коды подтверждения, номера карт и телефонов, паспортные данные.
+/// Суммы и маски карт ("*1234") не меняются, поэтому текст разбирается как прежде
+pub fn redact(text: &str) -> Redacted {
+    let mut kinds = Vec::new();
+
+    // Номер карты первым: его цифры иначе приняли бы за телефон или код
+    let text = card_regex().replace_all(text, |caps: &Captures| {
+        kinds.push(SensitiveKind::CardNumber);
+        mask_digits(&caps[0], 4)
+    });
+    let text = phone_regex().replace_all(&text, |caps: &Captures| {
+        let digits = caps["phone"].chars().filter(char::is_ascii_digit).count();
+        if caps.name("fraction").is_some() || !(10..=15).contains(&digits) {
+            return caps[0].to_string();
+        }
+        kinds.push(SensitiveKind::PhoneNumber);
+        mask_digits(&caps[0], 0)
+    });
+    let text = passport_regex().replace_all(&text, |caps: &Captures| {
+        kinds.push(SensitiveKind::Passport);
+        format!("{}{}", &caps["prefix"], mask_digits(&caps["number"], 0))
+    });
+
+    let codes = code_ranges(&text);
+    let text = mask_ranges(&text, &codes);
+    if !codes.is_empty() {
+        kinds.push(if secure_code_regex().is_match(&text) {
+            SensitiveKind::SecureCode
+        } else {
+            SensitiveKind::OneTimePassword
+        });
+    }
+
+    Redacted { text, kinds }
+}
+
+pub fn redact_text(text: &str) -> String {
+    redact(text).text
+}
+
+/// То же для всех строк JSON, например записи из карантина
+pub fn redact_json(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redact_text(text)),
+        Value::Array(items) => Value::Array(items.iter().map(redact_json).collect()),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), redact_json(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Уведомление в том виде, в каком его можно хранить. У кода подтверждения
+/// остаётся только заголовок: исходный текст не сохраняется даже замаскированным
+pub fn redact_notification(notification: &PendingNotification) -> PendingNotification {
+    let mut kinds = Vec::new();
+    let mut redact_field = |value: &str| {
+        let redacted = redact(value);
+        kinds.extend(redacted.kinds);
+        redacted.text
+    };
+
+    let mut redacted = notification.clone();
+    redacted.title = redact_field(&notification.title);
+    redacted.text = redact_field(&notification.text);
+    let extras = &mut redacted.extras;
+    extras.conversation_title = extras.conversation_title.as_deref().map(&mut redact_field);
+    extras.big_text = extras.big_text.as_deref().map(&mut redact_field);
+    extras.sub_text = extras.sub_text.as_deref().map(&mut redact_field);
+    extras.text_lines = extras.text_lines.iter().map(|line| redact_field(line)).collect();
+
+    if kinds.iter().any(|kind| kind.is_security_code()) {
+        redacted.text = SECURITY_CODE_TEXT.to_string();
+        redacted.notification_type = Some(SECURITY_CODE_TYPE.to_string());
+        redacted.extras.big_text = None;
+        redacted.extras.sub_text = None;
+        redacted.extras.text_lines.clear();
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::{self, NotificationExtras};
+    use crate::parser;
+    use crate::store::NotificationStore;
+    use std::path::PathBuf;
+
+    fn notification(title: &str, text: &str) -> PendingNotification {
+        PendingNotification {
+            id: String::new(),
+            package_name: "ru.sberbankmobile".to_string(),
+            title: title.to_string(),
+            text: text.to_string(),
+            timestamp: 1700000000000,
+            notification_type: Some("PAYMENT".to_string()),
+            notification_key: None,
+            extras: NotificationExtras::default(),
+        }
+    }
+
+    #[test]
+    fn masks_sensitive_values_before_storing() {
+        let cases = [
+            (
+                "Код для входа в СберБанк Онлайн: 48392. Никому его не сообщайте",
+                "Код для входа в СберБанк Онлайн: •••••. Никому его не сообщайте",
+                vec![SensitiveKind::OneTimePassword],
+            ),
+            (
+                "Пароль для подтверждения покупки 1 500 ₽ в OZON: 123456",
+                "Пароль для подтверждения покупки 1 500 ₽ в OZON: ••••••",
+                vec![SensitiveKind::SecureCode],
+            ),
+            (
+                "483920 is your verification code",
+                "•••••• is your verification code",
+                vec![SensitiveKind::OneTimePassword],
+            ),
+            (
+                "Перевод на карту 2202 2061 2345 7165",
+                "Перевод на карту •••• •••• •••• 7165",
+                vec![SensitiveKind::CardNumber],
+            ),
+            (
+                "Перевод по номеру +7 (912) 345-67-89, 8 912 345 67 89",
+                "Перевод по номеру +• (•••) •••-••-••, • ••• ••• •• ••",
+                vec![SensitiveKind::PhoneNumber, SensitiveKind::PhoneNumber],
+            ),
+            (
+                "Паспорт 45 06 123456 подтверждён",
+                "Паспорт •• •• •••••• подтверждён",
+                vec![SensitiveKind::Passport],
+            ),
+            (
+                "Зачисление +1 234 567 890,00 ₽. Код авторизации 123456",
+                "Зачисление +1 234 567 890,00 ₽. Код авторизации 123456",
+                vec![],
+            ),
+            (
+                "150 ₽ — Баланс: 12 345,67 ₽ MasterCard •• 7165",
+                "150 ₽ — Баланс: 12 345,67 ₽ MasterCard •• 7165",
+                vec![],
+            ),
+            (
+                "Покупка 350 р. Пятерочка. Код операции 48213917",
+                "Покупка 350 р. Пятерочка. Код операции 48213917",
+                vec![],
+            ),
+            (
+                "Одноразовый код 7731 для Госуслуг",
+                "Одноразовый код •••• для Госуслуг",
+                vec![SensitiveKind::OneTimePassword],
+            ),
+        ];
+        for (text, expected, kinds) in cases {
+            assert_eq!(redact(text), Redacted { text: expected.to_string(), kinds }, "{}", text);
+        }
+
+        // Разбор не замечает маскирования
+        let payment = notification("Покупка Пятерочка", "150 ₽ — Баланс: 12 345,67 ₽ MasterCard •• 7165");
+        assert_eq!(parser::parse_notification(&redact_notification(&payment)), parser::parse_notification(&payment));
+        let transfer = redact_notification(&notification("Перевод", "Перевод на карту 2202 2061 2345 7165"));
+        assert_eq!(parser::extract_card_mask("", &transfer.text).as_deref(), Some("*7165"));
+
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let mut otp = notification("СберБанк", "Код 4839 для оплаты 990 ₽ в Пятерочка");
+        otp.extras.big_text = Some("Код 4839 для оплаты 990 ₽ в Пятерочка. Никому не сообщайте".to_string());
+        let stored = store.insert_all(&[payment, otp]).unwrap();
+        assert_eq!(stored[1].text, SECURITY_CODE_TEXT);
+        assert_eq!(stored[1].extras.big_text, None);
+        assert_eq!(stored[1].notification_type.as_deref(), Some(SECURITY_CODE_TYPE));
+
+        let pending = notifications::read_pending_notifications(&store).unwrap();
+        assert_eq!(pending.iter().map(|n| n.title.as_str()).collect::<Vec<_>>(), ["Покупка Пятерочка"]);
+
+        let json = serde_json::json!({"text": "Код подтверждения 1234", "lines": ["+7 912 345 67 89"], "timestamp": 1});
+        assert_eq!(
+            redact_json(&json),
+            serde_json::json!({"text": "Код подтверждения ••••", "lines": ["+• ••• ••• •• ••"], "timestamp": 1})
+        );
+    }
+
+    #[test]
+    fn keeps_payment_with_operation_code() {
+        let purchase = notification("СберБанк", "Покупка 350 р. Пятерочка. Код операции 48213917");
+        let redacted = redact_notification(&purchase);
+        assert_eq!(redacted.text, purchase.text);
+        assert_eq!(redacted.notification_type.as_deref(), Some("PAYMENT"));
+    }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::notifications::{NotificationExtras, PendingNotification};
+
+/// Версия записи, которую пишет слушатель сейчас. Записи старых версий
+/// приводятся к текущей через `migrate_*` при импорте
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerNotificationType {
+    Payment,
+    Refund,
+    Transfer,
+    Other,
+}
+
+impl ListenerNotificationType {
+    /// Имя в `PendingNotification::notification_type`, которое ждёт фронтенд
+    pub fn legacy_name(self) -> &'static str {
+        match self {
+            Self::Payment => "PAYMENT",
+            Self::Refund => "REFUND",
+            Self::Transfer => "TRANSFER",
+            Self::Other => "OTHER",
+        }
+    }
+
+    fn from_legacy_name(name: &str) -> Option<Self> {
+        match name {
+            "PAYMENT" => Some(Self::Payment),
+            "REFUND" => Some(Self::Refund),
+            "TRANSFER" => Some(Self::Transfer),
+            "OTHER" => Some(Self::Other),
+            _ => None,
+        }
+    }
+}
+
+/// v1: формат до версионирования — без `schemaVersion`, тип строкой
+/// `NotificationType.name` из Kotlin
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryV1 {
+    package_name: String,
+    title: String,
+    text: String,
+    timestamp: i64,
+    #[serde(default)]
+    notification_type: Option<String>,
+    #[serde(default)]
+    notification_key: Option<String>,
+}
+
+/// Текущая запись `pending_notifications.json`. Поля extras необязательные:
+/// слушатель старой сборки их не пишет, но формат от этого не меняется
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpoolEntry {
+    pub schema_version: u32,
+    pub package_name: String,
+    /// У части банков (Райффайзен) заголовка нет
+    #[serde(default)]
+    pub title: String,
+    pub text: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    pub notification_type: Option<ListenerNotificationType>,
+    #[serde(default)]
+    pub notification_key: Option<String>,
+    #[serde(default)]
+    pub post_time: Option<i64>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    #[serde(default)]
+    pub group_key: Option<String>,
+    #[serde(default)]
+    pub conversation_title: Option<String>,
+    #[serde(default)]
+    pub big_text: Option<String>,
+    #[serde(default)]
+    pub sub_text: Option<String>,
+    #[serde(default)]
+    pub text_lines: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn migrate_v1(entry: EntryV1) -> Result<SpoolEntry, String> {
+    let notification_type = match entry.notification_type.as_deref() {
+        None => None,
+        Some(name) => Some(
+            ListenerNotificationType::from_legacy_name(name)
+                .ok_or_else(|| format!("Unknown notificationType: {}", name))?,
+        ),
+    };
+
+    Ok(SpoolEntry {
+        schema_version: 2,
+        package_name: entry.package_name,
+        title: entry.title,
+        text: entry.text,
+        timestamp: entry.timestamp,
+        notification_type,
+        notification_key: entry.notification_key,
+        ..Default::default()
+    })
+}
+
+/// Версия записи; записи без `schemaVersion` — это v1
+pub fn schema_version(value: &Value) -> Result<u32, String> {
+    match value.get("schemaVersion") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| format!("Invalid schemaVersion: {}", version)),
+    }
+}
+
+impl SpoolEntry {
+    /// Разбирает запись любой известной версии и проверяет её
+    pub fn parse(value: &Value) -> Result<Self, String> {
+        if !value.is_object() {
+            return Err("Entry is not a JSON object".to_string());
+        }
+
+        let entry = match schema_version(value)? {
+            1 => migrate_v1(
+                serde_json::from_value(value.clone())
+                    .map_err(|e| format!("Invalid v1 entry: {}", e))?,
+            )?,
+            2 => serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid v2 entry: {}", e))?,
+            version => return Err(format!("Unsupported schemaVersion: {}", version)),
+        };
+
+        entry.validate()?;
+        Ok(entry)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.package_name.trim().is_empty() {
+            return Err("packageName is empty".to_string());
+        }
+        if self.text.trim().is_empty() {
+            return Err("text is empty".to_string());
+        }
+        if self.timestamp <= 0 {
+            return Err(format!("Invalid timestamp: {}", self.timestamp));
+        }
+        Ok(())
+    }
+
+    pub fn into_pending(self) -> PendingNotification {
+        PendingNotification {
+            id: String::new(),
+            package_name: self.package_name,
+            title: self.title,
+            text: self.text,
+            timestamp: self.timestamp,
+            notification_type: self
+                .notification_type
+                .map(|kind| kind.legacy_name().to_string()),
+            notification_key: self.notification_key,
+            extras: NotificationExtras {
+                post_time: self.post_time.filter(|time| *time > 0),
+                channel_id: non_blank(self.channel_id),
+                group_key: non_blank(self.group_key),
+                conversation_title: non_blank(self.conversation_title),
+                big_text: non_blank(self.big_text),
+                sub_text: non_blank(self.sub_text),
+                text_lines: self
+                    .text_lines
+                    .into_iter()
+                    .filter_map(|line| non_blank(Some(line)))
+                    .collect(),
+                category: non_blank(self.category),
+            },
+        }
+        .with_computed_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrates_v1_and_reads_v2() {
+        let v1 = SpoolEntry::parse(&json!({
+            "packageName": "ru.sberbankmobile",
+            "title": "Покупка",
+            "text": "150 ₽",
+            "timestamp": 1700000000000i64,
+            "notificationType": "PAYMENT",
+            "cardLast4": "1234",
+        }))
+        .unwrap();
+        let v2 = SpoolEntry::parse(&json!({
+            "schemaVersion": 2,
+            "packageName": "ru.sberbankmobile",
+            "title": "Покупка",
+            "text": "150 ₽",
+            "timestamp": 1700000000000i64,
+            "notificationType": "payment",
+        }))
+        .unwrap();
+
+        assert_eq!(v1, v2);
+        assert_eq!(v1.into_pending().notification_type.as_deref(), Some("PAYMENT"));
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let reject = |value: Value| SpoolEntry::parse(&value).unwrap_err();
+
+        assert!(reject(json!({"packageName": "com.yandex.bank", "title": "broken"})).starts_with("Invalid v1 entry"));
+        assert_eq!(
+            reject(json!({"schemaVersion": 2, "packageName": "a", "text": " ", "timestamp": 1})),
+            "text is empty"
+        );
+        assert_eq!(
+            reject(json!({"packageName": "a", "title": "", "text": "x", "timestamp": 1, "notificationType": "SPAM"})),
+            "Unknown notificationType: SPAM"
+        );
+        assert_eq!(reject(json!({"schemaVersion": 9})), "Unsupported schemaVersion: 9");
+        assert_eq!(reject(json!("text")), "Entry is not a JSON object");
+    }
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+
+use crate::notifications::{NotificationExtras, PendingNotification};
+use crate::store::{NotificationStore, StoredNotification};
+use crate::worker;
+
+/// Канал, по которому симулированные уведомления отличаются от настоящих
+pub const SIMULATOR_CHANNEL: &str = "simulator";
+
+const DEFAULT_CARD: &str = "9012";
+
+/// Шаблон уведомления банка. Тексты повторяют форматы из rules/default.toml,
+/// поэтому уведомление проходит тот же разбор, что и настоящее
+struct Template {
+    id: &'static str,
+    bank: &'static str,
+    package_name: &'static str,
+    render: fn(&SimulationRequest, &str) -> (String, String),
+}
+
+const TEMPLATES: &[Template] = &[
+    Template {
+        id: "raiffeisen",
+        bank: "Райффайзен",
+        package_name: "ru.raiffeisennews",
+        render: |request, card| {
+            (
+                format!("Заплатили картой *{}", card),
+                format!(
+                    "− {} ₽ в {}. Теперь на карте 34 574.90 ₽",
+                    format_amount(request.amount, ' ', '.'),
+                    request.merchant
+                ),
+            )
+        },
+    },
+    Template {
+        id: "sberbank",
+        bank: "Сбербанк",
+        package_name: "ru.sberbankmobile",
+        render: |request, card| {
+            (
+                format!("Покупка {}", request.merchant),
+                format!(
+                    "{} ₽ — Баланс: 12 345,67 ₽ MasterCard •• {}",
+                    format_amount(request.amount, ' ', ','),
+                    card
+                ),
+            )
+        },
+    },
+    Template {
+        id: "tbank",
+        bank: "Т-Банк",
+        package_name: "com.idamob.tinkoff.android",
+        render: |request, card| {
+            (
+                request.merchant.clone(),
+                format!(
+                    "Покупка на {} ₽, карта *{}. Доступно 8 120,00 ₽",
+                    format_amount(request.amount, ' ', ','),
+                    card
+                ),
+            )
+        },
+    },
+    Template {
+        id: "yandex_bank",
+        bank: "Яндекс Банк",
+        package_name: "com.yandex.bank",
+        render: |request, _card| {
+            (
+                request.merchant.clone(),
+                format!("Покупка на {} RUB", format_amount(request.amount, ' ', '.')),
+            )
+        },
+    },
+    Template {
+        id: "ozon_external",
+        bank: "Ozon Банк",
+        package_name: "ru.ozon.app.android",
+        render: |request, _card| {
+            (
+                "Ozon Банк".to_string(),
+                format!(
+                    "Покупка в {}. {} RUR",
+                    request.merchant,
+                    format_amount(request.amount, ' ', '.')
+                ),
+            )
+        },
+    },
+    Template {
+        id: "bank_of_georgia",
+        bank: "Bank of Georgia",
+        package_name: "ge.bog.mobilebank",
+        render: |request, _card| {
+            (
+                "Bank of Georgia".to_string(),
+                format!(
+                    "Purchase: {} GEL at {}.",
+                    format_amount(request.amount, ',', '.'),
+                    request.merchant
+                ),
+            )
+        },
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationTemplate {
+    pub id: String,
+    pub bank: String,
+    pub package_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationRequest {
+    pub template: String,
+    pub amount: f64,
+    pub merchant: String,
+    /// Время уведомления в мс; по умолчанию — сейчас
+    #[serde(default)]
+    pub timestamp: Option<i64>,
+    #[serde(default)]
+    pub card_last4: Option<String>,
+}
+
+/// 1234.5 -> "1 234.50" для group = ' ', decimal = '.'
+fn format_amount(amount: f64, group: char, decimal: char) -> String {
+    let cents = (amount * 100.0).round() as i64;
+    let digits = (cents / 100).to_string();
+    let (head, tail) = digits.split_at((digits.len() - 1) % 3 + 1);
+    let mut integer = head.to_string();
+    for chunk in tail.as_bytes().chunks(3) {
+        integer.push(group);
+        integer.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+    }
+    format!("{}{}{:02}", integer, decimal, cents % 100)
+}
+
+pub fn templates() -> Vec<SimulationTemplate> {
+    TEMPLATES
+        .iter()
+        .map(|template| SimulationTemplate {
+            id: template.id.to_string(),
+            bank: template.bank.to_string(),
+            package_name: template.package_name.to_string(),
+        })
+        .collect()
+}
+
+/// Собирает уведомление по шаблону, не сохраняя его
+pub fn build(request: &SimulationRequest) -> Result<PendingNotification, String> {
+    let template = TEMPLATES
+        .iter()
+        .find(|template| template.id == request.template)
+        .ok_or_else(|| format!("Unknown simulation template: {}", request.template))?;
+    if !request.amount.is_finite() || request.amount <= 0.0 || request.amount >= 1e12 {
+        return Err(format!("Invalid amount: {}", request.amount));
+    }
+    if request.merchant.trim().is_empty() {
+        return Err("Merchant must not be empty".to_string());
+    }
+    let card = request.card_last4.as_deref().map(str::trim).unwrap_or(DEFAULT_CARD);
+    if card.len() != 4 || !card.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Card must be the last 4 digits: {}", card));
+    }
+
+    let request = SimulationRequest {
+        merchant: request.merchant.trim().to_string(),
+        ..request.clone()
+    };
+    let (title, text) = (template.render)(&request, card);
+    let timestamp = request
+        .timestamp
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
+
+    Ok(PendingNotification {
+        id: String::new(),
+        package_name: template.package_name.to_string(),
+        title,
+        text,
+        timestamp,
+        notification_type: Some("PAYMENT".to_string()),
+        notification_key: None,
+        extras: NotificationExtras {
+            post_time: Some(timestamp),
+            channel_id: Some(SIMULATOR_CHANNEL.to_string()),
+            ..Default::default()
+        },
+    }
+    .with_computed_id())
+}
+
+/// Кладёт уведомление в очередь так же, как слушатель: дальше его разбирает
+/// watcher и отдаёт фронтенду событием pending-notification
+pub fn inject(store: &NotificationStore, request: &SimulationRequest) -> Result<StoredNotification, String> {
+    let notification = build(request)?;
+    store
+        .insert_all(std::slice::from_ref(&notification))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Notification already exists: {}", notification.id))
+}
+
+#[tauri::command]
+pub async fn list_simulation_templates() -> Result<Vec<SimulationTemplate>, String> {
+    Ok(templates())
+}
+
+#[tauri::command]
+pub async fn simulate_notification(
+    store: tauri::State<'_, NotificationStore>,
+    request: SimulationRequest,
+) -> Result<StoredNotification, String> {
+    let store = store.inner().clone();
+    worker::run(move || inject(&store, &request)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use std::path::PathBuf;
+
+    #[test]
+    fn every_template_parses_back_to_the_request() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        for template in templates() {
+            let request = SimulationRequest {
+                template: template.id.clone(),
+                amount: 12345.6,
+                merchant: " Пятерочка ".to_string(),
+                timestamp: Some(1700000000000),
+                card_last4: None,
+            };
+            let stored = inject(&store, &request).unwrap();
+            assert_eq!(stored.package_name, template.package_name);
+
+            let payment = parser::parse_notification(&stored.to_pending())
+                .unwrap_or_else(|| panic!("{} is not parsed: {}", template.id, stored.text));
+            assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Пятерочка", 12345.6), "{}", template.id);
+
+            assert_eq!(inject(&store, &request).unwrap_err(), format!("Notification already exists: {}", stored.id));
+        }
+        assert_eq!(store.pending().unwrap().len(), TEMPLATES.len());
+
+        assert_eq!(format_amount(1234567.0, ' ', ','), "1 234 567,00");
+        assert!(build(&SimulationRequest {
+            template: "unknown".to_string(),
+            amount: 1.0,
+            merchant: "Shop".to_string(),
+            timestamp: None,
+            card_last4: None,
+        })
+        .is_err());
+    }
+}
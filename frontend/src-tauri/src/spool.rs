@@ -0,0 +1,195 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+pub const LOCK_SUFFIX: &str = "lock";
+pub const TEMP_SUFFIX: &str = "tmp";
+pub const DRAINING_SUFFIX: &str = "draining";
+pub const RECOVERED_PREFIX: &str = "recovered-";
+
+/// Держатель lock-файла, упавший посреди записи, не должен блокировать
+/// обмен навсегда. Запись занимает миллисекунды, поэтому 5 с с запасом
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(5);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+fn sibling(spool_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = spool_path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+pub fn lock_path(spool_path: &Path) -> PathBuf {
+    sibling(spool_path, LOCK_SUFFIX)
+}
+
+pub fn temp_path(spool_path: &Path) -> PathBuf {
+    sibling(spool_path, TEMP_SUFFIX)
+}
+
+pub fn draining_path(spool_path: &Path) -> PathBuf {
+    sibling(spool_path, DRAINING_SUFFIX)
+}
+
+pub fn recovered_path(spool_path: &Path) -> PathBuf {
+    sibling(
+        spool_path,
+        &format!("{}{}", RECOVERED_PREFIX, chrono::Utc::now().timestamp_millis()),
+    )
+}
+
+/// Блокировка `pending_notifications.json.lock`, которую соблюдают обе стороны:
+/// файл создаётся атомарно (`create_new` здесь, `File.createNewFile` в Kotlin)
+/// и удаляется при drop. `flock` не подходит: слушатель работает в том же
+/// процессе, а блокировки `flock`/`fcntl` привязаны к процессу, не к потоку
+#[derive(Debug)]
+pub struct SpoolLock {
+    path: PathBuf,
+}
+
+impl SpoolLock {
+    pub fn acquire(spool_path: &Path) -> Result<Self, String> {
+        Self::acquire_with(spool_path, LOCK_TIMEOUT, LOCK_STALE_AFTER)
+    }
+
+    fn acquire_with(spool_path: &Path, timeout: Duration, stale_after: Duration) -> Result<Self, String> {
+        let path = lock_path(spool_path);
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // Для отладки: кто держит блокировку
+                    let _ = write!(file, "rust {}", std::process::id());
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(format!("Failed to create notifications lock: {:?}", e)),
+            }
+
+            if is_stale(&path, stale_after) {
+                log::warn!("Removing stale notifications lock {}", path.display());
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if Instant::now() >= deadline {
+                return Err("Timed out waiting for notifications file lock".to_string());
+            }
+            thread::sleep(LOCK_RETRY_INTERVAL);
+        }
+    }
+}
+
+impl Drop for SpoolLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log::warn!("Failed to release notifications lock: {:?}", e);
+        }
+    }
+}
+
+fn is_stale(path: &Path, stale_after: Duration) -> bool {
+    match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => SystemTime::now()
+            .duration_since(modified)
+            .map(|age| age > stale_after)
+            .unwrap_or(false),
+        // Файл уже удалили — можно сразу пробовать снова
+        Err(_) => true,
+    }
+}
+
+/// Испорченный файл слушатель не затирает, а откладывает как
+/// `*.recovered-<ms>`; возвращает такие файлы в порядке создания
+pub fn recovered_files(spool_path: &Path) -> Result<Vec<PathBuf>, String> {
+    let (Some(dir), Some(name)) = (spool_path.parent(), spool_path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.{}", name.to_string_lossy(), RECOVERED_PREFIX);
+
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to list data dir: {:?}", e)),
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Разбирает JSON-массив, оборванный посреди записи: возвращает все элементы,
+/// которые успели записаться целиком, и признак того, что файл был неполным
+pub fn salvage_json_array(content: &str) -> (Vec<serde_json::Value>, bool) {
+    let content = content.trim();
+    if content.is_empty() {
+        return (Vec::new(), false);
+    }
+    if let Ok(serde_json::Value::Array(values)) = serde_json::from_str(content) {
+        return (values, false);
+    }
+
+    let mut values = Vec::new();
+    let Some(mut rest) = content.strip_prefix('[') else {
+        return (values, true);
+    };
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() || rest.starts_with(']') {
+            break;
+        }
+
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<serde_json::Value>();
+        match stream.next() {
+            Some(Ok(value)) => {
+                values.push(value);
+                rest = &rest[stream.byte_offset()..];
+            }
+            _ => break,
+        }
+    }
+
+    (values, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn salvages_truncated_array() {
+        let (values, truncated) = salvage_json_array(r#"[{"a":1}, {"b":"x"},{"c":"#);
+        assert!(truncated);
+        assert_eq!(values.len(), 2);
+
+        assert_eq!(salvage_json_array(r#"[{"a":1}]"#), (vec![serde_json::json!({"a":1})], false));
+        assert_eq!(salvage_json_array("garbage"), (Vec::new(), true));
+    }
+
+    #[test]
+    fn breaks_stale_locks_only() {
+        let dir = std::env::temp_dir().join(format!("hpio-spool-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let spool = dir.join("pending_notifications.json");
+
+        let held = SpoolLock::acquire(&spool).unwrap();
+        let busy = SpoolLock::acquire_with(&spool, Duration::from_millis(30), Duration::from_secs(60));
+        assert!(busy.is_err());
+
+        // Держатель «упал»: lock-файл остался, но старше порога
+        std::mem::forget(held);
+        let recovered = SpoolLock::acquire_with(&spool, Duration::from_millis(200), Duration::ZERO);
+        assert!(recovered.is_ok());
+        drop(recovered);
+        assert!(!lock_path(&spool).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
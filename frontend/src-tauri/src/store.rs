@@ -0,0 +1,1662 @@
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use crate::notifications::{NotificationExtras, PendingNotification};
+use crate::parser::ParsedPayment;
+use crate::quarantine;
+use crate::redaction;
+use crate::schema::SpoolEntry;
+use crate::spool;
+use crate::worker;
+
+pub const DATABASE_FILE: &str = "notifications.db";
+pub const PENDING_NOTIFICATIONS_FILE: &str = "pending_notifications.json";
+
+// Каждая миграция выполняется один раз, номер хранится в PRAGMA user_version
+const MIGRATIONS: &[&str] = &[r#"
+    CREATE TABLE notifications (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        package_name TEXT NOT NULL,
+        title TEXT NOT NULL,
+        text TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        notification_type TEXT,
+        received_at INTEGER NOT NULL,
+        UNIQUE (package_name, timestamp, title, text)
+    );
+    CREATE INDEX idx_notifications_package ON notifications (package_name, timestamp);
+    CREATE INDEX idx_notifications_timestamp ON notifications (timestamp);
+
+    CREATE TABLE parse_results (
+        notification_id INTEGER PRIMARY KEY REFERENCES notifications (id) ON DELETE CASCADE,
+        parsed INTEGER NOT NULL,
+        merchant_name TEXT,
+        amount REAL,
+        currency TEXT,
+        bank TEXT,
+        card_mask TEXT,
+        parsed_at INTEGER NOT NULL
+    );
+
+    CREATE TABLE processing_state (
+        notification_id INTEGER PRIMARY KEY REFERENCES notifications (id) ON DELETE CASCADE,
+        status TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_processing_state_status ON processing_state (status);
+"#, r#"
+    ALTER TABLE notifications ADD COLUMN uid TEXT;
+    ALTER TABLE notifications ADD COLUMN notification_key TEXT;
+    CREATE UNIQUE INDEX idx_notifications_uid ON notifications (uid);
+
+    ALTER TABLE processing_state ADD COLUMN acknowledged_at INTEGER;
+    ALTER TABLE processing_state ADD COLUMN payment_id TEXT;
+"#, r#"
+    ALTER TABLE processing_state ADD COLUMN duplicate_of INTEGER REFERENCES notifications (id) ON DELETE SET NULL;
+    ALTER TABLE processing_state ADD COLUMN duplicate_reason TEXT;
+    ALTER TABLE processing_state ADD COLUMN not_duplicate INTEGER NOT NULL DEFAULT 0;
+    CREATE INDEX idx_parse_results_amount ON parse_results (amount);
+
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+"#, r#"
+    CREATE TABLE merchant_rules (
+        id TEXT PRIMARY KEY,
+        category_id TEXT NOT NULL,
+        category_name TEXT,
+        merchant_keyword TEXT NOT NULL,
+        normalized_keyword TEXT NOT NULL,
+        updated_at TEXT,
+        cached_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_merchant_rules_keyword ON merchant_rules (normalized_keyword);
+"#, r#"
+    CREATE TABLE quarantine (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        source TEXT NOT NULL,
+        payload TEXT NOT NULL UNIQUE,
+        error TEXT NOT NULL,
+        schema_version INTEGER,
+        quarantined_at INTEGER NOT NULL
+    );
+"#, r#"
+    ALTER TABLE notifications ADD COLUMN post_time INTEGER;
+    ALTER TABLE notifications ADD COLUMN channel_id TEXT;
+    ALTER TABLE notifications ADD COLUMN group_key TEXT;
+    ALTER TABLE notifications ADD COLUMN conversation_title TEXT;
+    ALTER TABLE notifications ADD COLUMN big_text TEXT;
+    ALTER TABLE notifications ADD COLUMN sub_text TEXT;
+    ALTER TABLE notifications ADD COLUMN text_lines TEXT;
+    ALTER TABLE notifications ADD COLUMN category TEXT;
+    CREATE INDEX idx_notifications_group ON notifications (package_name, group_key);
+"#, r#"
+    ALTER TABLE notifications ADD COLUMN parent_id INTEGER REFERENCES notifications (id) ON DELETE CASCADE;
+    CREATE INDEX idx_notifications_parent ON notifications (parent_id);
+"#, r#"
+    ALTER TABLE processing_state ADD COLUMN outcome TEXT;
+    ALTER TABLE processing_state ADD COLUMN archived_at INTEGER;
+    UPDATE processing_state
+    SET outcome = CASE
+            WHEN status = 'duplicate' THEN 'duplicate'
+            WHEN payment_id IS NOT NULL THEN 'imported'
+            ELSE 'dismissed'
+        END,
+        archived_at = updated_at
+    WHERE status IN ('processed', 'duplicate');
+    CREATE INDEX idx_processing_state_archived ON processing_state (archived_at);
+"#, r#"
+    ALTER TABLE notifications ADD COLUMN redacted INTEGER NOT NULL DEFAULT 0;
+"#, r#"
+    -- Операции сгруппированного уведомления различаются по uid ({id}:{строка}),
+    -- а UNIQUE (package_name, timestamp, title, text) остаётся только для
+    -- уведомлений верхнего уровня. Ограничение таблицы не удалить без пересборки
+    CREATE TABLE notifications_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        package_name TEXT NOT NULL,
+        title TEXT NOT NULL,
+        text TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        notification_type TEXT,
+        received_at INTEGER NOT NULL,
+        uid TEXT,
+        notification_key TEXT,
+        post_time INTEGER,
+        channel_id TEXT,
+        group_key TEXT,
+        conversation_title TEXT,
+        big_text TEXT,
+        sub_text TEXT,
+        text_lines TEXT,
+        category TEXT,
+        parent_id INTEGER REFERENCES notifications (id) ON DELETE CASCADE,
+        redacted INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT INTO notifications_new
+        (id, package_name, title, text, timestamp, notification_type, received_at, uid, notification_key,
+         post_time, channel_id, group_key, conversation_title, big_text, sub_text, text_lines, category,
+         parent_id, redacted)
+    SELECT id, package_name, title, text, timestamp, notification_type, received_at, uid, notification_key,
+           post_time, channel_id, group_key, conversation_title, big_text, sub_text, text_lines, category,
+           parent_id, redacted
+    FROM notifications;
+    UPDATE sqlite_sequence
+    SET seq = (SELECT seq FROM sqlite_sequence WHERE name = 'notifications')
+    WHERE name = 'notifications_new';
+
+    DROP TABLE notifications;
+    ALTER TABLE notifications_new RENAME TO notifications;
+
+    CREATE UNIQUE INDEX idx_notifications_content ON notifications (package_name, timestamp, title, text)
+        WHERE parent_id IS NULL;
+    CREATE UNIQUE INDEX idx_notifications_uid ON notifications (uid);
+    CREATE INDEX idx_notifications_package ON notifications (package_name, timestamp);
+    CREATE INDEX idx_notifications_timestamp ON notifications (timestamp);
+    CREATE INDEX idx_notifications_group ON notifications (package_name, group_key);
+    CREATE INDEX idx_notifications_parent ON notifications (parent_id);
+
+    -- Раньше операции получали сдвиг в миллисекунды, чтобы пройти ограничение
+    UPDATE notifications
+    SET timestamp = (SELECT parent.timestamp FROM notifications parent WHERE parent.id = notifications.parent_id)
+    WHERE parent_id IS NOT NULL;
+"#, r#"
+    ALTER TABLE merchant_rules ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+"#, r#"
+    ALTER TABLE quarantine ADD COLUMN redacted INTEGER NOT NULL DEFAULT 0;
+"#];
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_PROCESSED: &str = "processed";
+pub const STATUS_DUPLICATE: &str = "duplicate";
+/// Сгруппированное уведомление, разделённое на операции (см. `insert_group`)
+pub const STATUS_GROUPED: &str = "grouped";
+
+// Чем закончилась обработка уведомления, попавшего в архив
+pub const OUTCOME_IMPORTED: &str = "imported";
+pub const OUTCOME_DISMISSED: &str = "dismissed";
+pub const OUTCOME_DUPLICATE: &str = "duplicate";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredNotification {
+    pub row_id: i64,
+    pub id: String,
+    pub package_name: String,
+    pub title: String,
+    pub text: String,
+    pub timestamp: i64,
+    pub notification_type: Option<String>,
+    pub notification_key: Option<String>,
+    #[serde(flatten)]
+    pub extras: NotificationExtras,
+    pub received_at: i64,
+    pub status: String,
+    pub acknowledged_at: Option<i64>,
+    pub payment_id: Option<String>,
+    pub parsed: Option<ParsedPayment>,
+    pub duplicate_of: Option<String>,
+    pub duplicate_reason: Option<String>,
+    /// Id сгруппированного уведомления, из которого выделена эта операция
+    pub parent_id: Option<String>,
+    pub outcome: Option<String>,
+    pub archived_at: Option<i64>,
+}
+
+impl StoredNotification {
+    pub fn to_pending(&self) -> PendingNotification {
+        PendingNotification {
+            id: self.id.clone(),
+            package_name: self.package_name.clone(),
+            title: self.title.clone(),
+            text: self.text.clone(),
+            timestamp: self.timestamp,
+            notification_type: self.notification_type.clone(),
+            notification_key: self.notification_key.clone(),
+            extras: self.extras.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct NotificationQuery {
+    pub status: Option<String>,
+    pub package_name: Option<String>,
+    pub from_timestamp: Option<i64>,
+    pub to_timestamp: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// Разобранные и все уведомления одного приложения за период
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseStats {
+    pub package_name: String,
+    /// Банк из разобранных уведомлений, если они были
+    pub bank: Option<String>,
+    pub parsed: u32,
+    pub total: u32,
+}
+
+/// Что изменилось в хранилище; передаётся подписчику из `set_change_listener`
+pub enum StoreChange<'a> {
+    Inserted(&'a [StoredNotification]),
+    StatusChanged,
+}
+
+pub type ChangeListener = Box<dyn Fn(&NotificationStore, StoreChange<'_>) + Send + Sync>;
+
+/// Хранилище уведомлений на стороне Rust. Слушатель Android по-прежнему пишет
+/// в `pending_notifications.json`, но это только спул: при старте и перед
+/// каждым чтением он переносится в SQLite
+#[derive(Clone)]
+pub struct NotificationStore {
+    conn: Arc<Mutex<Connection>>,
+    spool_path: PathBuf,
+    drain_lock: Arc<Mutex<()>>,
+    listener: Arc<OnceLock<ChangeListener>>,
+}
+
+impl NotificationStore {
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create data dir: {:?}", e))?;
+
+        let conn = Connection::open(data_dir.join(DATABASE_FILE))
+            .map_err(|e| format!("Failed to open notification database: {:?}", e))?;
+
+        Self::with_connection(conn, data_dir.join(PENDING_NOTIFICATIONS_FILE))
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory(spool_path: PathBuf) -> Result<Self, String> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| format!("Failed to open in-memory database: {:?}", e))?;
+
+        Self::with_connection(conn, spool_path)
+    }
+
+    fn with_connection(mut conn: Connection, spool_path: PathBuf) -> Result<Self, String> {
+        conn.execute_batch("PRAGMA journal_mode = WAL;")
+            .map_err(|e| format!("Failed to configure database: {:?}", e))?;
+        migrate(&mut conn)?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| format!("Failed to configure database: {:?}", e))?;
+        backfill_ids(&conn)?;
+        redact_existing(&mut conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            spool_path,
+            drain_lock: Arc::new(Mutex::new(())),
+            listener: Arc::new(OnceLock::new()),
+        })
+    }
+
+    pub fn spool_path(&self) -> &Path {
+        &self.spool_path
+    }
+
+    /// Единственный подписчик на вставки и смену статусов. Вызывается после
+    /// того, как блокировка базы снята
+    pub fn set_change_listener(&self, listener: ChangeListener) -> Result<(), String> {
+        self.listener
+            .set(listener)
+            .map_err(|_| "Notification store listener is already set".to_string())
+    }
+
+    fn notify(&self, change: StoreChange<'_>) {
+        if let Some(listener) = self.listener.get() {
+            listener(self, change);
+        }
+    }
+
+    pub(crate) fn connection(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        self.conn
+            .lock()
+            .map_err(|_| "Notification store lock poisoned".to_string())
+    }
+
+    /// Переносит в базу всё, что слушатель записал в спул. Файл
+    /// переименовывается под общей блокировкой (см. `spool`), поэтому
+    /// уведомление, пришедшее во время переноса, попадает в новый файл,
+    /// а не перезаписывается
+    pub fn drain_spool(&self) -> Result<Vec<StoredNotification>, String> {
+        // Слив из watcher и из команд не должен идти параллельно
+        let _draining = self.drain_lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Остаток от прерванного слива обрабатываем первым
+        let mut inserted = self.import_spool_files()?;
+        if self.claim_spool()? {
+            inserted.extend(self.import_spool_files()?);
+        }
+        Ok(inserted)
+    }
+
+    /// Забирает файл слушателя под блокировкой; `true`, если было что забрать
+    fn claim_spool(&self) -> Result<bool, String> {
+        let temp_path = spool::temp_path(&self.spool_path);
+        if !self.spool_path.exists() && !temp_path.exists() {
+            return Ok(false);
+        }
+
+        let _lock = spool::SpoolLock::acquire(&self.spool_path)?;
+        // Временный файл под блокировкой — след записи, прерванной до rename
+        if temp_path.exists() {
+            fs::rename(&temp_path, spool::recovered_path(&self.spool_path))
+                .map_err(|e| format!("Failed to move interrupted notifications file: {:?}", e))?;
+        }
+        if self.spool_path.exists() {
+            fs::rename(&self.spool_path, spool::draining_path(&self.spool_path))
+                .map_err(|e| format!("Failed to move notifications file: {:?}", e))?;
+        }
+        Ok(true)
+    }
+
+    fn import_spool_files(&self) -> Result<Vec<StoredNotification>, String> {
+        let draining_path = spool::draining_path(&self.spool_path);
+        let mut paths = spool::recovered_files(&self.spool_path)?;
+        if draining_path.exists() {
+            paths.insert(0, draining_path);
+        }
+
+        let mut inserted = Vec::new();
+        for path in paths {
+            let content = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read notifications file: {:?}", e))?;
+
+            let source = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (values, damaged) = spool::salvage_json_array(&content);
+            if damaged {
+                // Исходный текст сохраняем целиком: хвост после обрыва иначе потеряется
+                let error = format!("Damaged file, recovered {} entries", values.len());
+                quarantine::add(self, &source, &[(serde_json::Value::String(content), error)])?;
+            }
+            inserted.extend(self.import_values(&values, &source)?);
+
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove drained notifications file: {:?}", e))?;
+        }
+
+        Ok(inserted)
+    }
+
+    /// Импортирует JSON-массив в формате слушателя. Уже сохранённые записи
+    /// пропускаются, так что повторный импорт того же файла безопасен
+    #[cfg(test)]
+    pub fn import_json(&self, content: &str) -> Result<Vec<StoredNotification>, String> {
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let json_array: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse JSON: {:?}", e))?;
+
+        match json_array.as_array() {
+            Some(values) => self.import_values(values, "import"),
+            None => {
+                let error = "Notifications file is not a JSON array".to_string();
+                quarantine::add(self, "import", &[(json_array, error)])?;
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    /// Записи, не прошедшие проверку схемы, уходят в карантин, а не теряются
+    fn import_values(
+        &self,
+        values: &[serde_json::Value],
+        source: &str,
+    ) -> Result<Vec<StoredNotification>, String> {
+        let mut notifications = Vec::with_capacity(values.len());
+        let mut rejected = Vec::new();
+        for value in values {
+            match SpoolEntry::parse(value) {
+                Ok(entry) => notifications.push(entry.into_pending()),
+                Err(error) => rejected.push((value.clone(), error)),
+            }
+        }
+
+        if !rejected.is_empty() {
+            quarantine::add(self, source, &rejected)?;
+        }
+        self.insert_all(&notifications)
+    }
+
+    pub fn insert_all(
+        &self,
+        notifications: &[PendingNotification],
+    ) -> Result<Vec<StoredNotification>, String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {:?}", e))?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let mut inserted_ids = Vec::new();
+        for notification in notifications {
+            // Коды, номера карт и телефонов маскируются до записи в базу и до расчёта id
+            let notification = redaction::redact_notification(notification);
+            let uid = notification.compute_id();
+            if let Some(id) = insert_notification(&tx, &uid, &notification, notification.timestamp, None, now)? {
+                inserted_ids.push(id);
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit notifications: {:?}", e))?;
+        drop(conn);
+
+        let inserted = inserted_ids
+            .into_iter()
+            .filter_map(|id| self.get(id).transpose())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !inserted.is_empty() {
+            self.notify(StoreChange::Inserted(&inserted));
+        }
+
+        Ok(inserted)
+    }
+
+    /// Сохраняет операции сгруппированного уведомления отдельными строками
+    /// со ссылкой на него, а само уведомление убирает из очереди. Операции
+    /// ожидают обработки и очищаются независимо друг от друга. Пустой
+    /// список, если уведомление уже не в очереди
+    pub fn insert_group(
+        &self,
+        parent_id: i64,
+        items: &[PendingNotification],
+    ) -> Result<Vec<StoredNotification>, String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {:?}", e))?;
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let grouped = tx
+            .execute(
+                "UPDATE processing_state SET status = ?1, updated_at = ?2
+                 WHERE notification_id = ?3 AND status = ?4",
+                params![STATUS_GROUPED, now, parent_id, STATUS_PENDING],
+            )
+            .map_err(|e| format!("Failed to update processing state: {:?}", e))?;
+        if grouped == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut inserted_ids = Vec::new();
+        for item in items {
+            // Одинаковые строки в группе — разные покупки: их различает uid
+            if let Some(id) = insert_notification(&tx, &item.id, item, item.timestamp, Some(parent_id), now)? {
+                inserted_ids.push(id);
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit grouped notification: {:?}", e))?;
+        drop(conn);
+        self.notify(StoreChange::StatusChanged);
+
+        inserted_ids
+            .into_iter()
+            .filter_map(|id| self.get(id).transpose())
+            .collect()
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<StoredNotification>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            &format!("{} WHERE n.id = ?1", SELECT_STORED),
+            params![id],
+            stored_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load notification: {:?}", e))
+    }
+
+    pub fn find_by_id(&self, id: &str) -> Result<Option<StoredNotification>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            &format!("{} WHERE n.uid = ?1", SELECT_STORED),
+            params![id],
+            stored_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load notification: {:?}", e))
+    }
+
+    pub fn query(&self, query: &NotificationQuery) -> Result<Vec<StoredNotification>, String> {
+        let mut sql = format!("{} WHERE 1 = 1", SELECT_STORED);
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+        if let Some(status) = &query.status {
+            values.push(status.clone().into());
+            sql.push_str(&format!(" AND s.status = ?{}", values.len()));
+        }
+        if let Some(package_name) = &query.package_name {
+            values.push(package_name.clone().into());
+            sql.push_str(&format!(" AND n.package_name = ?{}", values.len()));
+        }
+        if let Some(from) = query.from_timestamp {
+            values.push(from.into());
+            sql.push_str(&format!(" AND n.timestamp >= ?{}", values.len()));
+        }
+        if let Some(to) = query.to_timestamp {
+            values.push(to.into());
+            sql.push_str(&format!(" AND n.timestamp <= ?{}", values.len()));
+        }
+        sql.push_str(" ORDER BY n.timestamp ASC, n.id ASC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| format!("Failed to prepare query: {:?}", e))?;
+        let rows = stmt
+            .query_map(params_from_iter(values), stored_from_row)
+            .map_err(|e| format!("Failed to query notifications: {:?}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read notification row: {:?}", e))
+    }
+
+    pub fn pending(&self) -> Result<Vec<StoredNotification>, String> {
+        self.query(&NotificationQuery {
+            status: Some(STATUS_PENDING.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[cfg(test)]
+    pub fn set_status(&self, ids: &[i64], status: &str) -> Result<usize, String> {
+        let conn = self.connection()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut updated = 0;
+        for id in ids {
+            updated += conn
+                .execute(
+                    "UPDATE processing_state SET status = ?1, updated_at = ?2 WHERE notification_id = ?3",
+                    params![status, now, id],
+                )
+                .map_err(|e| format!("Failed to update processing state: {:?}", e))?;
+        }
+        drop(conn);
+
+        if updated > 0 {
+            self.notify(StoreChange::StatusChanged);
+        }
+        Ok(updated)
+    }
+
+    pub fn acknowledge(&self, ids: &[i64]) -> Result<usize, String> {
+        let conn = self.connection()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut updated = 0;
+        for id in ids {
+            updated += conn
+                .execute(
+                    "UPDATE processing_state SET acknowledged_at = ?1
+                     WHERE notification_id = ?2 AND acknowledged_at IS NULL",
+                    params![now, id],
+                )
+                .map_err(|e| format!("Failed to acknowledge notification: {:?}", e))?;
+        }
+        Ok(updated)
+    }
+
+    /// Убирает обработанные уведомления из очереди в архив: со связанным
+    /// платежом — как импортированные, без него — как отклонённые
+    pub fn archive(&self, ids: &[i64]) -> Result<usize, String> {
+        let conn = self.connection()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut updated = 0;
+        for id in ids {
+            updated += conn
+                .execute(
+                    "UPDATE processing_state
+                     SET status = ?1, updated_at = ?2, archived_at = ?2,
+                         outcome = CASE WHEN payment_id IS NULL THEN ?3 ELSE ?4 END
+                     WHERE notification_id = ?5 AND status = ?6",
+                    params![STATUS_PROCESSED, now, OUTCOME_DISMISSED, OUTCOME_IMPORTED, id, STATUS_PENDING],
+                )
+                .map_err(|e| format!("Failed to archive notification: {:?}", e))?;
+        }
+        drop(conn);
+
+        if updated > 0 {
+            self.notify(StoreChange::StatusChanged);
+        }
+        Ok(updated)
+    }
+
+    /// Возвращает отклонённое уведомление в очередь
+    pub fn restore_dismissed(&self, id: i64) -> Result<bool, String> {
+        let conn = self.connection()?;
+        let updated = conn
+            .execute(
+                "UPDATE processing_state
+                 SET status = ?1, outcome = NULL, archived_at = NULL, updated_at = ?2
+                 WHERE notification_id = ?3 AND outcome = ?4",
+                params![STATUS_PENDING, chrono::Utc::now().timestamp_millis(), id, OUTCOME_DISMISSED],
+            )
+            .map_err(|e| format!("Failed to restore notification: {:?}", e))?;
+        drop(conn);
+
+        if updated > 0 {
+            self.notify(StoreChange::StatusChanged);
+        }
+        Ok(updated > 0)
+    }
+
+    /// Последнее уведомление, которое не разобрало ни одно правило
+    pub fn last_parse_failure(&self) -> Result<Option<StoredNotification>, String> {
+        let conn = self.connection()?;
+        conn.query_row(
+            &format!("{} WHERE p.parsed = 0 ORDER BY p.parsed_at DESC, n.id DESC LIMIT 1", SELECT_STORED),
+            [],
+            stored_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load last parse failure: {:?}", e))
+    }
+
+    /// Итоги разбора по приложениям для уведомлений с `timestamp` в [from, to).
+    /// Сгруппированные уведомления не считаются, их операции — считаются
+    pub fn parse_stats(&self, from: i64, to: i64) -> Result<Vec<ParseStats>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT n.package_name, MAX(p.bank), SUM(p.parsed), COUNT(*)
+                 FROM notifications n
+                 JOIN parse_results p ON p.notification_id = n.id
+                 JOIN processing_state s ON s.notification_id = n.id
+                 WHERE n.timestamp >= ?1 AND n.timestamp < ?2 AND s.status != ?3
+                 GROUP BY n.package_name
+                 ORDER BY n.package_name",
+            )
+            .map_err(|e| format!("Failed to prepare parse stats query: {:?}", e))?;
+        let rows = stmt
+            .query_map(params![from, to, STATUS_GROUPED], |row| {
+                Ok(ParseStats {
+                    package_name: row.get(0)?,
+                    bank: row.get(1)?,
+                    parsed: row.get(2)?,
+                    total: row.get(3)?,
+                })
+            })
+            .map_err(|e| format!("Failed to query parse stats: {:?}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read parse stats row: {:?}", e))
+    }
+
+    /// Неразобранные уведомления приложения начиная с `from`, свежие первыми
+    pub fn parse_failures(&self, package_name: &str, from: i64, limit: u32) -> Result<Vec<StoredNotification>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE p.parsed = 0 AND n.package_name = ?1 AND n.timestamp >= ?2 AND s.status != ?3
+                 ORDER BY n.timestamp DESC, n.id DESC LIMIT ?4",
+                SELECT_STORED
+            ))
+            .map_err(|e| format!("Failed to prepare parse failures query: {:?}", e))?;
+        let rows = stmt
+            .query_map(params![package_name, from, STATUS_GROUPED, limit], stored_from_row)
+            .map_err(|e| format!("Failed to query parse failures: {:?}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read notification row: {:?}", e))
+    }
+
+    /// Архив, свежие записи первыми
+    pub fn archived(
+        &self,
+        outcome: Option<&str>,
+        limit: Option<u32>,
+        offset: Option<u32>,
+    ) -> Result<Vec<StoredNotification>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE s.archived_at IS NOT NULL AND (?1 IS NULL OR s.outcome = ?1)
+                 ORDER BY s.archived_at DESC, n.id DESC LIMIT ?2 OFFSET ?3",
+                SELECT_STORED
+            ))
+            .map_err(|e| format!("Failed to prepare archive query: {:?}", e))?;
+        let rows = stmt
+            .query_map(
+                params![outcome, limit.map(i64::from).unwrap_or(-1), offset.unwrap_or(0)],
+                stored_from_row,
+            )
+            .map_err(|e| format!("Failed to query archive: {:?}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read notification row: {:?}", e))
+    }
+
+    /// Удаляет из архива записи старше `archived_before` и сверх `keep`
+    /// самых свежих, а затем сгруппированные уведомления без операций
+    pub fn prune_archive(&self, archived_before: Option<i64>, keep: Option<u32>) -> Result<usize, String> {
+        let mut conn = self.connection()?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {:?}", e))?;
+
+        let mut removed = 0;
+        if let Some(archived_before) = archived_before {
+            removed += tx
+                .execute(
+                    "DELETE FROM notifications WHERE id IN (
+                         SELECT notification_id FROM processing_state
+                         WHERE archived_at IS NOT NULL AND archived_at < ?1)",
+                    params![archived_before],
+                )
+                .map_err(|e| format!("Failed to prune archive: {:?}", e))?;
+        }
+        if let Some(keep) = keep {
+            removed += tx
+                .execute(
+                    "DELETE FROM notifications WHERE id IN (
+                         SELECT notification_id FROM processing_state
+                         WHERE archived_at IS NOT NULL
+                         ORDER BY archived_at DESC, notification_id DESC LIMIT -1 OFFSET ?1)",
+                    params![keep],
+                )
+                .map_err(|e| format!("Failed to prune archive: {:?}", e))?;
+        }
+        tx.execute(
+            "DELETE FROM notifications WHERE id IN (
+                 SELECT s.notification_id FROM processing_state s
+                 WHERE s.status = ?1
+                   AND NOT EXISTS (SELECT 1 FROM notifications c WHERE c.parent_id = s.notification_id))",
+            params![STATUS_GROUPED],
+        )
+        .map_err(|e| format!("Failed to prune grouped notifications: {:?}", e))?;
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit archive pruning: {:?}", e))?;
+        Ok(removed)
+    }
+
+    pub fn link_payment(&self, id: i64, payment_id: &str) -> Result<(), String> {
+        let conn = self.connection()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute(
+            "UPDATE processing_state
+             SET payment_id = ?1, status = ?2, outcome = ?3, updated_at = ?4, archived_at = COALESCE(archived_at, ?4)
+             WHERE notification_id = ?5",
+            params![payment_id, STATUS_PROCESSED, OUTCOME_IMPORTED, now, id],
+        )
+        .map_err(|e| format!("Failed to link payment: {:?}", e))?;
+        drop(conn);
+
+        self.notify(StoreChange::StatusChanged);
+        Ok(())
+    }
+
+    pub fn record_parse_result(
+        &self,
+        notification_id: i64,
+        parsed: Option<&ParsedPayment>,
+    ) -> Result<(), String> {
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO parse_results
+                (notification_id, parsed, merchant_name, amount, currency, bank, card_mask, parsed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                notification_id,
+                parsed.is_some(),
+                parsed.map(|p| p.merchant_name.as_str()),
+                parsed.map(|p| p.amount),
+                parsed.map(|p| p.currency.as_str()),
+                parsed.map(|p| p.bank.as_str()),
+                parsed.and_then(|p| p.card_mask.as_deref()),
+                chrono::Utc::now().timestamp_millis(),
+            ],
+        )
+        .map_err(|e| format!("Failed to save parse result: {:?}", e))?;
+        Ok(())
+    }
+
+    /// Более ранние уведомления, сами не повторы, с той же суммой и валютой
+    /// и временем в пределах `window_ms`
+    pub fn duplicate_candidates(
+        &self,
+        notification_id: i64,
+        parsed: &ParsedPayment,
+        timestamp: i64,
+        window_ms: i64,
+    ) -> Result<Vec<StoredNotification>, String> {
+        let conn = self.connection()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "{} WHERE n.id < ?1 AND s.status != ?2 AND p.parsed = 1
+                   AND p.amount BETWEEN ?3 - 0.005 AND ?3 + 0.005 AND p.currency = ?4
+                   AND n.timestamp BETWEEN ?5 AND ?6
+                 ORDER BY n.id ASC",
+                SELECT_STORED
+            ))
+            .map_err(|e| format!("Failed to prepare duplicate query: {:?}", e))?;
+        let rows = stmt
+            .query_map(
+                params![
+                    notification_id,
+                    STATUS_DUPLICATE,
+                    parsed.amount,
+                    parsed.currency,
+                    timestamp - window_ms,
+                    timestamp + window_ms,
+                ],
+                stored_from_row,
+            )
+            .map_err(|e| format!("Failed to query duplicates: {:?}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read notification row: {:?}", e))
+    }
+
+    /// Помечает ожидающее уведомление повтором `original_id`; строка остаётся
+    /// для проверки. false, если уведомление уже не ожидает или пользователь
+    /// раньше его восстановил
+    pub fn mark_duplicate(&self, id: i64, original_id: i64, reason: &str) -> Result<bool, String> {
+        let conn = self.connection()?;
+        let updated = conn
+            .execute(
+                "UPDATE processing_state
+                 SET status = ?1, duplicate_of = ?2, duplicate_reason = ?3, updated_at = ?4,
+                     outcome = ?7, archived_at = ?4
+                 WHERE notification_id = ?5 AND status = ?6 AND not_duplicate = 0",
+                params![
+                    STATUS_DUPLICATE,
+                    original_id,
+                    reason,
+                    chrono::Utc::now().timestamp_millis(),
+                    id,
+                    STATUS_PENDING,
+                    OUTCOME_DUPLICATE,
+                ],
+            )
+            .map_err(|e| format!("Failed to mark duplicate: {:?}", e))?;
+        drop(conn);
+
+        if updated > 0 {
+            self.notify(StoreChange::StatusChanged);
+        }
+        Ok(updated > 0)
+    }
+
+    /// Возвращает повтор в очередь; повторно дедупликация его не отметит
+    pub fn restore_duplicate(&self, id: i64) -> Result<bool, String> {
+        let conn = self.connection()?;
+        let updated = conn
+            .execute(
+                "UPDATE processing_state
+                 SET status = ?1, duplicate_of = NULL, duplicate_reason = NULL, not_duplicate = 1, updated_at = ?2,
+                     outcome = NULL, archived_at = NULL
+                 WHERE notification_id = ?3 AND status = ?4",
+                params![STATUS_PENDING, chrono::Utc::now().timestamp_millis(), id, STATUS_DUPLICATE],
+            )
+            .map_err(|e| format!("Failed to restore duplicate: {:?}", e))?;
+        drop(conn);
+
+        if updated > 0 {
+            self.notify(StoreChange::StatusChanged);
+        }
+        Ok(updated > 0)
+    }
+
+    pub fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        let conn = self.connection()?;
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read setting {}: {:?}", key, e))?;
+
+        value
+            .map(|value| {
+                serde_json::from_str(&value)
+                    .map_err(|e| format!("Failed to parse setting {}: {:?}", key, e))
+            })
+            .transpose()
+    }
+
+    pub fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        let value = serde_json::to_string(value)
+            .map_err(|e| format!("Failed to serialize setting {}: {:?}", key, e))?;
+        let conn = self.connection()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to save setting {}: {:?}", key, e))?;
+        Ok(())
+    }
+}
+
+/// Новая строка `notifications` со статусом pending; `None`, если такая уже есть
+fn insert_notification(
+    tx: &Transaction<'_>,
+    uid: &str,
+    notification: &PendingNotification,
+    timestamp: i64,
+    parent_id: Option<i64>,
+    now: i64,
+) -> Result<Option<i64>, String> {
+    let extras = &notification.extras;
+    let changed = tx
+        .execute(
+            "INSERT OR IGNORE INTO notifications
+                (uid, package_name, title, text, timestamp, notification_type, notification_key, received_at,
+                 post_time, channel_id, group_key, conversation_title, big_text, sub_text, text_lines, category,
+                 parent_id, redacted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, 1)",
+            params![
+                uid,
+                notification.package_name,
+                notification.title,
+                notification.text,
+                timestamp,
+                notification.notification_type,
+                notification.notification_key,
+                now,
+                extras.post_time,
+                extras.channel_id,
+                extras.group_key,
+                extras.conversation_title,
+                extras.big_text,
+                extras.sub_text,
+                text_lines_to_json(&extras.text_lines)?,
+                extras.category,
+                parent_id,
+            ],
+        )
+        .map_err(|e| format!("Failed to insert notification: {:?}", e))?;
+
+    if changed == 0 {
+        return Ok(None);
+    }
+
+    let id = tx.last_insert_rowid();
+    tx.execute(
+        "INSERT INTO processing_state (notification_id, status, updated_at) VALUES (?1, ?2, ?3)",
+        params![id, STATUS_PENDING, now],
+    )
+    .map_err(|e| format!("Failed to insert processing state: {:?}", e))?;
+    Ok(Some(id))
+}
+
+const SELECT_STORED: &str = "
+    SELECT n.id, n.uid, n.package_name, n.title, n.text, n.timestamp, n.notification_type,
+           n.notification_key, n.received_at, s.status, s.acknowledged_at, s.payment_id,
+           p.parsed, p.merchant_name, p.amount, p.currency, p.bank, p.card_mask,
+           (SELECT d.uid FROM notifications d WHERE d.id = s.duplicate_of), s.duplicate_reason,
+           n.post_time, n.channel_id, n.group_key, n.conversation_title, n.big_text, n.sub_text,
+           n.text_lines, n.category, (SELECT g.uid FROM notifications g WHERE g.id = n.parent_id),
+           s.outcome, s.archived_at
+    FROM notifications n
+    JOIN processing_state s ON s.notification_id = n.id
+    LEFT JOIN parse_results p ON p.notification_id = n.id";
+
+fn stored_from_row(row: &Row<'_>) -> rusqlite::Result<StoredNotification> {
+    let parsed = match row.get::<_, Option<bool>>(12)? {
+        Some(true) => Some(ParsedPayment {
+            merchant_name: row.get(13)?,
+            amount: row.get(14)?,
+            currency: row.get(15)?,
+            bank: row.get(16)?,
+            card_mask: row.get(17)?,
+        }),
+        _ => None,
+    };
+
+    Ok(StoredNotification {
+        row_id: row.get(0)?,
+        id: row.get(1)?,
+        package_name: row.get(2)?,
+        title: row.get(3)?,
+        text: row.get(4)?,
+        timestamp: row.get(5)?,
+        notification_type: row.get(6)?,
+        notification_key: row.get(7)?,
+        extras: NotificationExtras {
+            post_time: row.get(20)?,
+            channel_id: row.get(21)?,
+            group_key: row.get(22)?,
+            conversation_title: row.get(23)?,
+            big_text: row.get(24)?,
+            sub_text: row.get(25)?,
+            text_lines: text_lines_from_json(row.get(26)?),
+            category: row.get(27)?,
+        },
+        received_at: row.get(8)?,
+        status: row.get(9)?,
+        acknowledged_at: row.get(10)?,
+        payment_id: row.get(11)?,
+        parsed,
+        duplicate_of: row.get(18)?,
+        duplicate_reason: row.get(19)?,
+        parent_id: row.get(28)?,
+        outcome: row.get(29)?,
+        archived_at: row.get(30)?,
+    })
+}
+
+/// Строки InboxStyle хранятся JSON-массивом, пустой список — NULL
+fn text_lines_to_json(lines: &[String]) -> Result<Option<String>, String> {
+    if lines.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(lines)
+        .map(Some)
+        .map_err(|e| format!("Failed to serialize text lines: {:?}", e))
+}
+
+fn text_lines_from_json(value: Option<String>) -> Vec<String> {
+    value
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read schema version: {:?}", e))?;
+    // Внешние ключи на время миграций выключены: пересборка таблицы иначе
+    // каскадом удалила бы состояние обработки. Включает их `with_connection`
+    conn.execute_batch("PRAGMA foreign_keys = OFF;")
+        .map_err(|e| format!("Failed to configure database: {:?}", e))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start migration: {:?}", e))?;
+        tx.execute_batch(migration)
+            .map_err(|e| format!("Failed to apply migration {}: {:?}", index + 1, e))?;
+        let broken: Option<String> = tx
+            .query_row("PRAGMA foreign_key_check", [], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to check foreign keys: {:?}", e))?;
+        if let Some(table) = broken {
+            return Err(format!("Migration {} broke foreign keys in {}", index + 1, table));
+        }
+        tx.pragma_update(None, "user_version", index + 1)
+            .map_err(|e| format!("Failed to update schema version: {:?}", e))?;
+        tx.commit()
+            .map_err(|e| format!("Failed to commit migration {}: {:?}", index + 1, e))?;
+    }
+
+    Ok(())
+}
+
+/// Строки, сохранённые до появления стабильных id, получают их после миграции
+fn backfill_ids(conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, package_name, title, text, timestamp, notification_type, notification_key
+             FROM notifications WHERE uid IS NULL",
+        )
+        .map_err(|e| format!("Failed to prepare id backfill: {:?}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                PendingNotification {
+                    id: String::new(),
+                    package_name: row.get(1)?,
+                    title: row.get(2)?,
+                    text: row.get(3)?,
+                    timestamp: row.get(4)?,
+                    notification_type: row.get(5)?,
+                    notification_key: row.get(6)?,
+                    extras: NotificationExtras::default(),
+                },
+            ))
+        })
+        .map_err(|e| format!("Failed to read rows for id backfill: {:?}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read rows for id backfill: {:?}", e))?;
+
+    for (row_id, notification) in rows {
+        conn.execute(
+            "UPDATE notifications SET uid = ?1 WHERE id = ?2",
+            params![notification.compute_id(), row_id],
+        )
+        .map_err(|e| format!("Failed to backfill notification id: {:?}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Строки, сохранённые до появления маскирования, проходят его один раз.
+/// Id остаётся прежним: по нему на строку ссылается фронтенд
+fn redact_existing(conn: &mut Connection) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start redaction backfill: {:?}", e))?;
+    let mut stmt = tx
+        .prepare(
+            "SELECT id, package_name, title, text, timestamp, notification_type, notification_key,
+                    conversation_title, big_text, sub_text, text_lines
+             FROM notifications WHERE redacted = 0",
+        )
+        .map_err(|e| format!("Failed to prepare redaction backfill: {:?}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                PendingNotification {
+                    id: String::new(),
+                    package_name: row.get(1)?,
+                    title: row.get(2)?,
+                    text: row.get(3)?,
+                    timestamp: row.get(4)?,
+                    notification_type: row.get(5)?,
+                    notification_key: row.get(6)?,
+                    extras: NotificationExtras {
+                        conversation_title: row.get(7)?,
+                        big_text: row.get(8)?,
+                        sub_text: row.get(9)?,
+                        text_lines: text_lines_from_json(row.get(10)?),
+                        ..Default::default()
+                    },
+                },
+            ))
+        })
+        .map_err(|e| format!("Failed to read rows for redaction backfill: {:?}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read rows for redaction backfill: {:?}", e))?;
+    drop(stmt);
+
+    for (row_id, notification) in rows {
+        let redacted = redaction::redact_notification(&notification);
+        let extras = &redacted.extras;
+        let updated = tx
+            .execute(
+                "UPDATE OR IGNORE notifications
+                 SET title = ?1, text = ?2, notification_type = ?3, conversation_title = ?4, big_text = ?5,
+                     sub_text = ?6, text_lines = ?7, redacted = 1
+                 WHERE id = ?8",
+                params![
+                    redacted.title,
+                    redacted.text,
+                    redacted.notification_type,
+                    extras.conversation_title,
+                    extras.big_text,
+                    extras.sub_text,
+                    text_lines_to_json(&extras.text_lines)?,
+                    row_id,
+                ],
+            )
+            .map_err(|e| format!("Failed to redact stored notification: {:?}", e))?;
+        // После маскирования строка совпала с другой, то есть была её повтором
+        if updated == 0 {
+            let survivor: i64 = tx
+                .query_row(
+                    "SELECT id FROM notifications
+                     WHERE package_name = ?1 AND timestamp = ?2 AND title = ?3 AND text = ?4
+                       AND parent_id IS NULL AND id != ?5",
+                    params![redacted.package_name, redacted.timestamp, redacted.title, redacted.text, row_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| format!("Failed to find redacted duplicate: {:?}", e))?;
+            merge_redacted_duplicate(&tx, row_id, survivor)?;
+        }
+    }
+
+    redact_quarantine(&tx)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit redaction backfill: {:?}", e))
+}
+
+/// Записи карантина, сохранённые до маскирования. Запись, которая после
+/// маскирования совпала с другой, — её повтор и удаляется
+fn redact_quarantine(tx: &Transaction<'_>) -> Result<(), String> {
+    let mut stmt = tx
+        .prepare("SELECT id, payload FROM quarantine WHERE redacted = 0")
+        .map_err(|e| format!("Failed to prepare quarantine redaction: {:?}", e))?;
+    let entries = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to read quarantine for redaction: {:?}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read quarantine for redaction: {:?}", e))?;
+    drop(stmt);
+
+    for (id, payload) in entries {
+        let value = serde_json::from_str(&payload).unwrap_or(serde_json::Value::String(payload));
+        let updated = tx
+            .execute(
+                "UPDATE OR IGNORE quarantine SET payload = ?1, redacted = 1 WHERE id = ?2",
+                params![redaction::redact_json(&value).to_string(), id],
+            )
+            .map_err(|e| format!("Failed to redact quarantined payload: {:?}", e))?;
+        if updated == 0 {
+            tx.execute("DELETE FROM quarantine WHERE id = ?1", params![id])
+                .map_err(|e| format!("Failed to drop redacted quarantine duplicate: {:?}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Переносит на оставшуюся строку всё, что связано с удаляемым повтором:
+/// ссылку на платёж, результат обработки, разбор и операции группы
+fn merge_redacted_duplicate(tx: &Transaction<'_>, duplicate: i64, survivor: i64) -> Result<(), String> {
+    let state: Option<(String, Option<i64>)> = tx
+        .query_row(
+            "SELECT status, duplicate_of FROM processing_state WHERE notification_id = ?1",
+            params![duplicate],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load processing state: {:?}", e))?;
+
+    tx.execute(
+        "UPDATE processing_state
+         SET payment_id = COALESCE(payment_id, (SELECT payment_id FROM processing_state WHERE notification_id = ?2)),
+             acknowledged_at = COALESCE(acknowledged_at,
+                 (SELECT acknowledged_at FROM processing_state WHERE notification_id = ?2)),
+             not_duplicate = MAX(not_duplicate, (SELECT not_duplicate FROM processing_state WHERE notification_id = ?2))
+         WHERE notification_id = ?1",
+        params![survivor, duplicate],
+    )
+    .map_err(|e| format!("Failed to merge processing state: {:?}", e))?;
+    // Повтор, уже помеченный копией оставшейся строки, её состояние не меняет
+    let processed = matches!(
+        &state,
+        Some((status, duplicate_of)) if status != STATUS_PENDING && *duplicate_of != Some(survivor)
+    );
+    if processed {
+        tx.execute(
+            "UPDATE processing_state
+             SET (status, outcome, archived_at, duplicate_of, duplicate_reason, updated_at) =
+                 (SELECT status, outcome, archived_at, duplicate_of, duplicate_reason, updated_at
+                  FROM processing_state WHERE notification_id = ?2)
+             WHERE notification_id = ?1 AND status = ?3",
+            params![survivor, duplicate, STATUS_PENDING],
+        )
+        .map_err(|e| format!("Failed to merge processing state: {:?}", e))?;
+    }
+
+    for sql in [
+        "UPDATE OR IGNORE parse_results SET notification_id = ?1 WHERE notification_id = ?2",
+        "UPDATE notifications SET parent_id = ?1 WHERE parent_id = ?2",
+        "UPDATE processing_state SET duplicate_of = NULLIF(?1, notification_id) WHERE duplicate_of = ?2",
+    ] {
+        tx.execute(sql, params![survivor, duplicate])
+            .map_err(|e| format!("Failed to move references to redacted duplicate: {:?}", e))?;
+    }
+    tx.execute("DELETE FROM notifications WHERE id = ?1", params![duplicate])
+        .map_err(|e| format!("Failed to remove redacted duplicate: {:?}", e))?;
+    log::info!("Merged notification {} into {} after redaction", duplicate, survivor);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn query_notifications(
+    store: tauri::State<'_, NotificationStore>,
+    query: NotificationQuery,
+) -> Result<Vec<StoredNotification>, String> {
+    let store = store.inner().clone();
+    worker::run(move || {
+        store.drain_spool()?;
+        store.query(&query)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPOOL: &str = r#"[
+        {"packageName":"ru.sberbankmobile","title":"Покупка Купер","text":"150 ₽ — Баланс: 196,01 ₽","timestamp":1700000000000,"notificationType":"PAYMENT"},
+        {"packageName":"com.yandex.bank","title":"Ситикард","text":"Покупка на 35.00 RUB","timestamp":1700000100000,"notificationType":"PAYMENT"},
+        {"packageName":"com.yandex.bank","title":"broken"}
+    ]"#;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "hpio-store-{}-{}-{}",
+            name,
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn imports_json_once() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+
+        assert_eq!(store.import_json(SPOOL).unwrap().len(), 2);
+        assert_eq!(store.import_json(SPOOL).unwrap().len(), 0);
+        assert_eq!(store.pending().unwrap().len(), 2);
+
+        // Запись без text и timestamp попадает в карантин один раз
+        let quarantined = quarantine::list(&store, None).unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].payload["title"], "broken");
+        assert_eq!(quarantined[0].schema_version, Some(1));
+    }
+
+    #[test]
+    fn rebuild_keeps_grouped_transactions_at_their_real_time() {
+        let conn = Connection::open_in_memory().unwrap();
+        let rebuild = MIGRATIONS.iter().position(|migration| migration.contains("notifications_new")).unwrap();
+        for migration in &MIGRATIONS[..rebuild] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", rebuild).unwrap();
+        // Две одинаковые покупки из группы, сдвинутые на 1 мс старым кодом
+        conn.execute_batch(
+            "INSERT INTO notifications (id, uid, package_name, title, text, timestamp, received_at, parent_id, redacted)
+             VALUES (1, 'g', 'ru.ozon.app.android', 'Ozon Банк', '2 операции', 1000, 1, NULL, 1),
+                    (2, 'g:0', 'ru.ozon.app.android', 'Ozon Банк', 'Покупка в Магнит. 120.50 RUR', 1000, 1, 1, 1),
+                    (3, 'g:1', 'ru.ozon.app.android', 'Ozon Банк', 'Покупка в Магнит. 120.50 RUR', 1001, 1, 1, 1);
+             INSERT INTO processing_state (notification_id, status, updated_at, payment_id)
+             VALUES (1, 'grouped', 1, NULL), (2, 'processed', 1, 'payment-1'), (3, 'pending', 1, NULL);",
+        )
+        .unwrap();
+
+        let store = NotificationStore::with_connection(conn, PathBuf::new()).unwrap();
+        let children: Vec<(i64, String, Option<String>)> = [2, 3]
+            .into_iter()
+            .map(|id| store.get(id).unwrap().unwrap())
+            .map(|stored| (stored.timestamp, stored.status, stored.payment_id))
+            .collect();
+        assert_eq!(
+            children,
+            [
+                (1000, STATUS_PROCESSED.to_string(), Some("payment-1".to_string())),
+                (1000, STATUS_PENDING.to_string(), None),
+            ]
+        );
+        assert_eq!(store.get(3).unwrap().unwrap().parent_id.as_deref(), Some("g"));
+        // Ссылки на пересобранную таблицу работают: операции удаляются вместе с группой
+        store
+            .connection()
+            .unwrap()
+            .execute("DELETE FROM notifications WHERE id = 1", [])
+            .unwrap();
+        assert!(store.get(2).unwrap().is_none());
+        let orphaned: i64 = store
+            .connection()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM processing_state", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(orphaned, 0);
+
+        // Уведомления верхнего уровня по-прежнему не дублируются
+        assert_eq!(store.import_json(SPOOL).unwrap().len(), 2);
+        assert_eq!(store.import_json(SPOOL).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn redacts_rows_stored_before_masking() {
+        let dir = temp_dir("redaction");
+        let store = NotificationStore::open(&dir).unwrap();
+        store.import_json(SPOOL).unwrap();
+        // Так строки лежали в базе до появления маскирования
+        store
+            .connection()
+            .unwrap()
+            .execute_batch(
+                "UPDATE notifications SET text = 'Перевод на карту 2202 2061 2345 7165', redacted = 0
+                 WHERE package_name = 'com.yandex.bank';
+                 UPDATE notifications SET text = 'Код для входа: 4839', redacted = 0
+                 WHERE package_name = 'ru.sberbankmobile';",
+            )
+            .unwrap();
+        drop(store);
+
+        let store = NotificationStore::open(&dir).unwrap();
+        let stored = store.query(&NotificationQuery::default()).unwrap();
+        let texts: Vec<(&str, Option<&str>)> = stored
+            .iter()
+            .map(|s| (s.text.as_str(), s.notification_type.as_deref()))
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                (redaction::SECURITY_CODE_TEXT, Some(redaction::SECURITY_CODE_TYPE)),
+                ("Перевод на карту •••• •••• •••• 7165", Some("PAYMENT")),
+            ]
+        );
+
+        drop(store);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn merges_rows_that_collide_after_redaction() {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", MIGRATIONS.len()).unwrap();
+        // Две строки, которые после маскирования номера карты совпадают;
+        // вторая уже импортирована в платёж
+        conn.execute_batch(
+            "INSERT INTO notifications (id, uid, package_name, title, text, timestamp, received_at, redacted)
+             VALUES (1, 'a', 'com.yandex.bank', 'Покупка', 'Карта 2202 2061 2345 7165', 1000, 1, 0),
+                    (2, 'b', 'com.yandex.bank', 'Покупка', 'Карта 5536 9137 1234 7165', 1000, 1, 0);
+             INSERT INTO processing_state (notification_id, status, updated_at, payment_id, outcome, archived_at)
+             VALUES (1, 'pending', 1, NULL, NULL, NULL), (2, 'processed', 2, 'payment-1', 'imported', 2);",
+        )
+        .unwrap();
+
+        let store = NotificationStore::with_connection(conn, PathBuf::new()).unwrap();
+        let stored = store.query(&NotificationQuery::default()).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!((stored[0].id.as_str(), stored[0].status.as_str()), ("a", STATUS_PROCESSED));
+        assert_eq!(
+            (stored[0].payment_id.as_deref(), stored[0].outcome.as_deref(), stored[0].archived_at),
+            (Some("payment-1"), Some(OUTCOME_IMPORTED), Some(2))
+        );
+    }
+
+    #[test]
+    fn redacts_quarantined_payloads() {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", MIGRATIONS.len()).unwrap();
+        // Первые две записи после маскирования номера карты совпадают
+        conn.execute_batch(
+            r#"INSERT INTO quarantine (id, source, payload, error, quarantined_at)
+               VALUES (1, 'import', '{"text":"Карта 2202 2061 2345 7165"}', 'missing title', 1),
+                      (2, 'import', '{"text":"Карта 5536 9137 1234 7165"}', 'missing title', 2),
+                      (3, 'import', '{"text":"Код для входа: 4839"}', 'missing title', 3);"#,
+        )
+        .unwrap();
+
+        let store = NotificationStore::with_connection(conn, PathBuf::new()).unwrap();
+        let payloads: Vec<(i64, String)> = quarantine::list(&store, None)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.id, entry.payload["text"].as_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            payloads,
+            [(3, "Код для входа: ••••".to_string()), (1, "Карта •••• •••• •••• 7165".to_string())]
+        );
+    }
+
+    #[test]
+    fn keeps_android_extras_for_parsing() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let entry = serde_json::json!([{
+            "schemaVersion": 2,
+            "packageName": "ru.sberbankmobile",
+            "title": "СберБанк",
+            "text": "2 новых уведомления",
+            "timestamp": 1700000000000i64,
+            "postTime": 1699999999000i64,
+            "channelId": "payments",
+            "groupKey": "0|ru.sberbankmobile|g:operations",
+            "conversationTitle": "Покупка Купер",
+            "subText": " ",
+            "textLines": ["150 ₽ — Баланс: 196,01 ₽", ""],
+            "category": "msg",
+        }]);
+        store.import_json(&entry.to_string()).unwrap();
+
+        let stored = &store.pending().unwrap()[0];
+        assert_eq!(stored.extras.post_time, Some(1699999999000));
+        assert_eq!(stored.extras.group_key.as_deref(), Some("0|ru.sberbankmobile|g:operations"));
+        assert_eq!(stored.extras.sub_text, None);
+        assert_eq!(stored.extras.text_lines, vec!["150 ₽ — Баланс: 196,01 ₽"]);
+
+        // Сумма только в строке InboxStyle, продавец — в заголовке беседы
+        let payment = crate::parser::parse_notification(&stored.to_pending()).unwrap();
+        assert_eq!((payment.merchant_name.as_str(), payment.amount), ("Купер", 150.0));
+    }
+
+    #[test]
+    fn drains_spool_file() {
+        let dir = temp_dir("drain");
+        fs::write(dir.join(PENDING_NOTIFICATIONS_FILE), SPOOL).unwrap();
+
+        let store = NotificationStore::open(&dir).unwrap();
+        assert_eq!(store.drain_spool().unwrap().len(), 2);
+        assert!(!dir.join(PENDING_NOTIFICATIONS_FILE).exists());
+        assert_eq!(store.drain_spool().unwrap().len(), 0);
+
+        // Данные переживают переоткрытие базы
+        drop(store);
+        let store = NotificationStore::open(&dir).unwrap();
+        assert_eq!(store.pending().unwrap().len(), 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Тот же протокол, что в `saveNotification` (PaymentNotificationListenerService.kt)
+    fn listener_append(spool_path: &Path, entry: serde_json::Value) {
+        let _lock = spool::SpoolLock::acquire(spool_path).unwrap();
+        let mut entries: Vec<serde_json::Value> = match fs::read_to_string(spool_path) {
+            Ok(content) => serde_json::from_str(&content).unwrap(),
+            Err(_) => Vec::new(),
+        };
+        entries.push(entry);
+
+        let temp_path = spool::temp_path(spool_path);
+        fs::write(&temp_path, serde_json::to_string(&entries).unwrap()).unwrap();
+        fs::rename(&temp_path, spool_path).unwrap();
+    }
+
+    #[test]
+    fn keeps_notifications_posted_during_drain() {
+        const POSTED: usize = 200;
+        let dir = temp_dir("race");
+        let store = NotificationStore::open(&dir).unwrap();
+        let spool_path = store.spool_path().to_path_buf();
+
+        let listener = std::thread::spawn(move || {
+            for i in 0..POSTED {
+                listener_append(
+                    &spool_path,
+                    serde_json::json!({
+                        "packageName": "ru.sberbankmobile",
+                        "title": "Покупка",
+                        "text": format!("{} ₽", i + 1),
+                        "timestamp": 1700000000000i64 + i as i64,
+                        "notificationType": "PAYMENT",
+                    }),
+                );
+            }
+        });
+
+        let mut drained = 0;
+        while !listener.is_finished() {
+            drained += store.drain_spool().unwrap().len();
+        }
+        listener.join().unwrap();
+        drained += store.drain_spool().unwrap().len();
+
+        assert_eq!(drained, POSTED);
+        assert_eq!(store.pending().unwrap().len(), POSTED);
+        assert!(!spool::lock_path(store.spool_path()).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_interrupted_and_damaged_files() {
+        let dir = temp_dir("recover");
+        let store = NotificationStore::open(&dir).unwrap();
+        let spool_path = store.spool_path().to_path_buf();
+
+        // Слушатель упал между записью временного файла и rename
+        fs::write(spool::temp_path(&spool_path), SPOOL).unwrap();
+        // Испорченный файл, отложенный слушателем: второй элемент оборван
+        let damaged = &SPOOL[..SPOOL.find("\"Ситикард\"").unwrap()];
+        fs::write(spool::recovered_path(&spool_path), damaged).unwrap();
+
+        assert_eq!(store.drain_spool().unwrap().len(), 2);
+        assert!(spool::recovered_files(&spool_path).unwrap().is_empty());
+        assert!(!spool::temp_path(&spool_path).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn queries_by_status_package_and_time() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let inserted = store.import_json(SPOOL).unwrap();
+        store.set_status(&[inserted[0].row_id], STATUS_PROCESSED).unwrap();
+
+        let by_package = store
+            .query(&NotificationQuery {
+                package_name: Some("com.yandex.bank".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_package.len(), 1);
+
+        let processed = store
+            .query(&NotificationQuery {
+                status: Some(STATUS_PROCESSED.to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(processed[0].package_name, "ru.sberbankmobile");
+
+        let in_range = store
+            .query(&NotificationQuery {
+                from_timestamp: Some(1700000050000),
+                to_timestamp: Some(1700000200000),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(in_range.len(), 1);
+        assert_eq!(in_range[0].title, "Ситикард");
+    }
+
+    #[test]
+    fn ids_are_stable_across_whitespace_and_unicode_form() {
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        let inserted = store.import_json(SPOOL).unwrap();
+
+        let mut reformatted = inserted[0].to_pending();
+        reformatted.id = String::new();
+        reformatted.text = "150\u{00A0}₽ —  Баланс: 196,01 ₽ ".to_string();
+        assert_eq!(reformatted.compute_id(), inserted[0].id);
+
+        let legacy = inserted[0].to_pending().legacy_key();
+        assert!(inserted[0].to_pending().matches_key(&legacy));
+        assert!(inserted[0].to_pending().matches_key(&inserted[0].id));
+
+        let found = store.find_by_id(&inserted[1].id).unwrap().unwrap();
+        assert_eq!(found.title, "Ситикард");
+    }
+}
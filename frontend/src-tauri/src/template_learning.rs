@@ -0,0 +1,534 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::notifications::PendingNotification;
+use crate::parser::{self, ParsedPayment};
+use crate::parser_rules::{self, NumberFormat, ParserRule, ParserRulesSummary, RuleSet};
+use crate::platform::Platform;
+use crate::store::{NotificationQuery, NotificationStore, STATUS_GROUPED};
+use crate::worker;
+
+/// Число с любыми разделителями: "34 574.90", "14-10-24", "12:34"
+const NUMBER_WILDCARD: &str = r"\d(?:[\d \x{00A0}\x{202F}.,:/\-]*\d)?";
+/// Слово с цифрами, например маска "5***1234"
+const WORD_WILDCARD: &str = r"\S+";
+const MAX_SAMPLES: usize = 5;
+
+// Валюты, которые распознаются рядом с выделенной суммой
+const KNOWN_CURRENCIES: &[&str] = &["RUB", "KZT", "GEL", "BYN", "UZS", "USD", "EUR"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanField {
+    Title,
+    Text,
+}
+
+/// Выделение в заголовке или тексте. Смещения в UTF-16, как `selectionStart`
+/// и `selectionEnd` в JS
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSpan {
+    pub field: SpanField,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateSelection {
+    pub amount: TemplateSpan,
+    /// Без выделения продавцом считается заголовок
+    #[serde(default)]
+    pub merchant: Option<TemplateSpan>,
+    #[serde(default)]
+    pub card: Option<TemplateSpan>,
+    #[serde(default)]
+    pub bank: Option<String>,
+    /// По умолчанию — валюта рядом с суммой, иначе RUB
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateMatch {
+    pub id: String,
+    pub title: String,
+    pub text: String,
+    pub payment: ParsedPayment,
+}
+
+/// Как шаблон ведёт себя на уже сохранённых уведомлениях того же пакета
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateValidation {
+    pub checked: usize,
+    pub matched: usize,
+    /// Совпавшие уведомления, которые текущие правила не разобрали
+    pub newly_parsed: usize,
+    /// Совпавшие уведомления, которые текущие правила разобрали иначе
+    pub conflicts: usize,
+    pub samples: Vec<TemplateMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnedTemplate {
+    pub rule: ParserRule,
+    pub payment: ParsedPayment,
+    pub validation: TemplateValidation,
+    /// Итог перезагрузки правил, если шаблон сохранён
+    pub saved: Option<ParserRulesSummary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Group {
+    Amount,
+    Merchant,
+    Card,
+}
+
+struct Selected {
+    group: Group,
+    field: SpanField,
+    range: Range<usize>,
+}
+
+fn amount_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| Regex::new(r"^\d[\d \x{00A0}\x{202F}.,]*$").expect("invalid amount regex"))
+}
+
+fn number_regex() -> &'static Regex {
+    static CELL: OnceLock<Regex> = OnceLock::new();
+    CELL.get_or_init(|| Regex::new(r"^\d[\d.,:/\-]*$").expect("invalid number regex"))
+}
+
+/// UTF-16 смещение в байтовое; None, если оно не на границе символа
+fn byte_offset(source: &str, utf16_offset: usize) -> Option<usize> {
+    let mut units = 0;
+    for (index, c) in source.char_indices() {
+        if units == utf16_offset {
+            return Some(index);
+        }
+        units += c.len_utf16();
+    }
+    (units == utf16_offset).then_some(source.len())
+}
+
+/// Сужает выделение до нужного: сумму и карту — до цифр, продавца — без пробелов
+fn resolve(source: &str, span: &TemplateSpan, group: Group) -> Result<Range<usize>, String> {
+    let (Some(start), Some(end)) = (byte_offset(source, span.start), byte_offset(source, span.end)) else {
+        return Err(format!("Selection {}..{} is outside the text", span.start, span.end));
+    };
+    if start >= end {
+        return Err(format!("Selection {}..{} is empty", span.start, span.end));
+    }
+
+    let selected = &source[start..end];
+    let trimmed = match group {
+        Group::Merchant => selected.trim(),
+        Group::Amount | Group::Card => selected.trim_matches(|c: char| !c.is_ascii_digit()),
+    };
+    if trimmed.is_empty() {
+        return Err(format!("Selection '{}' has nothing to match", selected));
+    }
+    let start = start + (trimmed.as_ptr() as usize - selected.as_ptr() as usize);
+    let mut range = start..start + trimmed.len();
+
+    if group == Group::Card {
+        // Из маски "5***1234" берём последние четыре цифры
+        let card = &source[range.clone()];
+        let last4 = card.len().checked_sub(4).map(|offset| &card[offset..]);
+        match last4 {
+            Some(digits) if digits.chars().all(|c| c.is_ascii_digit()) => range.start = range.end - 4,
+            _ => return Err(format!("Card selection must end with 4 digits: {}", card)),
+        }
+    }
+    Ok(range)
+}
+
+/// Формат суммы по выделенному примеру и шаблон её дробной части
+fn amount_format(amount: &str) -> Result<(NumberFormat, &'static str), String> {
+    if !amount_regex().is_match(amount) {
+        return Err(format!("Amount selection is not a number: {}", amount));
+    }
+    let decimal = amount
+        .rfind(['.', ','])
+        .filter(|index| (2..=3).contains(&(amount.len() - index)))
+        .map(|index| &amount[index..index + 1]);
+    let integer_part = match decimal {
+        Some(_) => &amount[..amount.rfind(['.', ',']).unwrap_or(amount.len())],
+        None => amount,
+    };
+
+    let named = |name: &str| NumberFormat::Named(name.to_string());
+    Ok(match decimal {
+        Some(",") if integer_part.contains('.') => (
+            NumberFormat::Custom {
+                group: " \u{00A0}\u{202F}.".to_string(),
+                decimal: ",".to_string(),
+            },
+            r"(?:,\d{1,2})?",
+        ),
+        Some(",") => (named("space_comma"), r"(?:,\d{1,2})?"),
+        Some(_) => (named("en"), r"(?:\.\d{1,2})?"),
+        None if integer_part.contains(',') => (named("en"), r"(?:\.\d{1,2})?"),
+        None => (named("ru"), r"(?:[.,]\d{1,2})?"),
+    })
+}
+
+enum Token<'a> {
+    Space,
+    Number,
+    Word,
+    Literal(&'a str),
+}
+
+/// Литерал между выделениями: пробелы — `\s+`, числа и слова с цифрами —
+/// шаблоны, остальное экранируется как есть
+fn generalize_literal(literal: &str) -> String {
+    let mut tokens = Vec::new();
+    let mut rest = literal;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| c.is_whitespace() != rest.starts_with(char::is_whitespace))
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(split);
+        rest = tail;
+
+        if chunk.starts_with(char::is_whitespace) {
+            tokens.push(Token::Space);
+            continue;
+        }
+        let core = chunk.trim_matches(|c: char| !c.is_ascii_digit());
+        if core.is_empty() {
+            tokens.push(Token::Literal(chunk));
+        } else if number_regex().is_match(core) {
+            let offset = core.as_ptr() as usize - chunk.as_ptr() as usize;
+            let (prefix, suffix) = (&chunk[..offset], &chunk[offset + core.len()..]);
+            if !prefix.is_empty() {
+                tokens.push(Token::Literal(prefix));
+            }
+            // "34 574.90": число с пробелом внутри остаётся одним числом
+            if matches!(tokens.as_slice(), [.., Token::Number, Token::Space]) && prefix.is_empty() {
+                tokens.pop();
+            } else {
+                tokens.push(Token::Number);
+            }
+            if !suffix.is_empty() {
+                tokens.push(Token::Literal(suffix));
+            }
+        } else {
+            tokens.push(Token::Word);
+        }
+    }
+
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            Token::Space => r"\s+".to_string(),
+            Token::Number => NUMBER_WILDCARD.to_string(),
+            Token::Word => WORD_WILDCARD.to_string(),
+            Token::Literal(text) => regex::escape(text),
+        })
+        .collect()
+}
+
+/// Шаблон строки с выделенными группами
+fn generalize(source: &str, selected: &[&Selected], amount_decimal: &str) -> String {
+    let mut pattern = String::new();
+    let mut position = 0;
+    for item in selected {
+        pattern.push_str(&generalize_literal(&source[position..item.range.start]));
+        let group = match item.group {
+            Group::Amount => format!("(?P<amount>{{amount}}{})", amount_decimal),
+            // Продавец в конце строки забирает всё до конца
+            Group::Merchant if source[item.range.end..].trim().is_empty() => "(?P<merchant>.+)".to_string(),
+            Group::Merchant => "(?P<merchant>.+?)".to_string(),
+            Group::Card => r"(?P<card>\d{4})".to_string(),
+        };
+        pattern.push_str(&group);
+        position = item.range.end;
+    }
+    pattern.push_str(&generalize_literal(&source[position..]));
+    pattern
+}
+
+/// Валюта в слове сразу до или после суммы
+fn detect_currency(text: &str, amount: &Range<usize>) -> Option<String> {
+    let after = text[amount.end..].split_whitespace().next();
+    let before = text[..amount.start].split_whitespace().last();
+    [after, before].into_iter().flatten().find_map(|word| {
+        let currency = parser::normalize_currency(word.trim_matches(|c: char| c.is_ascii_punctuation() && c != '$'));
+        KNOWN_CURRENCIES.contains(&currency.as_str()).then_some(currency)
+    })
+}
+
+fn unique_id(base: &str, taken: &[String]) -> String {
+    if !taken.iter().any(|id| id == base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}_{}", base, n))
+        .find(|id| !taken.iter().any(|taken| taken == id))
+        .unwrap_or_default()
+}
+
+/// Банк по умолчанию — второй сегмент пакета: "kz.kaspi.mobile" -> "kaspi"
+fn default_bank(package_name: &str) -> String {
+    package_name
+        .split('.')
+        .nth(1)
+        .unwrap_or(package_name)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Правило из уведомления и выделенных пользователем фрагментов
+pub fn build_rule(
+    notification: &PendingNotification,
+    selection: &TemplateSelection,
+    taken_ids: &[String],
+) -> Result<ParserRule, String> {
+    let source = |field: SpanField| match field {
+        SpanField::Title => notification.title.as_str(),
+        SpanField::Text => notification.text.as_str(),
+    };
+
+    let mut selected = Vec::new();
+    for (group, span) in [
+        (Group::Amount, Some(&selection.amount)),
+        (Group::Merchant, selection.merchant.as_ref()),
+        (Group::Card, selection.card.as_ref()),
+    ] {
+        if let Some(span) = span {
+            selected.push(Selected {
+                group,
+                field: span.field,
+                range: resolve(source(span.field), span, group)?,
+            });
+        }
+    }
+    selected.sort_by_key(|item| (item.field == SpanField::Text, item.range.start));
+    if selected
+        .windows(2)
+        .any(|pair| pair[0].field == pair[1].field && pair[0].range.end > pair[1].range.start)
+    {
+        return Err("Selections must not overlap".to_string());
+    }
+
+    let amount = selected
+        .iter()
+        .find(|item| item.group == Group::Amount)
+        .ok_or_else(|| "Amount selection is required".to_string())?;
+    let (number_format, amount_decimal) = amount_format(&source(amount.field)[amount.range.clone()])?;
+    let in_field = |field: SpanField| selected.iter().filter(|item| item.field == field).collect::<Vec<_>>();
+    let (title_spans, text_spans) = (in_field(SpanField::Title), in_field(SpanField::Text));
+
+    let merchant_from_title = selection.merchant.is_none();
+    if merchant_from_title && (notification.title.trim().is_empty() || !title_spans.is_empty()) {
+        return Err("Select the merchant: the title can't be used as one".to_string());
+    }
+    if text_spans.is_empty() {
+        return Err("Select at least one fragment in the notification text".to_string());
+    }
+
+    let currency = match &selection.currency {
+        Some(currency) => parser::normalize_currency(currency),
+        None => detect_currency(source(amount.field), &amount.range).unwrap_or_else(|| "RUB".to_string()),
+    };
+    let bank = selection
+        .bank
+        .as_deref()
+        .map(str::trim)
+        .filter(|bank| !bank.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| default_bank(&notification.package_name));
+
+    Ok(ParserRule {
+        id: unique_id(&format!("learned_{}", bank), taken_ids),
+        bank,
+        packages: vec![notification.package_name.clone()],
+        title_pattern: (!title_spans.is_empty())
+            .then(|| format!("^{}$", generalize(&notification.title, &title_spans, amount_decimal))),
+        allow_empty_title: false,
+        text_pattern: generalize(&notification.text, &text_spans, amount_decimal),
+        merchant: None,
+        merchant_from_title,
+        currency,
+        number_format,
+        case_insensitive: true,
+        enabled: true,
+    })
+}
+
+/// Разбирает исходное уведомление и сохранённые уведомления того же пакета
+pub fn validate(
+    store: &NotificationStore,
+    notification: &PendingNotification,
+    rule: &ParserRule,
+) -> Result<(ParsedPayment, TemplateValidation), String> {
+    let rules = RuleSet::compile(vec![rule.clone()])?;
+    let payment = parser::parse_notification_with(&rules, notification)
+        .ok_or_else(|| "The template does not match the selected notification".to_string())?;
+
+    let similar = store.query(&NotificationQuery {
+        package_name: Some(notification.package_name.clone()),
+        ..Default::default()
+    })?;
+    let mut validation = TemplateValidation {
+        checked: 0,
+        matched: 0,
+        newly_parsed: 0,
+        conflicts: 0,
+        samples: Vec::new(),
+    };
+    for stored in similar.iter().rev().filter(|stored| stored.status != STATUS_GROUPED) {
+        validation.checked += 1;
+        let Some(learned) = parser::parse_notification_with(&rules, &stored.to_pending()) else {
+            continue;
+        };
+        validation.matched += 1;
+        match &stored.parsed {
+            None => validation.newly_parsed += 1,
+            Some(current) if current.amount != learned.amount || current.merchant_name != learned.merchant_name => {
+                validation.conflicts += 1
+            }
+            Some(_) => {}
+        }
+        if validation.samples.len() < MAX_SAMPLES {
+            validation.samples.push(TemplateMatch {
+                id: stored.id.clone(),
+                title: stored.title.clone(),
+                text: stored.text.clone(),
+                payment: learned,
+            });
+        }
+    }
+    Ok((payment, validation))
+}
+
+pub fn learn(
+    store: &NotificationStore,
+    data_dir: &Path,
+    notification: &PendingNotification,
+    selection: &TemplateSelection,
+    save: bool,
+) -> Result<LearnedTemplate, String> {
+    let rule = build_rule(notification, selection, &parser_rules::rule_ids(data_dir)?)?;
+    let (payment, validation) = validate(store, notification, &rule)?;
+    let saved = if save {
+        Some(parser_rules::add_user_rule(data_dir, rule.clone())?)
+    } else {
+        None
+    };
+    Ok(LearnedTemplate {
+        rule,
+        payment,
+        validation,
+        saved,
+    })
+}
+
+/// Строит правило разбора по выделенным сумме, продавцу и карте и проверяет
+/// его на архиве. С `save` дописывает его в parser_rules.toml
+#[tauri::command]
+pub async fn learn_parser_template(
+    platform: tauri::State<'_, Platform>,
+    store: tauri::State<'_, NotificationStore>,
+    notification: PendingNotification,
+    selection: TemplateSelection,
+    save: bool,
+) -> Result<LearnedTemplate, String> {
+    let platform = platform.inner().clone();
+    let store = store.inner().clone();
+    worker::run(move || learn(&store, &platform.files_dir()?, &notification, &selection, save)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn span(field: SpanField, source: &str, fragment: &str) -> TemplateSpan {
+        let start = source.find(fragment).unwrap();
+        TemplateSpan {
+            field,
+            start: source[..start].encode_utf16().count(),
+            end: source[..start + fragment.len()].encode_utf16().count(),
+        }
+    }
+
+    #[test]
+    fn learns_saves_and_reuses_a_template() {
+        let dir = std::env::temp_dir().join(format!("hpio-learning-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let store = NotificationStore::open_in_memory(PathBuf::new()).unwrap();
+        store
+            .import_json(
+                r#"[
+                {"packageName":"com.example.learning","title":"Карта *7001","text":"Списание 1 250,00 ₸ 🛒 Small Астана. Баланс 34 574,90 ₸","timestamp":1700000000000},
+                {"packageName":"com.example.learning","title":"Карта *7002","text":"Списание 90 ₸ 🛒 Yandex Go. Баланс 120,00 ₸","timestamp":1700000100000},
+                {"packageName":"com.example.learning","title":"Карта *7003","text":"Пополнение 500 ₸","timestamp":1700000200000}
+            ]"#,
+            )
+            .unwrap();
+
+        let title = "Карта *4417";
+        let text = "Списание 5 000,50 ₸ 🛒 Magnum Cash&Carry. Баланс 1 000 ₸";
+        let notification = PendingNotification {
+            id: String::new(),
+            package_name: "com.example.learning".to_string(),
+            title: title.to_string(),
+            text: text.to_string(),
+            timestamp: 1700000300000,
+            notification_type: None,
+            notification_key: None,
+            extras: Default::default(),
+        };
+        let selection = TemplateSelection {
+            amount: span(SpanField::Text, text, " 5 000,50 "),
+            merchant: Some(span(SpanField::Text, text, "Magnum Cash&Carry")),
+            card: Some(span(SpanField::Title, title, "*4417")),
+            bank: None,
+            currency: None,
+        };
+
+        // Без сохранения: общий набор правил не меняется под параллельными тестами
+        let learned = learn(&store, &dir, &notification, &selection, false).unwrap();
+        assert!(learned.saved.is_none());
+        assert_eq!(learned.rule.id, "learned_example");
+        assert_eq!(learned.rule.title_pattern.as_deref(), Some(r"^Карта\s+\*(?P<card>\d{4})$"));
+        assert_eq!(
+            (learned.payment.merchant_name.as_str(), learned.payment.amount, learned.payment.currency.as_str()),
+            ("Magnum Cash&Carry", 5000.5, "KZT")
+        );
+        assert_eq!(learned.payment.card_mask.as_deref(), Some("*4417"));
+        assert_eq!((learned.validation.checked, learned.validation.matched), (3, 2));
+        assert_eq!(learned.validation.newly_parsed, 2);
+        assert_eq!(learned.validation.samples[0].payment.merchant_name, "Yandex Go");
+
+        // Сохранённое правило подхватывается при загрузке и не занимает id повторно
+        parser_rules::save_user_rule(&dir, learned.rule.clone()).unwrap();
+        let saved = RuleSet::load(&dir).unwrap();
+        assert!(saved.rules().any(|rule| rule.id == "learned_example"));
+        assert_eq!(
+            saved.parse("com.example.learning", "Карта *7002", "Списание 12 ₸ 🛒 Kiosk. Баланс 1 ₸").unwrap().amount,
+            12.0
+        );
+        let again = build_rule(&notification, &selection, &parser_rules::rule_ids(&dir).unwrap()).unwrap();
+        assert_eq!(again.id, "learned_example_2");
+
+        let no_merchant = TemplateSelection {
+            merchant: None,
+            ..selection
+        };
+        assert!(build_rule(&notification, &no_merchant, &[]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
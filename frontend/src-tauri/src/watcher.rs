@@ -0,0 +1,121 @@
+use serde::Serialize;
+use std::fs;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::drift::{self, PARSER_DRIFT_EVENT};
+use crate::notifications::is_import_candidate;
+use crate::parser;
+use crate::store::{NotificationStore, StoreChange};
+
+pub const PENDING_NOTIFICATION_EVENT: &str = "pending-notification";
+pub const PENDING_COUNT_EVENT: &str = "pending-notification-count";
+
+// Проверка спула — один stat, это дешевле опроса через webview и JNI
+const SPOOL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingCount {
+    pub count: usize,
+}
+
+/// Подписывается на изменения хранилища и следит за спулом слушателя, чтобы
+/// webview получал события `pending-notification` и
+/// `pending-notification-count`, а не опрашивал `get_pending_notifications`
+pub fn start<R: Runtime>(app: AppHandle<R>, store: NotificationStore) -> Result<(), String> {
+    store.set_change_listener(Box::new(move |store, change| {
+        if let StoreChange::Inserted(inserted) = change {
+            let mut parsed_any = false;
+            for stored in inserted {
+                let notification = stored.to_pending();
+                if !is_import_candidate(&notification) {
+                    continue;
+                }
+
+                // Повторы остаются в базе со статусом duplicate и в очередь не попадают,
+                // сгруппированное уведомление даёт событие на каждую операцию
+                let events = match parser::process_stored(store, stored) {
+                    Ok(events) => events,
+                    Err(e) => {
+                        log::error!("Failed to process notification: {}", e);
+                        continue;
+                    }
+                };
+                parsed_any = true;
+                for event in events {
+                    if let Err(e) = app.emit(PENDING_NOTIFICATION_EVENT, event) {
+                        log::error!("Failed to emit pending notification event: {:?}", e);
+                    }
+                }
+            }
+            if parsed_any {
+                emit_drift(&app, store);
+            }
+        }
+
+        emit_count(&app, store);
+    }))?;
+
+    thread::Builder::new()
+        .name("pending-notification-watcher".to_string())
+        .spawn(move || watch_spool(store))
+        .map_err(|e| format!("Failed to start notification watcher: {:?}", e))?;
+
+    Ok(())
+}
+
+fn emit_count<R: Runtime>(app: &AppHandle<R>, store: &NotificationStore) {
+    let count = match store.pending() {
+        Ok(pending) => pending
+            .iter()
+            .filter(|stored| is_import_candidate(&stored.to_pending()))
+            .count(),
+        Err(e) => {
+            log::error!("Failed to count pending notifications: {}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = app.emit(PENDING_COUNT_EVENT, PendingCount { count }) {
+        log::error!("Failed to emit pending count event: {:?}", e);
+    }
+}
+
+/// Тревога о банке, уведомления которого перестали разбираться
+fn emit_drift<R: Runtime>(app: &AppHandle<R>, store: &NotificationStore) {
+    let drifts = match drift::check(store, chrono::Utc::now().timestamp_millis()) {
+        Ok(drifts) => drifts,
+        Err(e) => {
+            log::error!("Failed to check parser drift: {}", e);
+            return;
+        }
+    };
+
+    for drift in drifts {
+        if let Err(e) = app.emit(PARSER_DRIFT_EVENT, drift) {
+            log::error!("Failed to emit parser drift event: {:?}", e);
+        }
+    }
+}
+
+fn watch_spool(store: NotificationStore) {
+    let mut last_modified: Option<SystemTime> = None;
+
+    loop {
+        thread::sleep(SPOOL_POLL_INTERVAL);
+
+        let modified = fs::metadata(store.spool_path())
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_none() || modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        // Новые записи уходят в подписчика через StoreChange::Inserted
+        if let Err(e) = store.drain_spool() {
+            log::error!("Failed to drain pending notifications: {}", e);
+        }
+    }
+}
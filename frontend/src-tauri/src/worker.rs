@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+/// Сколько команда ждёт JNI или диск, прежде чем вернуть ошибку в UI
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Выполняет блокирующую работу (JNI, SQLite, файлы) в пуле потоков, чтобы
+/// не держать поток IPC. Зависший вызов возвращает ошибку по таймауту; сам
+/// поток при этом доработает в фоне
+pub async fn run<T, F>(task: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    run_with_timeout(COMMAND_TIMEOUT, task).await
+}
+
+pub async fn run_with_timeout<T, F>(timeout: Duration, task: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    let handle = tauri::async_runtime::spawn_blocking(task);
+    match tokio::time::timeout(timeout, handle).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(format!("Background task failed: {:?}", e)),
+        Err(_) => Err(format!("Timed out after {} ms", timeout.as_millis())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_result_or_times_out() {
+        tauri::async_runtime::block_on(async {
+            assert_eq!(run(|| Ok(42)).await, Ok(42));
+            assert_eq!(run::<(), _>(|| Err("boom".to_string())).await, Err("boom".to_string()));
+
+            let hung = run_with_timeout(Duration::from_millis(20), || {
+                std::thread::sleep(Duration::from_millis(500));
+                Ok(())
+            })
+            .await;
+            assert_eq!(hung, Err("Timed out after 20 ms".to_string()));
+        });
+    }
+}
@@ -48,7 +48,11 @@ import {
   getPendingNotifications,
   clearPendingNotifications,
   checkNotificationPermission,
+  linkNotificationToPayment,
   PendingNotification,
+  ParsedNotification,
+  ParserDrift,
+  TransactionKind,
 } from './api/notificationPermission';
 import { parseNotification } from './utils/notificationParser';
 import { normalizeNotificationTimestamp } from './utils/dateUtils';
@@ -89,10 +93,20 @@ const AutomationRulesPage = React.lazy(
 );
 
 const NATIVE_NOTIFICATION_EVENT = 'hpio-native-notification';
+// События наблюдателя за очередью в Rust, см. watcher.rs
+const PENDING_NOTIFICATION_EVENT = 'pending-notification';
+const PENDING_COUNT_EVENT = 'pending-notification-count';
+const NON_EXPENSE_KINDS: TransactionKind[] = [
+  'refund',
+  'transfer',
+  'decline',
+  'hold',
+  'top_up',
+];
 
 // --- Helper Components ---
 
-const Navigation: React.FC = () => {
+const Navigation: React.FC<{ pendingCount: number }> = ({ pendingCount }) => {
   const { isAuthenticated, user, logout, token } = useAuth();
   const location = useLocation();
   const {
@@ -142,6 +156,11 @@ const Navigation: React.FC = () => {
               )}
             >
               Платежи
+              {pendingCount > 0 && (
+                <span className="ml-1.5 rounded-full bg-red-500 px-1.5 py-0.5 text-xs font-semibold text-white">
+                  {pendingCount}
+                </span>
+              )}
             </Link>
             {canManageCategories && (
               <Link
@@ -300,10 +319,11 @@ function App() {
   const tagsAndCategoriesEnabled = isTagsAndCategoriesEnabled();
   const tagsEnabled = tagsAndCategoriesEnabled;
   const canManageCategories = Boolean(user?.isAdmin);
+  const [pendingNotificationCount, setPendingNotificationCount] = useState(0);
 
   const mobileNavItems = [
     { to: '/dashboard', label: 'Главная' },
-    { to: '/payments', label: 'Платежи' },
+    { to: '/payments', label: 'Платежи', badge: pendingNotificationCount },
     ...(canManageCategories
       ? [
           { to: '/categories', label: 'Категории' },
@@ -380,7 +400,9 @@ function App() {
   };
 
   // --- Logic: Notification Processing (Core) ---
+  // Rust computes a stable id; the legacy key is only a fallback for older builds
   const createNotificationKey = (notification: PendingNotification): string =>
+    notification.id ||
     `${notification.timestamp}_${notification.package_name}_${notification.text}`;
 
   const saveProcessedKeysToStorage = (keys: Set<string>) => {
@@ -419,7 +441,7 @@ function App() {
         return;
       }
 
-      let notifications: PendingNotification[] = [];
+      let notifications: ParsedNotification[] = [];
 
       if (isActuallyTauri && !shouldBypassPermissionCheck) {
         const { granted } = await checkNotificationPermission();
@@ -427,14 +449,13 @@ function App() {
           logger.warn('Notification permission missing.');
           return;
         }
+        // Разбор, классификация и категория приходят из Rust
         notifications = await getPendingNotifications();
-        notifications = notifications.filter(
-          (n) => !n.notification_type || n.notification_type === 'PAYMENT',
-        );
       }
 
       const unprocessedNotifications = notifications.filter(
-        (n) => !processedNotificationKeys.has(createNotificationKey(n)),
+        (n) =>
+          !processedNotificationKeys.has(createNotificationKey(n.notification)),
       );
 
       if (unprocessedNotifications.length > 0) {
@@ -442,7 +463,8 @@ function App() {
         let autoCreatedCount = 0;
         const successfullyProcessedKeys: string[] = [];
 
-        for (const notification of unprocessedNotifications) {
+        for (const item of unprocessedNotifications) {
+          const { notification } = item;
           const notificationKey = createNotificationKey(notification);
           try {
             logger.info(
@@ -457,11 +479,17 @@ function App() {
               );
             }
 
-            const parsed = parseNotification(
-              notification.package_name,
-              notification.text,
-              notification.title,
-            );
+            // Парсер на TS остаётся запасным вариантом, если Rust не разобрал
+            const parsed = item.payment
+              ? {
+                  merchantName: item.payment.merchant_name,
+                  amount: item.payment.amount,
+                }
+              : parseNotification(
+                  notification.package_name,
+                  notification.text,
+                  notification.title,
+                );
 
             try {
               const combinedText = notification.title
@@ -475,15 +503,23 @@ function App() {
               /* silent fail */
             }
 
-            if (!parsed) {
+            // Возвраты, переводы, отказы и пополнения не импортируются
+            // как платежи; «other» остаётся на запасной разбор
+            const isExpense = !NON_EXPENSE_KINDS.includes(
+              item.classification.kind,
+            );
+
+            if (!parsed || !isExpense) {
               newKeys.add(notificationKey);
               successfullyProcessedKeys.push(notificationKey);
               continue;
             }
 
-            const existingRule = await merchantRuleApi.findRuleByMerchant(
-              parsed.merchantName,
-            );
+            const categoryId =
+              item.category?.category_id ??
+              (await merchantRuleApi.findRuleByMerchant(parsed.merchantName))
+                ?.categoryId ??
+              null;
 
             const today = new Date().toISOString().split('T')[0];
             const completedAt = normalizeNotificationTimestamp(
@@ -495,7 +531,7 @@ function App() {
                 title: parsed.merchantName,
                 amount: parsed.amount,
                 dueDate: today,
-                categoryId: existingRule ? existingRule.categoryId : null,
+                categoryId,
                 createAsCompleted: true,
                 autoCreated: true,
                 notificationTimestamp: notification.timestamp,
@@ -506,8 +542,20 @@ function App() {
                 `Auto-creating payment: ${parsed.merchantName}, ${parsed.amount}, ts=${notification.timestamp}`,
               );
 
-              await axiosInstance.post('/payments', payload);
+              const response = await axiosInstance.post('/payments', payload);
               autoCreatedCount += 1;
+
+              // Связь с платежом нужна архиву уведомлений
+              if (isActuallyTauri && notification.id && response.data?.id) {
+                try {
+                  await linkNotificationToPayment(
+                    notification.id,
+                    String(response.data.id),
+                  );
+                } catch (linkError) {
+                  logger.warn('Failed to link notification to payment', linkError);
+                }
+              }
             } catch (e) {
               logger.error('Auto-create failed', e);
               // We re-throw or handle here. If we want to retry auto-creation,
@@ -584,28 +632,45 @@ function App() {
 
     window.addEventListener(NATIVE_NOTIFICATION_EVENT, handleNativeEvent);
 
-    let unlistenTauri: (() => void) | undefined;
+    let unlistenPending: (() => void) | undefined;
+    let unlistenCount: (() => void) | undefined;
+    let unlistenDrift: (() => void) | undefined;
     if (isTauri()) {
       import('@tauri-apps/api/event').then(async ({ listen }) => {
-        unlistenTauri = await listen('payment-notification-received', () => {
-          logger.info('Tauri event received');
-          processNotificationsRef.current?.();
+        // Rust сам следит за очередью, опрос и visibilitychange не нужны
+        unlistenPending = await listen<ParsedNotification>(
+          PENDING_NOTIFICATION_EVENT,
+          () => {
+            logger.info('Pending notification event received');
+            processNotificationsRef.current?.();
+          },
+        );
+        unlistenCount = await listen<{ count: number }>(
+          PENDING_COUNT_EVENT,
+          (event) => setPendingNotificationCount(event.payload.count),
+        );
+        unlistenDrift = await listen<ParserDrift>('parser-drift', (event) => {
+          const drift = event.payload;
+          logger.warn(
+            `Parser drift for ${drift.package_name}: ${drift.recent_failures}/${drift.recent_total} not parsed`,
+            drift.samples,
+          );
+          showToast(
+            `Уведомления ${drift.bank} перестали распознаваться. Проверьте импорт в диагностике`,
+            'error',
+            8000,
+          );
         });
       });
     }
 
-    const handleVisibility = () => {
-      if (document.visibilityState === 'visible')
-        processNotificationsRef.current?.();
-    };
-    document.addEventListener('visibilitychange', handleVisibility);
-
     return () => {
       window.removeEventListener(NATIVE_NOTIFICATION_EVENT, handleNativeEvent);
-      document.removeEventListener('visibilitychange', handleVisibility);
-      if (unlistenTauri) unlistenTauri();
+      if (unlistenPending) unlistenPending();
+      if (unlistenCount) unlistenCount();
+      if (unlistenDrift) unlistenDrift();
     };
-  }, [isAuthenticated, navigate]);
+  }, [isAuthenticated, navigate, showToast]);
 
   // --- Logic: Handlers ---
   const handleLogoClick = (e: React.MouseEvent<HTMLAnchorElement>) => {
@@ -774,7 +839,7 @@ function App() {
 
       <div className="flex items-center gap-2 sm:gap-4">
         <SyncStatusIndicator />
-        <Navigation />
+        <Navigation pendingCount={pendingNotificationCount} />
 
         <div className="md:hidden flex items-center gap-2">
           {headerRightAction}
@@ -1,4 +1,6 @@
 import axiosInstance from "./axiosInstance";
+import logger from "../utils/logger";
+import { isTauri } from "../utils/platform";
 
 export interface MerchantCategoryRule {
   id: string;
@@ -11,6 +13,8 @@ export interface MerchantCategoryRule {
     name: string;
     builtinIconName?: string;
   };
+  /** Теги, которые получит платёж; кэш на устройстве подставляет название категории */
+  tags?: string[];
 }
 
 export interface CreateMerchantRuleData {
@@ -23,9 +27,59 @@ export interface UnassignedMerchant {
   count: number;
 }
 
+export interface CategorySuggestion {
+  rule_id: string;
+  category_id: string;
+  category_name: string | null;
+  merchant_keyword: string;
+  tags: string[];
+  match_type: "exact" | "keyword";
+}
+
+/**
+ * Сохраняет правила в кэш на устройстве, чтобы категоризация работала без сети
+ */
+async function cacheRulesOnDevice(
+  rules: MerchantCategoryRule[],
+  replace: boolean
+): Promise<void> {
+  if (!isTauri()) return;
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    await invoke("cache_merchant_rules", { rules, replace });
+  } catch (error) {
+    logger.warn("Failed to cache merchant rules on device:", error);
+  }
+}
+
+async function findRuleOnDevice(
+  merchant: string
+): Promise<MerchantCategoryRule | null> {
+  if (!isTauri()) return null;
+  const { invoke } = await import("@tauri-apps/api/core");
+  const suggestion = await invoke<CategorySuggestion | null>(
+    "categorize_merchant",
+    { merchantName: merchant }
+  );
+  if (!suggestion) return null;
+
+  return {
+    id: suggestion.rule_id,
+    categoryId: suggestion.category_id,
+    merchantKeyword: suggestion.merchant_keyword,
+    createdAt: "",
+    updatedAt: "",
+    transactionCategory: suggestion.category_name
+      ? { id: suggestion.category_id, name: suggestion.category_name }
+      : undefined,
+    tags: suggestion.tags,
+  };
+}
+
 export const merchantRuleApi = {
   async getMerchantRules(): Promise<MerchantCategoryRule[]> {
     const response = await axiosInstance.get("/merchant-rules");
+    await cacheRulesOnDevice(response.data, true);
     return response.data;
   },
 
@@ -41,11 +95,16 @@ export const merchantRuleApi = {
       const response = await axiosInstance.get("/merchant-rules/find", {
         params: { merchant },
       });
+      await cacheRulesOnDevice([response.data], false);
       return response.data;
     } catch (error: unknown) {
       if (error.response?.status === 404) {
         return null;
       }
+      // Нет сети: подбираем категорию по правилам, закэшированным на устройстве
+      if (!error.response && isTauri()) {
+        return findRuleOnDevice(merchant);
+      }
       throw error;
     }
   },
@@ -1,16 +1,62 @@
 import logger from "../utils/logger";
 import { isTauri } from "../utils/platform";
+import type { CategorySuggestion } from "./merchantRuleApi";
 
 export interface PermissionStatus {
   granted: boolean;
 }
 
 export interface PendingNotification {
+  id: string;
   package_name: string;
   title: string;
   text: string;
   timestamp: number;
   notification_type?: string;
+  notification_key?: string | null;
+  // Android extras, см. NotificationExtras в notifications.rs
+  post_time?: number | null;
+  channel_id?: string | null;
+  group_key?: string | null;
+  conversation_title?: string | null;
+  big_text?: string | null;
+  sub_text?: string | null;
+  text_lines?: string[];
+  category?: string | null;
+}
+
+export type TransactionKind =
+  | "payment"
+  | "refund"
+  | "transfer"
+  | "decline"
+  | "hold"
+  | "cash_withdrawal"
+  | "top_up"
+  | "other";
+
+export interface Classification {
+  kind: TransactionKind;
+  confidence: number;
+  auto_import: boolean;
+  signals: {
+    rule: string;
+    kind: TransactionKind;
+    weight: number;
+    matched: string | null;
+  }[];
+  explanation: string;
+}
+
+/**
+ * Уведомление из очереди с результатом разбора на Rust, см. ParsedNotification
+ * в parser.rs
+ */
+export interface ParsedNotification {
+  notification: PendingNotification;
+  payment: ParsedPayment | null;
+  classification: Classification;
+  category: CategorySuggestion | null;
 }
 
 interface NativeServiceStatus {
@@ -56,10 +102,10 @@ export async function openNotificationSettings(): Promise<void> {
 }
 
 /**
- * Получает список ожидающих уведомлений (Android only)
+ * Получает ожидающие уведомления, уже разобранные и классифицированные на Rust
  */
 export async function getPendingNotifications(): Promise<
-  PendingNotification[]
+  ParsedNotification[]
 > {
   // Only attempt to call Tauri APIs if actually running in Tauri
   if (!isTauri()) {
@@ -68,7 +114,7 @@ export async function getPendingNotifications(): Promise<
 
   try {
     const { invoke } = await import("@tauri-apps/api/core");
-    return await invoke<PendingNotification[]>("get_pending_notifications");
+    return await invoke<ParsedNotification[]>("get_pending_notifications");
   } catch (error) {
     logger.error("Failed to get pending notifications:", error);
     return [];
@@ -271,3 +317,445 @@ export async function getDeviceManufacturer(): Promise<string> {
     return "";
   }
 }
+
+export interface QuarantinedNotification {
+  id: number;
+  source: string;
+  payload: unknown;
+  error: string;
+  schema_version: number | null;
+  quarantined_at: number;
+}
+
+/**
+ * Записи слушателя, отброшенные при импорте (не прошли проверку схемы)
+ */
+export async function getQuarantinedNotifications(
+  limit?: number
+): Promise<QuarantinedNotification[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<QuarantinedNotification[]>(
+      "list_quarantined_notifications",
+      { limit }
+    );
+  } catch (error) {
+    logger.error("Failed to get quarantined notifications:", error);
+    return [];
+  }
+}
+
+/**
+ * Выгрузка карантина одним JSON-документом
+ */
+export async function exportQuarantinedNotifications(): Promise<string> {
+  if (!isTauri()) {
+    throw new Error("Quarantine export only available in Tauri environment");
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<string>("export_quarantined_notifications");
+  } catch (error) {
+    logger.error("Failed to export quarantined notifications:", error);
+    throw error;
+  }
+}
+
+export interface SeenPackage {
+  package_name: string;
+  app_name: string | null;
+  last_seen: number;
+  count: number;
+}
+
+/**
+ * Пакеты, уведомления которых слушатель сохраняет
+ */
+export async function getMonitoredPackages(): Promise<string[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<string[]>("get_monitored_packages");
+  } catch (error) {
+    logger.error("Failed to get monitored packages:", error);
+    return [];
+  }
+}
+
+/**
+ * Добавляет пакет в список; возвращает обновлённый список
+ */
+export async function addMonitoredPackage(
+  packageName: string
+): Promise<string[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<string[]>("add_monitored_package", { packageName });
+}
+
+/**
+ * Убирает пакет из списка; возвращает обновлённый список
+ */
+export async function removeMonitoredPackage(
+  packageName: string
+): Promise<string[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<string[]>("remove_monitored_package", { packageName });
+}
+
+/**
+ * Приложения, чьи уведомления слушатель видел, но не сохранял
+ */
+export async function getSeenPackages(): Promise<SeenPackage[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<SeenPackage[]>("get_seen_packages");
+  } catch (error) {
+    logger.error("Failed to get seen packages:", error);
+    return [];
+  }
+}
+
+export type ArchiveOutcome = "imported" | "dismissed" | "duplicate";
+
+export interface ArchivedNotification extends PendingNotification {
+  row_id: number;
+  status: string;
+  payment_id: string | null;
+  outcome: ArchiveOutcome | null;
+  archived_at: number | null;
+  duplicate_of: string | null;
+  duplicate_reason: string | null;
+  parent_id: string | null;
+}
+
+export interface ArchiveRetention {
+  max_age_days: number | null;
+  max_entries: number | null;
+}
+
+/**
+ * Связывает уведомление с созданным платежом; уведомление уходит в архив
+ * как импортированное
+ */
+export async function linkNotificationToPayment(
+  id: string,
+  paymentId: string
+): Promise<void> {
+  if (!isTauri()) {
+    return;
+  }
+
+  const { invoke } = await import("@tauri-apps/api/core");
+  await invoke("link_notification_to_payment", { id, paymentId });
+}
+
+/**
+ * Обработанные уведомления, свежие первыми
+ */
+export async function getArchivedNotifications(
+  outcome?: ArchiveOutcome,
+  limit?: number,
+  offset?: number
+): Promise<ArchivedNotification[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<ArchivedNotification[]>("query_archive", {
+      outcome,
+      limit,
+      offset,
+    });
+  } catch (error) {
+    logger.error("Failed to get archived notifications:", error);
+    return [];
+  }
+}
+
+/**
+ * Возвращает отклонённое уведомление в очередь
+ */
+export async function undoNotificationDismissal(id: string): Promise<boolean> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<boolean>("undo_dismissal", { id });
+}
+
+export async function getArchiveRetention(): Promise<ArchiveRetention | null> {
+  if (!isTauri()) {
+    return null;
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<ArchiveRetention>("get_archive_retention");
+  } catch (error) {
+    logger.error("Failed to get archive retention:", error);
+    return null;
+  }
+}
+
+/**
+ * Сохраняет настройки хранения; возвращает число удалённых записей
+ */
+export async function setArchiveRetention(
+  config: ArchiveRetention
+): Promise<number> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<number>("set_archive_retention", { config });
+}
+
+export interface ImportRecommendation {
+  id: string;
+  priority: number;
+  message: string;
+}
+
+export interface ParserDrift {
+  package_name: string;
+  bank: string;
+  baseline_rate: number;
+  recent_rate: number;
+  baseline_total: number;
+  recent_total: number;
+  recent_failures: number;
+  /** Неразобранные уведомления без кодов и номеров */
+  samples: string[];
+  detected_at: number;
+}
+
+export interface ImportDiagnostics {
+  generated_at: number;
+  platform: string;
+  listener_permission: boolean | null;
+  app_notification_permission: boolean | null;
+  battery_optimization_disabled: boolean | null;
+  autostart_enabled: boolean | null;
+  manufacturer: string | null;
+  last_heartbeat: number | null;
+  heartbeat_history: {
+    events: { timestamp: number; reason: string }[];
+    restarts: number;
+    unclean_restarts: number;
+    gaps: {
+      kind: "disconnected" | "stopped" | "killed";
+      from: number;
+      to: number;
+      duration_ms: number;
+    }[];
+  };
+  pending_count: number | null;
+  last_parse_failure: {
+    id: string;
+    package_name: string;
+    title: string;
+    text: string;
+    timestamp: number;
+  } | null;
+  parser_drift: ParserDrift[] | null;
+  recommendations: ImportRecommendation[];
+  errors: string[];
+}
+
+/**
+ * Состояние импорта одним вызовом: разрешения, слушатель, очередь,
+ * последняя ошибка разбора и рекомендации по важности
+ */
+export async function getImportDiagnostics(): Promise<ImportDiagnostics | null> {
+  if (!isTauri()) {
+    return null;
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<ImportDiagnostics>("get_import_diagnostics");
+  } catch (error) {
+    logger.error("Failed to get import diagnostics:", error);
+    return null;
+  }
+}
+
+/**
+ * Банки, у которых за последние дни резко упала доля разобранных уведомлений
+ */
+export async function getParserDrift(): Promise<ParserDrift[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<ParserDrift[]>("get_parser_drift");
+  } catch (error) {
+    logger.error("Failed to get parser drift:", error);
+    return [];
+  }
+}
+
+export interface SimulationTemplate {
+  id: string;
+  bank: string;
+  package_name: string;
+}
+
+export interface SimulationRequest {
+  template: string;
+  amount: number;
+  merchant: string;
+  timestamp?: number;
+  card_last4?: string;
+}
+
+/**
+ * Шаблоны банков для симулятора уведомлений
+ */
+export async function getSimulationTemplates(): Promise<SimulationTemplate[]> {
+  if (!isTauri()) {
+    return [];
+  }
+
+  try {
+    const { invoke } = await import("@tauri-apps/api/core");
+    return await invoke<SimulationTemplate[]>("list_simulation_templates");
+  } catch (error) {
+    logger.error("Failed to get simulation templates:", error);
+    return [];
+  }
+}
+
+/**
+ * Кладёт синтетическое уведомление в очередь на любой платформе; дальше
+ * оно проходит обычный разбор и импорт
+ */
+export async function simulateNotification(
+  request: SimulationRequest
+): Promise<ArchivedNotification> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<ArchivedNotification>("simulate_notification", {
+    request,
+  });
+}
+
+export interface PaymentDraft {
+  merchant_name: string;
+  amount: number;
+  currency: string;
+  bank: string;
+  card_mask: string | null;
+  category: CategorySuggestion | null;
+  source_text: string;
+  line: number;
+}
+
+/**
+ * Черновики платежей из вставленного текста SMS или письма. Несколько
+ * операций в тексте дают несколько черновиков
+ */
+export async function parsePastedPayments(
+  text: string,
+  packageName?: string
+): Promise<PaymentDraft[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<PaymentDraft[]>("parse_pasted_payments", {
+    text,
+    packageName,
+  });
+}
+
+/**
+ * То же для текста из буфера обмена
+ */
+export async function importClipboardPayments(
+  packageName?: string
+): Promise<PaymentDraft[]> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<PaymentDraft[]>("import_clipboard_payments", {
+    packageName,
+  });
+}
+
+/**
+ * Выделение в заголовке или тексте уведомления; смещения как у
+ * selectionStart/selectionEnd
+ */
+export interface TemplateSpan {
+  field: "title" | "text";
+  start: number;
+  end: number;
+}
+
+export interface TemplateSelection {
+  amount: TemplateSpan;
+  merchant?: TemplateSpan;
+  card?: TemplateSpan;
+  bank?: string;
+  currency?: string;
+}
+
+export interface LearnedParserRule {
+  id: string;
+  bank: string;
+  packages: string[];
+  title_pattern: string | null;
+  text_pattern: string;
+  merchant_from_title: boolean;
+  currency: string;
+  number_format: string | { group: string; decimal: string };
+}
+
+export interface ParsedPayment {
+  merchant_name: string;
+  amount: number;
+  currency: string;
+  bank: string;
+  card_mask: string | null;
+}
+
+export interface LearnedTemplate {
+  rule: LearnedParserRule;
+  payment: ParsedPayment;
+  validation: {
+    checked: number;
+    matched: number;
+    newly_parsed: number;
+    conflicts: number;
+    samples: {
+      id: string;
+      title: string;
+      text: string;
+      payment: ParsedPayment;
+    }[];
+  };
+  saved: { total: number; user_rules: number; source: string | null } | null;
+}
+
+/**
+ * Строит правило разбора по выделенным фрагментам неразобранного
+ * уведомления и проверяет его на архиве. С save=true правило сохраняется
+ * в пользовательские правила и сразу применяется
+ */
+export async function learnParserTemplate(
+  notification: PendingNotification,
+  selection: TemplateSelection,
+  save: boolean
+): Promise<LearnedTemplate> {
+  const { invoke } = await import("@tauri-apps/api/core");
+  return await invoke<LearnedTemplate>("learn_parser_template", {
+    notification,
+    selection,
+    save,
+  });
+}
@@ -17,6 +17,7 @@ import { useAvatarCache } from "../hooks/useAvatarCache";
 type NavItem = {
   to: string;
   label: string;
+  badge?: number;
 };
 
 type DragMode = "opening" | "closing";
@@ -417,6 +418,11 @@ const MobileNavigationDrawer: React.FC<MobileNavigationDrawerProps> = ({
                   </span>
                 )}
                 {item.label}
+                {item.badge ? (
+                  <span className="ml-auto rounded-full bg-red-500 px-2 py-0.5 text-xs font-semibold text-white">
+                    {item.badge}
+                  </span>
+                ) : null}
               </Link>
             );
           })}
@@ -1,10 +1,10 @@
-import React, { useState } from "react";
+import React, { useEffect, useState } from "react";
 import ToggleSwitch from "../ToggleSwitch";
 import Select from "../Select";
 import SettingsSection from "../SettingsSection";
 import { CodeBracketIcon } from "@heroicons/react/24/outline";
 import { useToast } from "../../context/ToastContext";
-import { isTauriMobile } from "../../utils/platform";
+import { isTauri, isTauriMobile } from "../../utils/platform";
 import {
   readLogFile,
   clearLogFile,
@@ -17,6 +17,12 @@ import ConfirmModal from "../ConfirmModal";
 import userApi from "../../api/userApi";
 import ParticleNotification from "../ParticleNotification";
 import { getFeatureFlags, setFeatureFlag } from "../../utils/featureFlags";
+import { Input } from "../Input";
+import {
+  getSimulationTemplates,
+  simulateNotification,
+  SimulationTemplate,
+} from "../../api/notificationPermission";
 
 const DeveloperSection: React.FC = () => {
   const { showToast } = useToast();
@@ -45,6 +51,25 @@ const DeveloperSection: React.FC = () => {
   const [isClearingLogs, setIsClearingLogs] = useState(false);
   const [isCopyingLogs, setIsCopyingLogs] = useState(false);
   const [isSendingTestEmail, setIsSendingTestEmail] = useState(false);
+  const [simulationTemplates, setSimulationTemplates] = useState<
+    SimulationTemplate[]
+  >([]);
+  const [simulationTemplate, setSimulationTemplate] = useState<string | null>(
+    null
+  );
+  const [simulationAmount, setSimulationAmount] = useState("689.68");
+  const [simulationMerchant, setSimulationMerchant] = useState("Пятерочка");
+  const [simulationTime, setSimulationTime] = useState("");
+  const [isInjecting, setIsInjecting] = useState(false);
+
+  useEffect(() => {
+    getSimulationTemplates().then((templates) => {
+      setSimulationTemplates(templates);
+      if (templates.length > 0) {
+        setSimulationTemplate(templates[0].id);
+      }
+    });
+  }, []);
 
   const handleMobileOverrideChange = (value: string | null) => {
     const newValue = value || "-";
@@ -122,6 +147,32 @@ const DeveloperSection: React.FC = () => {
     }
   };
 
+  const handleInjectNotification = async () => {
+    const amount = Number(simulationAmount.replace(",", "."));
+    if (!simulationTemplate || !(amount > 0) || !simulationMerchant.trim()) {
+      showToast("Укажите шаблон, сумму и продавца", "error");
+      return;
+    }
+
+    setIsInjecting(true);
+    try {
+      await simulateNotification({
+        template: simulationTemplate,
+        amount,
+        merchant: simulationMerchant,
+        timestamp: simulationTime
+          ? new Date(simulationTime).getTime()
+          : undefined,
+      });
+      showToast("Уведомление добавлено в очередь", "success");
+    } catch (error) {
+      logger.error("Failed to inject simulated notification:", error);
+      showToast(`Не удалось добавить уведомление: ${error}`, "error");
+    } finally {
+      setIsInjecting(false);
+    }
+  };
+
   const handleSendTestEmail = async () => {
     setIsSendingTestEmail(true);
     try {
@@ -364,7 +415,7 @@ const DeveloperSection: React.FC = () => {
         </div>
 
         {/* Notification Simulation Section */}
-        {isTauriMobile() && (
+        {isTauri() && (
           <div className="mt-6 pt-6 border-t border-gray-100 dark:border-gray-800">
             <h4 className="text-lg font-medium text-gray-900 dark:text-white mb-4">
               Симуляция уведомлений
@@ -372,17 +423,75 @@ const DeveloperSection: React.FC = () => {
             <div className="space-y-4">
               <div className="p-4 bg-yellow-50 dark:bg-yellow-900/20 border border-yellow-100 dark:border-yellow-900/30 rounded-lg">
                 <p className="text-sm text-yellow-800 dark:text-yellow-200">
-                  <strong>Тестирование:</strong> Симулирует получение
-                  уведомления от Райффайзен банка для проверки автоматической
-                  обработки платежей.
+                  <strong>Тестирование:</strong> Добавляет уведомление банка
+                  прямо в очередь. Оно проходит тот же разбор и импорт, что и
+                  настоящее, на любой платформе.
                 </p>
               </div>
-              <button
-                onClick={handleSimulateNotification}
-                className="px-4 py-2 text-sm font-medium text-white bg-indigo-600 hover:bg-indigo-700 rounded-md transition-colors"
-              >
-                Симулировать уведомление Райффайзен
-              </button>
+              <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                    Банк
+                  </label>
+                  <Select
+                    options={simulationTemplates.map((template) => ({
+                      label: template.bank,
+                      value: template.id,
+                    }))}
+                    value={simulationTemplate}
+                    onChange={setSimulationTemplate}
+                  />
+                </div>
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                    Сумма
+                  </label>
+                  <Input
+                    inputMode="decimal"
+                    value={simulationAmount}
+                    onChange={(e) => setSimulationAmount(e.target.value)}
+                  />
+                </div>
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                    Продавец
+                  </label>
+                  <Input
+                    value={simulationMerchant}
+                    onChange={(e) => setSimulationMerchant(e.target.value)}
+                  />
+                </div>
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1">
+                    Время
+                  </label>
+                  <Input
+                    type="datetime-local"
+                    value={simulationTime}
+                    onChange={(e) => setSimulationTime(e.target.value)}
+                  />
+                  <p className="text-xs text-gray-500 dark:text-gray-400 mt-1">
+                    Пусто — текущее время
+                  </p>
+                </div>
+              </div>
+              <div className="flex flex-wrap gap-3">
+                <button
+                  onClick={handleInjectNotification}
+                  disabled={isInjecting}
+                  className="px-4 py-2 text-sm font-medium text-white bg-indigo-600 hover:bg-indigo-700 rounded-md disabled:bg-indigo-400 disabled:cursor-not-allowed transition-colors"
+                >
+                  {isInjecting ? "Добавление..." : "Добавить в очередь"}
+                </button>
+                {isTauriMobile() && (
+                  <button
+                    onClick={handleSimulateNotification}
+                    className="px-4 py-2 text-sm font-medium text-indigo-700 bg-indigo-50 hover:bg-indigo-100 dark:text-indigo-300 dark:bg-indigo-900/30 rounded-md transition-colors"
+                  >
+                    Системное уведомление Райффайзен
+                  </button>
+                )}
+              </div>
             </div>
           </div>
         )}